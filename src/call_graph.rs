@@ -0,0 +1,346 @@
+//! The static call graph: a node per function and method, nested in the containers of the module it is defined in, and a `Calls` edge wherever a call in its body resolves to a function of the crate. Calls that can't be resolved that way (into other crates, or through a trait that several types implement) get an `UnresolvedCall` edge to a placeholder node named after what was called. Focused on a single function, it shows that function, everything it ends up calling and whatever calls it directly.
+//!
+//! Resolution goes by names only, without type information: paths are matched against where functions are defined (and failing that against the one function of that name, which covers most `use`s), calls on `self` go to the methods of the type being implemented, and other method calls go to the crate's only method of that name. Method calls with a name the crate doesn't define are almost always into the standard library, so their placeholders can be left out with `external_methods` turned off.
+
+use std::collections::{HashMap, HashSet};
+
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use syn::visit::{self, Visit};
+
+use crate::model::{self, EdgeKind, GraphModel, NodeKind};
+use crate::rust_source::{for_each_item, type_name, ParsedFile, ViewBuilder};
+
+/// A function or method defined in the sources.
+struct Function<'a> {
+    /// Its module path followed by the type or trait it belongs to, if any, and its name.
+    path: Vec<String>,
+    module: Vec<String>,
+    owner: Option<String>,
+    name: String,
+    body: &'a syn::Block,
+    node: NodeIndex,
+}
+
+/// The calls made in a function body, as written.
+#[derive(Default)]
+struct Calls {
+    paths: Vec<Vec<String>>,
+    /// Method names, and whether they were called on `self`.
+    methods: Vec<(String, bool)>,
+}
+
+impl<'ast> Visit<'ast> for Calls {
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(path) = &*call.func {
+            if path.qself.is_none() {
+                self.paths
+                    .push(path.path.segments.iter().map(|segment| segment.ident.to_string()).collect());
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        let on_self = matches!(&*call.receiver, syn::Expr::Path(path) if path.path.is_ident("self"));
+        self.methods.push((call.method.to_string(), on_self));
+        visit::visit_expr_method_call(self, call);
+    }
+
+    // Functions defined inside of a body are functions of their own, and aren't called just by being defined.
+    fn visit_item_fn(&mut self, _: &'ast syn::ItemFn) {}
+}
+
+fn starts_uppercase(name: &str) -> bool {
+    name.chars().next().map_or(false, char::is_uppercase)
+}
+
+pub fn build(files: &[ParsedFile], focus: Option<&str>, external_methods: bool) -> GraphModel {
+    let mut view = ViewBuilder::default();
+    let mut functions: Vec<Function> = Vec::new();
+
+    for file in files {
+        for_each_item(file, |module, item| {
+            let mut define = |owner: Option<String>, ident: &syn::Ident, body| {
+                let name = ident.to_string();
+                let mut path = module.to_vec();
+                path.extend(owner.clone());
+                path.push(name.clone());
+                let label = match &owner {
+                    Some(owner) => format!("{}::{}", owner, name),
+                    None => name.clone(),
+                };
+                let container = view.module(&file.krate, module);
+                let node = view.node(&format!("fn {}::{}", file.krate, path.join("::")), &label, NodeKind::Function);
+                model::set_parent(&mut view.graph, node, Some(container));
+                view.locate(node, file.location(ident));
+                functions.push(Function {
+                    path,
+                    module: module.to_vec(),
+                    owner,
+                    name,
+                    body,
+                    node,
+                });
+            };
+
+            match item {
+                syn::Item::Fn(item) => define(None, &item.sig.ident, &item.block),
+                syn::Item::Impl(item) => {
+                    let owner = type_name(&item.self_ty);
+                    for impl_item in &item.items {
+                        if let syn::ImplItem::Method(method) = impl_item {
+                            define(Some(owner.clone()), &method.sig.ident, &method.block);
+                        }
+                    }
+                }
+                syn::Item::Trait(item) => {
+                    for trait_item in &item.items {
+                        if let syn::TraitItem::Method(syn::TraitItemMethod {
+                            sig,
+                            default: Some(block),
+                            ..
+                        }) = trait_item
+                        {
+                            define(Some(item.ident.to_string()), &sig.ident, block);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        });
+    }
+
+    let mut by_path: HashMap<&[String], usize> = HashMap::new();
+    // Paths as written are often only the end of where the function is defined (`Type::new`, `module::function`), so functions can also be looked up by the last two parts of their path.
+    let mut by_tail: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+    let mut free_by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut methods_by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (position, function) in functions.iter().enumerate() {
+        by_path.insert(&function.path, position);
+        if let [.., parent, name] = function.path.as_slice() {
+            by_tail.entry((parent.as_str(), name.as_str())).or_insert_with(Vec::new).push(position);
+        }
+        let by_name = match function.owner {
+            Some(_) => &mut methods_by_name,
+            None => &mut free_by_name,
+        };
+        by_name.entry(function.name.as_str()).or_insert_with(Vec::new).push(position);
+    }
+    let only = |candidates: Option<&Vec<usize>>| match candidates.map(Vec::as_slice) {
+        Some([only]) => Some(*only),
+        _ => None,
+    };
+
+    for function in &functions {
+        let mut calls = Calls::default();
+        calls.visit_block(function.body);
+
+        for written in calls.paths {
+            let mut path: Vec<String> = written
+                .iter()
+                .filter(|segment| !matches!(segment.as_str(), "crate" | "self" | "super"))
+                .cloned()
+                .collect();
+            if path.first().map(String::as_str) == Some("Self") {
+                match &function.owner {
+                    Some(owner) => path[0] = owner.clone(),
+                    None => continue,
+                }
+            }
+            let name = match path.last() {
+                Some(name) => name.as_str(),
+                None => continue,
+            };
+            // Tuple structs and enum variants are called like functions, but they are constructors.
+            if starts_uppercase(name) {
+                continue;
+            }
+
+            let mut local = function.module.clone();
+            local.extend(path.iter().cloned());
+            let ending = match path.as_slice() {
+                [.., parent, name] => by_tail.get(&(parent.as_str(), name.as_str())).and_then(|candidates| {
+                    candidates
+                        .iter()
+                        .copied()
+                        .find(|candidate| functions[*candidate].path.ends_with(&path))
+                }),
+                _ => None,
+            };
+            let resolved = by_path
+                .get(local.as_slice())
+                .copied()
+                .or(ending)
+                .or_else(|| match path.len() {
+                    1 => only(free_by_name.get(name)),
+                    _ => None,
+                });
+
+            match resolved {
+                Some(callee) => view.edge(function.node, functions[callee].node, EdgeKind::Calls, ""),
+                None => {
+                    let label = written.join("::");
+                    let placeholder = view.node(&format!("unresolved {}", label), &label, NodeKind::Function);
+                    view.edge(function.node, placeholder, EdgeKind::UnresolvedCall, "");
+                }
+            }
+        }
+
+        for (name, on_self) in calls.methods {
+            let own = match (&function.owner, on_self) {
+                (Some(owner), true) => only(by_tail.get(&(owner.as_str(), name.as_str()))),
+                _ => None,
+            };
+            let candidates = methods_by_name.get(name.as_str());
+            match own.or_else(|| only(candidates)) {
+                Some(callee) => view.edge(function.node, functions[callee].node, EdgeKind::Calls, ""),
+                // Either several types have a method of this name, so which one is called depends on the receiver, or it is defined elsewhere.
+                None if candidates.is_some() || external_methods => {
+                    let label = format!(".{}()", name);
+                    let placeholder = view.node(&format!("unresolved {}", label), &label, NodeKind::Function);
+                    view.edge(function.node, placeholder, EdgeKind::UnresolvedCall, "");
+                }
+                None => {}
+            }
+        }
+    }
+
+    let mut graph = view.graph;
+    if let Some(focus) = focus {
+        let keep = match graph
+            .node_indices()
+            .find(|index| graph[*index].kind == NodeKind::Function && graph[*index].label == focus)
+        {
+            Some(start) => focused(&graph, start),
+            None => HashSet::new(),
+        };
+        graph.retain_nodes(|_, index| keep.contains(&index));
+    }
+    graph
+}
+
+/// `start`, everything it calls directly or through other functions, its direct callers, and the modules all of those are in.
+fn focused(graph: &GraphModel, start: NodeIndex) -> HashSet<NodeIndex> {
+    let mut keep = HashSet::new();
+    let mut pending = vec![start];
+    while let Some(index) = pending.pop() {
+        if keep.insert(index) {
+            pending.extend(graph.edges(index).map(|edge| edge.target()));
+        }
+    }
+    keep.extend(graph.edges_directed(start, Direction::Incoming).map(|edge| edge.source()));
+
+    let containers: Vec<NodeIndex> = keep.iter().flat_map(|index| model::ancestors(graph, *index)).collect();
+    keep.extend(containers);
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::visit::IntoEdgeReferences;
+
+    use super::*;
+    use crate::rust_source::parse_sources;
+
+    const MAIN: &str = "
+        mod draw;
+
+        struct Graph;
+
+        impl Graph {
+            fn new() -> Self {
+                Graph
+            }
+
+            fn redraw(&self) {
+                self.clear();
+                draw::draw_node();
+            }
+
+            fn clear(&self) {}
+        }
+
+        fn main() {
+            App::build().add_plugins(DefaultPlugins).add_system(enact_interaction.system()).run();
+        }
+
+        fn enact_interaction() {
+            draw_node();
+            draw::draw_edge();
+            let graph = Graph::new();
+            graph.redraw();
+            helpers::missing();
+        }
+    ";
+
+    const DRAW: &str = "
+        pub fn draw_node() {}
+
+        pub fn draw_edge() {
+            crate::Graph::new();
+        }
+    ";
+
+    /// The edges of the call graph of `MAIN` and `DRAW`, as the keys of their ends and their kind.
+    fn calls(external_methods: bool) -> HashSet<(String, String, EdgeKind)> {
+        let files = parse_sources(&[("app/src/main.rs", MAIN), ("app/src/draw.rs", DRAW)]);
+        let graph = build(&files, None, external_methods);
+        let key = |index: NodeIndex| graph[index].properties[model::KEY_PROPERTY].clone();
+        graph
+            .edge_references()
+            .map(|edge| (key(edge.source()), key(edge.target()), edge.weight().kind))
+            .collect()
+    }
+
+    fn call(caller: &str, callee: &str, kind: EdgeKind) -> (String, String, EdgeKind) {
+        (caller.to_string(), callee.to_string(), kind)
+    }
+
+    #[test]
+    fn calls_resolve_by_path_tail_and_name() {
+        let calls = calls(false);
+        let expected = [
+            // The path as written from the caller's module.
+            call("fn app::enact_interaction", "fn app::draw::draw_edge", EdgeKind::Calls),
+            call("fn app::enact_interaction", "fn app::Graph::new", EdgeKind::Calls),
+            call("fn app::Graph::redraw", "fn app::draw::draw_node", EdgeKind::Calls),
+            // The end of where the function is defined.
+            call("fn app::draw::draw_edge", "fn app::Graph::new", EdgeKind::Calls),
+            // The only function, or method, of that name.
+            call("fn app::enact_interaction", "fn app::draw::draw_node", EdgeKind::Calls),
+            call("fn app::enact_interaction", "fn app::Graph::redraw", EdgeKind::Calls),
+            // A method of the type being implemented.
+            call("fn app::Graph::redraw", "fn app::Graph::clear", EdgeKind::Calls),
+            // Placeholders for what isn't in the crate.
+            call("fn app::main", "unresolved App::build", EdgeKind::UnresolvedCall),
+            call("fn app::enact_interaction", "unresolved helpers::missing", EdgeKind::UnresolvedCall),
+        ];
+        assert_eq!(calls, expected.iter().cloned().collect());
+    }
+
+    #[test]
+    fn external_method_calls_are_placeholders() {
+        let calls = calls(true);
+        for method in &[".add_plugins()", ".add_system()", ".system()", ".run()"] {
+            let placeholder = format!("unresolved {}", method);
+            assert!(calls.contains(&call("fn app::main", &placeholder, EdgeKind::UnresolvedCall)), "{}", method);
+        }
+        assert!(calls.contains(&call("fn app::main", "unresolved App::build", EdgeKind::UnresolvedCall)));
+        assert!(calls.contains(&call("fn app::enact_interaction", "fn app::Graph::redraw", EdgeKind::Calls)));
+    }
+
+    #[test]
+    fn focus_follows_the_calls() {
+        let files = parse_sources(&[("app/src/main.rs", MAIN), ("app/src/draw.rs", DRAW)]);
+        let graph = build(&files, Some("Graph::redraw"), false);
+        let mut labels: Vec<&str> = graph
+            .node_weights()
+            .filter(|node| node.kind == NodeKind::Function)
+            .map(|node| node.label.as_str())
+            .collect();
+        labels.sort();
+        assert_eq!(labels, vec!["Graph::clear", "Graph::redraw", "draw_node", "enact_interaction"]);
+    }
+}
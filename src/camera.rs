@@ -0,0 +1,116 @@
+//! Panning (middle or right mouse drag) and zooming (scroll wheel) the canvas. `CanvasView` is the source of truth for what part of the canvas is on screen; the camera is moved to match it whenever it changes.
+
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+
+/// How far out and in the canvas can be zoomed, as canvas units per screen pixel.
+const MIN_SCALE: f32 = 0.05;
+const MAX_SCALE: f32 = 50.0;
+
+/// Tag for the camera looking at the canvas.
+pub struct MainCamera;
+
+/// The part of the canvas that is on screen: the canvas point in the middle of the window, and how many canvas units one screen pixel covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanvasView {
+    pub translation: Vec2,
+    pub scale: f32,
+}
+
+impl Default for CanvasView {
+    fn default() -> Self {
+        CanvasView {
+            translation: Vec2::ZERO,
+            scale: 1.0,
+        }
+    }
+}
+
+impl CanvasView {
+    /// Converts a window position (origin at the bottom left, as bevy reports the cursor) to canvas coordinates.
+    pub fn screen_to_canvas(&self, window_size: Vec2, screen: Vec2) -> Vec2 {
+        self.translation + (screen - window_size / 2.0) * self.scale
+    }
+
+    /// The (min, max) corners of the canvas area that is on screen.
+    pub fn visible_rect(&self, window_size: Vec2) -> (Vec2, Vec2) {
+        let half = window_size / 2.0 * self.scale;
+        (self.translation - half, self.translation + half)
+    }
+}
+
+pub fn window_size(window: &Window) -> Vec2 {
+    Vec2::new(window.width(), window.height())
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(CanvasView::default())
+            .add_system(pan_and_zoom.system())
+            .add_system(sync_camera.system());
+    }
+}
+
+fn pan_and_zoom(
+    windows: Res<Windows>,
+    egui_context: ResMut<EguiContext>,
+    buttons: Res<Input<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    mut view: ResMut<CanvasView>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    if egui_context.ctx().is_pointer_over_area() {
+        return;
+    }
+
+    let mut next = view.clone();
+
+    if buttons.pressed(MouseButton::Middle) || buttons.pressed(MouseButton::Right) {
+        for event in motion.iter() {
+            // Mouse motion has y pointing down, the canvas has it pointing up.
+            next.translation += Vec2::new(-event.delta.x, event.delta.y) * next.scale;
+        }
+    }
+
+    for event in wheel.iter() {
+        let steps = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 40.0,
+        };
+        let scale = (next.scale * 0.9f32.powf(steps)).max(MIN_SCALE).min(MAX_SCALE);
+
+        // Zoom around the cursor: the canvas point under it stays under it.
+        if let Some(cursor) = window.cursor_position() {
+            let size = window_size(window);
+            let before = next.screen_to_canvas(size, cursor);
+            next.scale = scale;
+            let after = next.screen_to_canvas(size, cursor);
+            next.translation += before - after;
+        } else {
+            next.scale = scale;
+        }
+    }
+
+    // Only written when something moved, so that systems watching the view don't run every frame.
+    if next != *view {
+        *view = next;
+    }
+}
+
+fn sync_camera(view: Res<CanvasView>, mut cameras: Query<&mut Transform, With<MainCamera>>) {
+    if !view.is_changed() {
+        return;
+    }
+    for mut transform in cameras.iter_mut() {
+        transform.translation.x = view.translation.x;
+        transform.translation.y = view.translation.y;
+        transform.scale = Vec3::new(view.scale, view.scale, 1.0);
+    }
+}
@@ -0,0 +1,129 @@
+//! The canvas index over everything drawn with `Bounds`, and the culling that hides whatever is off screen. Picking (clicks, hover, rubber-band selection) looks things up in the index instead of going through every entity on the canvas.
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+
+use crate::camera::{self, CanvasView};
+use crate::edge_filter::EdgeFilter;
+use crate::lod::{Detail, LevelOfDetail};
+use crate::spatial::SpatialIndex;
+use crate::{Bounds, Edge, Graph, Node};
+
+/// How far past the edges of the window things are still drawn, in screen pixels, so that panning doesn't show them popping in.
+const CULL_MARGIN: f32 = 64.0;
+
+#[derive(Default)]
+pub struct CanvasIndex(pub SpatialIndex<Entity>);
+
+pub struct CullingPlugin;
+
+impl Plugin for CullingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // Entities despawned during the update are only reported as removed until the end of the frame, so the index is kept up to date after the update.
+        app.init_resource::<CanvasIndex>()
+            .add_system_to_stage(CoreStage::PostUpdate, index_and_cull.system());
+    }
+}
+
+/// Keeps the index in step with the entities that were added, moved or despawned, then shows what overlaps the view and isn't inside of a container drawn collapsed, and hides everything else. Edges are bundled at the dots tier, so they are hidden there as well, and edges of the kinds filtered out are always hidden.
+///
+/// Only the entities that could have changed are looked at: the new ones, the ones around the old and the new view when the view moved, and the nodes (with their edges) that went in or out of a collapsed container when the graph changed. Everything is looked at again when the level of detail or the edge filter changes.
+fn index_and_cull(
+    mut index: ResMut<CanvasIndex>,
+    removed: RemovedComponents<Bounds>,
+    changed: Query<Entity, Changed<Bounds>>,
+    mut shapes: Query<(Entity, &Bounds, &mut Visible, Option<&Node>, Option<&Edge>)>,
+    graph: Res<Graph>,
+    lod: Res<LevelOfDetail>,
+    filter: Res<EdgeFilter>,
+    view: Res<CanvasView>,
+    windows: Res<Windows>,
+    mut last_view: Local<Option<(Vec2, Vec2)>>,
+    mut hidden_nodes: Local<HashSet<NodeIndex>>,
+) {
+    for entity in removed.iter() {
+        index.0.remove(entity);
+    }
+
+    let mut pending: Vec<Entity> = Vec::new();
+    for entity in changed.iter() {
+        if let Ok((_, bounds, ..)) = shapes.get_mut(entity) {
+            index.0.insert(entity, bounds.min, bounds.max);
+            pending.push(entity);
+        }
+    }
+
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let (min, max) = view.visible_rect(camera::window_size(window));
+    let margin = Vec2::splat(CULL_MARGIN * view.scale);
+    let view_rect = (min - margin, max + margin);
+
+    let full = lod.is_changed() || filter.is_changed() || last_view.is_none();
+    if graph.is_changed() || lod.is_changed() {
+        let model = &graph.0;
+        let now: HashSet<NodeIndex> = model.node_indices().filter(|index| lod.is_hidden(model, *index)).collect();
+        if !full {
+            for index in now.symmetric_difference(&*hidden_nodes) {
+                if !model.contains_node(*index) {
+                    continue;
+                }
+                pending.extend(model[*index].bevy_identity);
+                let edges = model
+                    .edges_directed(*index, Direction::Outgoing)
+                    .chain(model.edges_directed(*index, Direction::Incoming));
+                pending.extend(edges.filter_map(|edge| edge.weight().bevy_identity));
+            }
+        }
+        *hidden_nodes = now;
+    }
+    if !full && Some(view_rect) != *last_view {
+        if let Some((old_min, old_max)) = *last_view {
+            pending.extend(index.0.in_rect(old_min, old_max));
+        }
+        pending.extend(index.0.in_rect(view_rect.0, view_rect.1));
+    }
+    *last_view = Some(view_rect);
+
+    let graph = &graph.0;
+    let node_hidden = |index: NodeIndex| graph.contains_node(index) && lod.is_hidden(graph, index);
+    let hidden = |node: Option<&Node>, edge: Option<&Edge>| {
+        let edge_hidden = edge.and_then(|edge| edge.graph_identity).map_or(false, |edge| {
+            lod.detail == Detail::Dots
+                || graph.edge_weight(edge).map_or(false, |data| !filter.shows(data.kind))
+                || graph
+                    .edge_endpoints(edge)
+                    .map_or(false, |(a, b)| node_hidden(a) || node_hidden(b))
+        });
+        node.and_then(|node| node.identity).map_or(false, node_hidden) || edge_hidden
+    };
+    let update = |bounds: &Bounds, visible: &mut Mut<Visible>, node: Option<&Node>, edge: Option<&Edge>| {
+        let wanted = bounds.min.x <= view_rect.1.x
+            && bounds.max.x >= view_rect.0.x
+            && bounds.min.y <= view_rect.1.y
+            && bounds.max.y >= view_rect.0.y
+            && !hidden(node, edge);
+        // Only written when it changes, so that unchanged entities aren't flagged as changed.
+        if visible.is_visible != wanted {
+            visible.is_visible = wanted;
+        }
+    };
+
+    if full {
+        for (_, bounds, mut visible, node, edge) in shapes.iter_mut() {
+            update(bounds, &mut visible, node, edge);
+        }
+    } else {
+        for entity in pending {
+            if let Ok((_, bounds, mut visible, node, edge)) = shapes.get_mut(entity) {
+                update(bounds, &mut visible, node, edge);
+            }
+        }
+    }
+}
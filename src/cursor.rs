@@ -0,0 +1,191 @@
+//! The tool cursor: a 2D shape that follows the mouse and shows which tool is selected -- a crosshair for the selector, a ghost of the node about to be placed, a pen for edges and an empty frame for groups. The OS cursor is hidden over the canvas so that only this one shows, and the shape lights up when it is over something the tool can act on.
+
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use bevy_prototype_lyon::prelude::*;
+
+use crate::camera::CanvasView;
+use crate::culling::CanvasIndex;
+use crate::grid::GridSettings;
+use crate::theme::Theme;
+use crate::edges::EDGE_TOLERANCE;
+use crate::{adjust_cursor_position, clicked_entity, Bounds, EntityType, Placed, Route, ToolHistory, Tools};
+
+/// The cursor is drawn above everything else on the canvas.
+const CURSOR_Z: f32 = 10.0;
+
+pub struct Cursor {
+    pub current_tool: Tools,
+    /// Whether the cursor is over something that the current tool can act on.
+    pub on_target: bool,
+}
+
+pub struct CursorPlugin;
+
+impl Plugin for CursorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(change_cursor_position.system())
+            .add_system(change_tool.system())
+            .add_system(toggle_os_cursor.system());
+    }
+}
+
+/// Whether clicking with `tool` on `hovered` (or on empty space, for `None`) would do something.
+fn is_valid_target(tool: &Tools, hovered: Option<&EntityType>) -> bool {
+    match tool {
+        Tools::Selector => hovered.is_some(),
+        Tools::Node => hovered.is_none(),
+        Tools::Edge => hovered == Some(&Tools::Node),
+        Tools::Group => matches!(hovered, None | Some(Tools::Node) | Some(Tools::Group)),
+    }
+}
+
+fn change_cursor_position(
+    windows: Res<Windows>,
+    view: Res<CanvasView>,
+    grid: Res<GridSettings>,
+    canvas_index: Res<CanvasIndex>,
+    placed: Query<(&Placed, &Bounds, &Visible)>,
+    edges: Query<(&Route, &Visible)>,
+    tool_history: Res<ToolHistory>,
+    mut query: Query<(&mut Cursor, &mut Transform)>,
+) {
+    if let Some((x, y)) = adjust_cursor_position(&windows, &view, None) {
+        let hovered = clicked_entity((x, y), EDGE_TOLERANCE * view.scale, &canvas_index, &placed, &edges)
+            .map(|(_, entity_type)| entity_type);
+        let on_target = is_valid_target(&tool_history.current_tool, hovered.as_ref());
+
+        // The ghost node previews where the node will land, so it snaps and zooms along with the canvas. The other cursors keep their size on screen.
+        let (position, scale) = match tool_history.current_tool {
+            Tools::Node => (grid.place(Vec2::new(x, y)), 1.0),
+            _ => (Vec2::new(x, y), view.scale),
+        };
+
+        for (mut cursor, mut transform) in query.iter_mut() {
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
+            transform.scale = Vec3::new(scale, scale, 1.0);
+            // Only written when it flips, so that `change_tool` isn't told to redraw every frame.
+            if cursor.on_target != on_target {
+                cursor.on_target = on_target;
+            }
+        }
+    }
+}
+
+/// Redraws the cursor whenever the tool or the theme changes, or the cursor moves on or off a target.
+fn change_tool(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    tool_history: Res<ToolHistory>,
+    cursors: Query<(Entity, &Cursor, &Transform)>,
+    mut drawn: Local<Option<(Tools, bool)>>,
+) {
+    let existing = cursors.iter().next();
+    let on_target = existing.map_or(false, |(_, cursor, _)| cursor.on_target);
+    let wanted = (tool_history.current_tool.clone(), on_target);
+    if existing.is_some() && drawn.as_ref() == Some(&wanted) && !theme.is_changed() {
+        return;
+    }
+
+    let translation = match existing {
+        Some((entity, _, transform)) => {
+            commands.entity(entity).despawn();
+            transform.translation
+        }
+        None => Vec3::ZERO,
+    };
+    let transform = Transform::from_xyz(translation.x, translation.y, CURSOR_Z);
+
+    let color = if on_target {
+        theme.cursor_on_target
+    } else {
+        theme.cursor
+    };
+    let stroke = DrawMode::Stroke(StrokeOptions::default().with_line_width(2.0));
+
+    let bundle = match tool_history.current_tool {
+        Tools::Selector => {
+            let mut builder = PathBuilder::new();
+            builder.move_to(Vec2::new(-10.0, 0.0));
+            builder.line_to(Vec2::new(10.0, 0.0));
+            builder.move_to(Vec2::new(0.0, -10.0));
+            builder.line_to(Vec2::new(0.0, 10.0));
+            GeometryBuilder::build_as(&builder.build(), ShapeColors::new(color), stroke, transform)
+        }
+        Tools::Node => {
+            let ghost = shapes::Rectangle {
+                width: theme.node_size,
+                height: theme.node_size,
+                origin: shapes::RectangleOrigin::Center,
+            };
+            let mut fill = color;
+            fill.set_a(0.35);
+            GeometryBuilder::build_as(
+                &ghost,
+                ShapeColors::outlined(fill, color),
+                DrawMode::Outlined {
+                    fill_options: FillOptions::default(),
+                    outline_options: StrokeOptions::default().with_line_width(1.5),
+                },
+                transform,
+            )
+        }
+        Tools::Edge => {
+            // A pen leaning to the right with its nib on the hotspot.
+            let pen = shapes::Polygon {
+                points: vec![
+                    Vec2::new(0.0, 0.0),
+                    Vec2::new(4.0, 6.0),
+                    Vec2::new(16.0, 18.0),
+                    Vec2::new(18.0, 16.0),
+                    Vec2::new(6.0, 4.0),
+                ],
+                closed: true,
+            };
+            GeometryBuilder::build_as(
+                &pen,
+                ShapeColors::outlined(color, Color::BLACK),
+                DrawMode::Outlined {
+                    fill_options: FillOptions::default(),
+                    outline_options: StrokeOptions::default().with_line_width(1.0),
+                },
+                transform,
+            )
+        }
+        Tools::Group => {
+            let frame = shapes::Rectangle {
+                width: theme.node_size * 1.5,
+                height: theme.node_size,
+                origin: shapes::RectangleOrigin::Center,
+            };
+            GeometryBuilder::build_as(&frame, ShapeColors::new(color), stroke, transform)
+        }
+    };
+
+    commands.spawn_bundle(bundle).insert(Cursor {
+        current_tool: tool_history.current_tool.clone(),
+        on_target,
+    });
+    *drawn = Some(wanted);
+}
+
+/// Our cursor replaces the OS one over the canvas, but egui windows still get the normal pointer.
+fn toggle_os_cursor(egui_context: ResMut<EguiContext>, mut windows: ResMut<Windows>, mut cursors: Query<&mut Visible, With<Cursor>>) {
+    let over_egui = egui_context.ctx().is_pointer_over_area();
+
+    // Looking before touching, since borrowing the window mutably marks `Windows` as changed every frame.
+    let differs = windows
+        .get_primary()
+        .map_or(false, |window| window.cursor_visible() != over_egui);
+    if differs {
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_cursor_visibility(over_egui);
+        }
+    }
+    for mut visible in cursors.iter_mut() {
+        if visible.is_visible == over_egui {
+            visible.is_visible = !over_egui;
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! Comparing two versions of a graph, such as the views of a project at two revisions or two saves of a diagram. `diff` merges them into a single graph in which whatever was added, removed or changed records so in `CHANGE_PROPERTY`, and is drawn in the theme's colors for it.
+//!
+//! Nodes are matched by their identity: the key the view built from sources gave them (`model::KEY_PROPERTY`), which comes out the same whichever checkout it was built from, or their `id` otherwise. Edges are matched by `id` first, and then by their endpoints, kind and label.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use uuid::Uuid;
+
+use crate::metrics::Metric;
+use crate::model::{self, EdgeKind, GraphModel, NodeData};
+
+/// How a node or edge changed, as one of `Change::name`.
+pub const CHANGE_PROPERTY: &str = "change";
+
+/// What differs between the two versions of a changed node or edge: the names of properties, or `label`, `kind`, `parent` and `endpoints`, separated by commas.
+pub const CHANGED_PROPERTY: &str = "changed";
+
+/// Properties that aren't compared. Where an item was found moves with any edit above it.
+const IGNORED_PROPERTIES: &[&str] = &[model::SOURCE_PROPERTY, CHANGE_PROPERTY, CHANGED_PROPERTY];
+
+/// Whether the property `key` is left out of the comparison: the metrics go along with the edits that are already shown, and would otherwise mark most of a view as changed.
+fn ignored(key: &str) -> bool {
+    IGNORED_PROPERTIES.contains(&key) || Metric::iter().any(|metric| metric.property() == key)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Change {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Changed => "changed",
+        }
+    }
+
+    /// The change recorded in the properties of a node or edge, if any.
+    pub fn of(properties: &BTreeMap<String, String>) -> Option<Change> {
+        let name = properties.get(CHANGE_PROPERTY)?;
+        Change::iter().find(|change| change.name() == name)
+    }
+}
+
+/// What a node is matched by between the two versions.
+pub fn identity(node: &NodeData) -> String {
+    node.properties
+        .get(model::KEY_PROPERTY)
+        .cloned()
+        .unwrap_or_else(|| node.id.to_string())
+}
+
+fn changed_properties(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> BTreeSet<String> {
+    before
+        .keys()
+        .chain(after.keys())
+        .filter(|key| !ignored(key))
+        .filter(|key| before.get(*key) != after.get(*key))
+        .cloned()
+        .collect()
+}
+
+fn mark(properties: &mut BTreeMap<String, String>, change: Change, changed: BTreeSet<String>) {
+    properties.insert(CHANGE_PROPERTY.to_string(), change.name().to_string());
+    if !changed.is_empty() {
+        let changed: Vec<String> = changed.into_iter().collect();
+        properties.insert(CHANGED_PROPERTY.to_string(), changed.join(", "));
+    }
+}
+
+/// The identity of the container `index` is in, if any.
+fn parent_identity(graph: &GraphModel, index: NodeIndex) -> Option<String> {
+    graph[index].parent().map(|parent| identity(&graph[parent]))
+}
+
+/// What an edge is matched by when its `id` doesn't match: the identities of its endpoints, its kind and its label.
+type EdgeEnds = (String, String, EdgeKind, String);
+
+fn edge_ends(graph: &GraphModel, edge: EdgeIndex) -> Option<EdgeEnds> {
+    let (source, target) = graph.edge_endpoints(edge)?;
+    let weight = &graph[edge];
+    Some((identity(&graph[source]), identity(&graph[target]), weight.kind, weight.label.clone()))
+}
+
+/// `after`, with what was only in `before` added back in where it was. Everything that was added, removed or changed between the two is marked with `CHANGE_PROPERTY`.
+pub fn diff(before: &GraphModel, after: &GraphModel) -> GraphModel {
+    let mut merged = GraphModel::default();
+    let from_after = model::merge(&mut merged, after);
+
+    let before_nodes: HashMap<String, NodeIndex> = before
+        .node_indices()
+        .map(|index| (identity(&before[index]), index))
+        .collect();
+    // Where every node of `before` ends up in the merged graph.
+    let mut from_before: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+
+    for index in after.node_indices() {
+        let merged_index = from_after[&index];
+        match before_nodes.get(&identity(&after[index])) {
+            Some(old) => {
+                from_before.insert(*old, merged_index);
+                let (old_node, new_node) = (&before[*old], &after[index]);
+                let mut changed = changed_properties(&old_node.properties, &new_node.properties);
+                if old_node.label != new_node.label {
+                    changed.insert(String::from("label"));
+                }
+                if old_node.kind != new_node.kind {
+                    changed.insert(String::from("kind"));
+                }
+                if parent_identity(before, *old) != parent_identity(after, index) {
+                    changed.insert(String::from("parent"));
+                }
+                if !changed.is_empty() {
+                    mark(&mut merged[merged_index].properties, Change::Changed, changed);
+                }
+            }
+            None => mark(&mut merged[merged_index].properties, Change::Added, BTreeSet::new()),
+        }
+    }
+
+    let removed: Vec<NodeIndex> = before
+        .node_indices()
+        .filter(|index| !from_before.contains_key(index))
+        .collect();
+    for index in &removed {
+        let mut weight = before[*index].detached();
+        mark(&mut weight.properties, Change::Removed, BTreeSet::new());
+        from_before.insert(*index, merged.add_node(weight));
+    }
+    // Only once every removed node is in, since a removed node can be in a removed container.
+    for index in removed {
+        if let Some(parent) = before[index].parent() {
+            let merged_parent = from_before.get(&parent).copied();
+            model::set_parent(&mut merged, from_before[&index], merged_parent);
+        }
+    }
+
+    let before_by_id: HashMap<Uuid, EdgeIndex> = before.edge_references().map(|edge| (edge.weight().id, edge.id())).collect();
+    let before_by_ends: HashMap<EdgeEnds, EdgeIndex> = before
+        .edge_indices()
+        .filter_map(|edge| Some((edge_ends(before, edge)?, edge)))
+        .collect();
+    let mut matched: HashSet<EdgeIndex> = HashSet::new();
+
+    for edge in merged.edge_indices().collect::<Vec<_>>() {
+        let ends = edge_ends(&merged, edge);
+        let old = before_by_id
+            .get(&merged[edge].id)
+            .or_else(|| ends.as_ref().and_then(|ends| before_by_ends.get(ends)))
+            .copied()
+            .filter(|old| !matched.contains(old));
+        match old {
+            Some(old) => {
+                matched.insert(old);
+                let (old_edge, new_edge) = (&before[old], &merged[edge]);
+                let mut changed = changed_properties(&old_edge.properties, &new_edge.properties);
+                if old_edge.label != new_edge.label {
+                    changed.insert(String::from("label"));
+                }
+                if old_edge.kind != new_edge.kind {
+                    changed.insert(String::from("kind"));
+                }
+                let old_ends = edge_ends(before, old).map(|(source, target, ..)| (source, target));
+                if old_ends != ends.map(|(source, target, ..)| (source, target)) {
+                    changed.insert(String::from("endpoints"));
+                }
+                if !changed.is_empty() {
+                    mark(&mut merged[edge].properties, Change::Changed, changed);
+                }
+            }
+            None => mark(&mut merged[edge].properties, Change::Added, BTreeSet::new()),
+        }
+    }
+
+    for edge in before.edge_references().filter(|edge| !matched.contains(&edge.id())) {
+        let mut weight = edge.weight().clone();
+        weight.bevy_identity = None;
+        mark(&mut weight.properties, Change::Removed, BTreeSet::new());
+        merged.add_edge(from_before[&edge.source()], from_before[&edge.target()], weight);
+    }
+
+    merged
+}
+
+/// How many nodes and edges of `graph` are marked with `change`.
+pub fn count(graph: &GraphModel, change: Change) -> usize {
+    let nodes = graph
+        .node_weights()
+        .filter(|node| Change::of(&node.properties) == Some(change))
+        .count();
+    let edges = graph
+        .edge_weights()
+        .filter(|edge| Change::of(&edge.properties) == Some(change))
+        .count();
+    nodes + edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{EdgeData, NodeKind};
+
+    fn keyed(key: &str, kind: NodeKind) -> NodeData {
+        let mut node = NodeData::new(key, kind);
+        node.properties.insert(model::KEY_PROPERTY.to_string(), key.to_string());
+        node
+    }
+
+    fn change_of(graph: &GraphModel, label: &str) -> Option<Change> {
+        let node = graph.node_weights().find(|node| node.label == label).unwrap();
+        Change::of(&node.properties)
+    }
+
+    fn changed_of(graph: &GraphModel, label: &str) -> Option<String> {
+        let node = graph.node_weights().find(|node| node.label == label).unwrap();
+        node.properties.get(CHANGED_PROPERTY).cloned()
+    }
+
+    #[test]
+    fn added_removed_and_changed_nodes() {
+        let mut before = GraphModel::default();
+        let kept = before.add_node(keyed("kept", NodeKind::Struct));
+        let edited = before.add_node(keyed("edited", NodeKind::Struct));
+        let gone = before.add_node(keyed("gone", NodeKind::Struct));
+        before.add_edge(kept, gone, EdgeData::new(EdgeKind::UsesType));
+        before[edited].properties.insert(String::from("color"), String::from("red"));
+
+        let mut after = GraphModel::default();
+        let kept = after.add_node(keyed("kept", NodeKind::Struct));
+        let edited = after.add_node(keyed("edited", NodeKind::Enum));
+        let new = after.add_node(keyed("new", NodeKind::Struct));
+        after.add_edge(kept, new, EdgeData::new(EdgeKind::UsesType));
+        after[edited].properties.insert(String::from("color"), String::from("blue"));
+
+        let merged = diff(&before, &after);
+        assert_eq!(merged.node_count(), 4);
+        assert_eq!(change_of(&merged, "kept"), None);
+        assert_eq!(change_of(&merged, "edited"), Some(Change::Changed));
+        assert_eq!(changed_of(&merged, "edited").as_deref(), Some("color, kind"));
+        assert_eq!(change_of(&merged, "gone"), Some(Change::Removed));
+        assert_eq!(change_of(&merged, "new"), Some(Change::Added));
+        assert_eq!(count(&merged, Change::Added), 2);
+        assert_eq!(count(&merged, Change::Removed), 2);
+        assert_eq!(count(&merged, Change::Changed), 1);
+    }
+
+    #[test]
+    fn metrics_and_locations_arent_changes() {
+        let mut before = GraphModel::default();
+        let node = before.add_node(keyed("f", NodeKind::Function));
+        before[node].properties.insert(model::SOURCE_PROPERTY.to_string(), String::from("src/lib.rs:3"));
+        let mut after = before.clone();
+        after[node].properties.insert(model::SOURCE_PROPERTY.to_string(), String::from("src/lib.rs:9"));
+        for metric in Metric::iter() {
+            before[node].properties.insert(metric.property().to_string(), String::from("1"));
+            after[node].properties.insert(metric.property().to_string(), String::from("2"));
+        }
+
+        assert_eq!(change_of(&diff(&before, &after), "f"), None);
+    }
+
+    #[test]
+    fn nodes_match_by_key_then_by_id() {
+        // Built from sources twice, the same item has a new id but the same key.
+        let before_node = keyed("fn app::run", NodeKind::Function);
+        let after_node = keyed("fn app::run", NodeKind::Function);
+        assert_ne!(before_node.id, after_node.id);
+        // Without a key, only the id tells that two nodes are the same.
+        let drawn = NodeData::new("drawn", NodeKind::Item);
+        let mut redrawn = NodeData::new("drawn", NodeKind::Item);
+        redrawn.id = drawn.id;
+        // Nor does a label, which anything can share.
+        let lookalike = NodeData::new("lookalike", NodeKind::Item);
+        let other = NodeData::new("lookalike", NodeKind::Item);
+
+        let mut before = GraphModel::default();
+        before.add_node(before_node);
+        before.add_node(drawn);
+        before.add_node(lookalike);
+        let mut after = GraphModel::default();
+        after.add_node(after_node);
+        after.add_node(redrawn);
+        after.add_node(other);
+
+        let merged = diff(&before, &after);
+        assert_eq!(change_of(&merged, "fn app::run"), None);
+        assert_eq!(change_of(&merged, "drawn"), None);
+        assert_eq!(merged.node_count(), 4);
+        assert_eq!(count(&merged, Change::Removed), 1);
+        assert_eq!(count(&merged, Change::Added), 1);
+    }
+
+    #[test]
+    fn removed_node_stays_in_its_removed_container() {
+        let mut before = GraphModel::default();
+        let krate = before.add_node(keyed("app", NodeKind::Crate));
+        let module = before.add_node(keyed("app::old", NodeKind::Module));
+        let item = before.add_node(keyed("app::old::Thing", NodeKind::Struct));
+        model::set_parent(&mut before, module, Some(krate));
+        model::set_parent(&mut before, item, Some(module));
+        let mut after = GraphModel::default();
+        after.add_node(keyed("app", NodeKind::Crate));
+
+        let merged = diff(&before, &after);
+        let index = |label: &str| merged.node_indices().find(|index| merged[*index].label == label).unwrap();
+        assert_eq!(change_of(&merged, "app"), None);
+        assert_eq!(change_of(&merged, "app::old"), Some(Change::Removed));
+        assert_eq!(change_of(&merged, "app::old::Thing"), Some(Change::Removed));
+        assert_eq!(merged[index("app::old::Thing")].parent(), Some(index("app::old")));
+        assert_eq!(merged[index("app::old")].parent(), Some(index("app")));
+        assert_eq!(model::children(&merged, index("app::old")), vec![index("app::old::Thing")]);
+    }
+}
@@ -0,0 +1,133 @@
+//! The egui Diff window. Two versions of a project are compared (see `diff`) and the merged graph is added to the canvas, with what was added, removed or changed drawn in the theme's colors for it. Natively each version is either a checkout directory, read into one of the Rust source views, or a saved diagram file; diagrams can also be pasted in.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use strum::IntoEnumIterator;
+
+use crate::diff::{self, Change};
+use crate::import;
+use crate::layout;
+use crate::model::{self, GraphModel};
+use crate::rust_source::SourceView;
+use crate::theme::Theme;
+use crate::Graph;
+
+struct DiffState {
+    /// The view checkout directories are read into.
+    view: SourceView,
+    before: String,
+    after: String,
+    pasted_before: String,
+    pasted_after: String,
+    /// The outcome of the last comparison, shown underneath the buttons.
+    status: String,
+}
+
+impl Default for DiffState {
+    fn default() -> Self {
+        DiffState {
+            view: SourceView::Traits,
+            before: String::new(),
+            after: String::new(),
+            pasted_before: String::new(),
+            pasted_after: String::new(),
+            status: String::new(),
+        }
+    }
+}
+
+pub struct DiffMenuPlugin;
+
+impl Plugin for DiffMenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(DiffState::default())
+            .add_system(diff_menu.system());
+    }
+}
+
+/// One version of the project: the view of a checkout directory, or the diagram in a file.
+#[cfg(not(target_arch = "wasm32"))]
+fn load(path: &str, view: SourceView) -> Result<GraphModel, String> {
+    let path = std::path::Path::new(path);
+    if path.is_dir() {
+        let files = crate::rust_source::read_checkout(path)
+            .map_err(|error| format!("Couldn't read {}: {}", path.display(), error))?;
+        view.build(&files, &Default::default())
+            .map_err(|error| format!("Couldn't build the view of {}: {}", path.display(), error))
+    } else {
+        let source = std::fs::read_to_string(path).map_err(|error| format!("Couldn't read {}: {}", path.display(), error))?;
+        import::import(&source).map_err(|error| format!("Couldn't import {}: {}", path.display(), error))
+    }
+}
+
+fn diff_menu(
+    egui_context: ResMut<EguiContext>,
+    mut graph: ResMut<Graph>,
+    theme: Res<Theme>,
+    mut state: ResMut<DiffState>,
+) {
+    let mut versions: Option<Result<(GraphModel, GraphModel), String>> = None;
+
+    egui::Window::new("Diff").show(egui_context.ctx(), |ui| {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.label("Compare two checkouts of a project, or two diagram files:");
+            ui.horizontal(|ui| {
+                for view in SourceView::iter() {
+                    if ui.selectable_label(state.view == view, view.name()).clicked() {
+                        state.view = view;
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Before");
+                ui.text_edit_singleline(&mut state.before);
+            });
+            ui.horizontal(|ui| {
+                ui.label("After");
+                ui.text_edit_singleline(&mut state.after);
+            });
+            if ui.button("Compare").clicked() {
+                let view = state.view;
+                versions = Some(load(&state.before, view).and_then(|before| Ok((before, load(&state.after, view)?))));
+            }
+            ui.separator();
+        }
+
+        ui.label("Or paste two diagrams:");
+        ui.columns(2, |columns| {
+            columns[0].label("Before");
+            columns[0].add(egui::TextEdit::multiline(&mut state.pasted_before).desired_rows(6));
+            columns[1].label("After");
+            columns[1].add(egui::TextEdit::multiline(&mut state.pasted_after).desired_rows(6));
+        });
+        if ui.button("Compare pasted diagrams").clicked() {
+            let imported = import::import(&state.pasted_before)
+                .and_then(|before| Ok((before, import::import(&state.pasted_after)?)));
+            versions = Some(imported.map_err(|error| format!("Couldn't import: {}", error)));
+        }
+
+        if !state.status.is_empty() {
+            ui.label(state.status.clone());
+        }
+    });
+
+    match versions {
+        Some(Ok((before, after))) => {
+            let merged = diff::diff(&before, &after);
+            // Laying out after merging keeps the new nodes clear of whatever is already on the canvas.
+            model::merge(&mut graph.0, &merged);
+            layout::layout_unpositioned(&mut graph.0, theme.node_size);
+            let counts: Vec<String> = Change::iter()
+                .map(|change| format!("{} {}", diff::count(&merged, change), change.name()))
+                .collect();
+            state.status = format!(
+                "{} of {} nodes and edges.",
+                counts.join(", "),
+                merged.node_count() + merged.edge_count()
+            );
+        }
+        Some(Err(error)) => state.status = error,
+        None => {}
+    }
+}
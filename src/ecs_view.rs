@@ -0,0 +1,337 @@
+//! The Bevy ECS view: a node per system (every function registered with `add_system` and its relatives), nested in the containers of the module it is defined in, a node per resource and component they touch, and `Reads`/`Writes` edges going by the system's parameters (`Res`/`ResMut`, `Query<&T>`/`Query<&mut T>`, `EventReader`/`EventWriter` as the `Events<T>` resource). Resources and components that one system writes and another one reads or writes as well are marked with `CONFLICT_PROPERTY`, since those systems can't run in parallel and the order they run in matters. Focused on a system, resource or component, it shows that and whatever it is directly connected to.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use quote::ToTokens;
+use syn::visit::{self, Visit};
+
+use crate::model::{self, EdgeKind, GraphModel, NodeKind};
+use crate::rust_source::{for_each_item, path_name, type_name, ParsedFile, ViewBuilder};
+
+/// The property recording which systems other than the writer touch a resource or component something writes.
+pub const CONFLICT_PROPERTY: &str = "conflicts";
+
+/// The property recording the stage a system was added to, for the systems not added to the default one.
+pub const STAGE_PROPERTY: &str = "stage";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+}
+
+/// The systems registered in a body, by function name, along with the stage they were added to.
+#[derive(Default)]
+struct Registrations {
+    systems: Vec<(String, Option<String>)>,
+}
+
+impl<'ast> Visit<'ast> for Registrations {
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        let stage = match call.method.to_string().as_str() {
+            "add_system" | "with_system" => Some(None),
+            "add_startup_system" | "add_startup_system_to_stage" => Some(Some(String::from("startup"))),
+            "add_system_to_stage" => Some(call.args.first().map(stage_name)),
+            _ => None,
+        };
+        // The system is always the last argument, after the stage when there is one.
+        if let (Some(stage), Some(system)) = (stage, call.args.last()) {
+            if let Some(name) = system_name(system) {
+                self.systems.push((name, stage));
+            }
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+/// `CoreStage::PostUpdate` and `stage::POST_UPDATE` are known by their last part, string labels by their contents.
+fn stage_name(stage: &syn::Expr) -> String {
+    match stage {
+        syn::Expr::Path(path) => path_name(&path.path).unwrap_or_default(),
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(label),
+            ..
+        }) => label.value(),
+        _ => stage.to_token_stream().to_string(),
+    }
+}
+
+/// The function a system argument such as `enact_interaction.system().label("enact")` is made from.
+fn system_name(mut system: &syn::Expr) -> Option<String> {
+    loop {
+        match system {
+            syn::Expr::MethodCall(call) => system = &call.receiver,
+            syn::Expr::Paren(paren) => system = &paren.expr,
+            syn::Expr::Path(path) => return path_name(&path.path),
+            _ => return None,
+        }
+    }
+}
+
+/// The first type argument of `segment`, such as `T` in `Res<T>`.
+fn type_argument(segment: &syn::PathSegment) -> Option<&syn::Type> {
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(arguments) => arguments.args.iter().find_map(|argument| match argument {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/// What a system parameter of type `ty` reads and writes, as kind and name.
+fn parameter_access(ty: &syn::Type, found: &mut Vec<(Access, NodeKind, String)>) {
+    let segment = match ty {
+        syn::Type::Path(path) if path.qself.is_none() => match path.path.segments.last() {
+            Some(segment) => segment,
+            None => return,
+        },
+        _ => return,
+    };
+    let argument = match type_argument(segment) {
+        Some(argument) => argument,
+        None => return,
+    };
+    match segment.ident.to_string().as_str() {
+        "Res" => found.push((Access::Read, NodeKind::Resource, type_name(argument))),
+        "ResMut" => found.push((Access::Write, NodeKind::Resource, type_name(argument))),
+        "EventReader" => found.push((Access::Read, NodeKind::Resource, format!("Events<{}>", type_name(argument)))),
+        "EventWriter" => found.push((Access::Write, NodeKind::Resource, format!("Events<{}>", type_name(argument)))),
+        "Option" => parameter_access(argument, found),
+        "Query" => query_access(argument, found),
+        _ => {}
+    }
+}
+
+/// What the data of a query (`&T`, `&mut T`, `Option<&T>` or a tuple of those) reads and writes.
+fn query_access(data: &syn::Type, found: &mut Vec<(Access, NodeKind, String)>) {
+    match data {
+        syn::Type::Reference(reference) => {
+            let access = if reference.mutability.is_some() { Access::Write } else { Access::Read };
+            found.push((access, NodeKind::Component, type_name(&reference.elem)));
+        }
+        syn::Type::Tuple(tuple) => {
+            for element in &tuple.elems {
+                query_access(element, found);
+            }
+        }
+        syn::Type::Paren(paren) => query_access(&paren.elem, found),
+        syn::Type::Path(path) => {
+            if let Some(segment) = path.path.segments.last() {
+                if segment.ident == "Option" {
+                    if let Some(argument) = type_argument(segment) {
+                        query_access(argument, found);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn build(files: &[ParsedFile], focus: Option<&str>) -> GraphModel {
+    let mut registrations = Registrations::default();
+    let mut definitions: HashMap<String, Vec<(&ParsedFile, Vec<String>, &syn::ItemFn)>> = HashMap::new();
+    for file in files {
+        registrations.visit_file(&file.syntax);
+        for_each_item(file, |module, item| {
+            if let syn::Item::Fn(item) = item {
+                definitions
+                    .entry(item.sig.ident.to_string())
+                    .or_insert_with(Vec::new)
+                    .push((file, module.to_vec(), item));
+            }
+        });
+    }
+
+    let mut view = ViewBuilder::default();
+    let mut seen: HashSet<&str> = HashSet::new();
+    for (name, stage) in &registrations.systems {
+        // Systems added twice (to several stages, or by several plugins) are drawn once, with the first stage they were added to.
+        if !seen.insert(name) {
+            continue;
+        }
+        for (file, module, item) in definitions.get(name).into_iter().flatten() {
+            let container = view.module(&file.krate, module);
+            let mut path = module.clone();
+            path.push(name.clone());
+            let system = view.node(&format!("fn {}::{}", file.krate, path.join("::")), name, NodeKind::Function);
+            model::set_parent(&mut view.graph, system, Some(container));
+            view.locate(system, file.location(&item.sig.ident));
+            if let Some(stage) = stage {
+                view.graph[system].properties.insert(STAGE_PROPERTY.to_string(), stage.clone());
+            }
+
+            let mut found = Vec::new();
+            for input in &item.sig.inputs {
+                if let syn::FnArg::Typed(input) = input {
+                    parameter_access(&input.ty, &mut found);
+                }
+            }
+            for (access, kind, name) in found {
+                let key = match kind {
+                    NodeKind::Component => format!("component {}", name),
+                    _ => format!("resource {}", name),
+                };
+                let data = view.node(&key, &name, kind);
+                let edge_kind = match access {
+                    Access::Read => EdgeKind::Reads,
+                    Access::Write => EdgeKind::Writes,
+                };
+                view.edge(system, data, edge_kind, "");
+            }
+        }
+    }
+
+    let mut graph = view.graph;
+    mark_conflicts(&mut graph);
+    if let Some(focus) = focus {
+        let keep = match graph
+            .node_indices()
+            .find(|index| !graph[*index].kind.is_container() && graph[*index].label == focus)
+        {
+            Some(start) => focused(&graph, start),
+            None => HashSet::new(),
+        };
+        graph.retain_nodes(|_, index| keep.contains(&index));
+    }
+    graph
+}
+
+/// Records on every resource and component that a system writes which other systems touch it as well.
+fn mark_conflicts(graph: &mut GraphModel) {
+    let data: Vec<NodeIndex> = graph
+        .node_indices()
+        .filter(|index| matches!(graph[*index].kind, NodeKind::Resource | NodeKind::Component))
+        .collect();
+    for index in data {
+        let accessors: Vec<(NodeIndex, EdgeKind)> = graph
+            .edges_directed(index, Direction::Incoming)
+            .map(|edge| (edge.source(), edge.weight().kind))
+            .collect();
+        let writes = accessors.iter().any(|(_, kind)| *kind == EdgeKind::Writes);
+        let systems: BTreeSet<&str> = accessors.iter().map(|(system, _)| graph[*system].label.as_str()).collect();
+        if writes && systems.len() > 1 {
+            let systems = systems.into_iter().collect::<Vec<_>>().join(", ");
+            graph[index].properties.insert(CONFLICT_PROPERTY.to_string(), systems);
+        }
+    }
+}
+
+/// `start`, whatever it has an edge to or from, and the modules all of those are in.
+fn focused(graph: &GraphModel, start: NodeIndex) -> HashSet<NodeIndex> {
+    let mut keep: HashSet<NodeIndex> = graph
+        .edges_directed(start, Direction::Outgoing)
+        .map(|edge| edge.target())
+        .chain(graph.edges_directed(start, Direction::Incoming).map(|edge| edge.source()))
+        .collect();
+    keep.insert(start);
+
+    let containers: Vec<NodeIndex> = keep.iter().flat_map(|index| model::ancestors(graph, *index)).collect();
+    keep.extend(containers);
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::visit::IntoEdgeReferences;
+
+    use super::*;
+    use crate::rust_source::parse_sources;
+
+    const MAIN: &str = "
+        fn main() {
+            App::build()
+                .add_system(enact_interaction.system())
+                .add_system(highlight.system().label(\"highlight\"))
+                .add_system_to_stage(CoreStage::PostUpdate, follow.system())
+                .add_startup_system(setup.system())
+                .run();
+        }
+
+        fn enact_interaction(
+            mut commands: Commands,
+            mut interaction_history: ResMut<InteractionHistory>,
+            mut graph_interaction_history: ResMut<GraphInteractionHistory>,
+            theme: Res<Theme>,
+        ) {
+        }
+
+        fn highlight(theme: Res<Theme>, nodes: Query<(&Node, &mut Transform)>, mut clicks: EventReader<Clicked>) {}
+
+        fn follow(transforms: Query<&Transform>, history: Res<InteractionHistory>) {}
+
+        fn setup(mut clicks: EventWriter<Clicked>) {}
+
+        fn not_a_system(history: ResMut<InteractionHistory>) {}
+    ";
+
+    fn graph() -> GraphModel {
+        build(&parse_sources(&[("app/src/main.rs", MAIN)]), None)
+    }
+
+    fn find(graph: &GraphModel, label: &str) -> NodeIndex {
+        graph.node_indices().find(|index| graph[*index].label == label).unwrap()
+    }
+
+    fn property<'a>(graph: &'a GraphModel, label: &str, property: &str) -> Option<&'a str> {
+        graph[find(graph, label)].properties.get(property).map(String::as_str)
+    }
+
+    #[test]
+    fn systems_read_and_write_their_parameters() {
+        let graph = graph();
+        let mut accesses: Vec<(&str, &str, NodeKind, EdgeKind)> = graph
+            .edge_references()
+            .map(|edge| {
+                let data = &graph[edge.target()];
+                (graph[edge.source()].label.as_str(), data.label.as_str(), data.kind, edge.weight().kind)
+            })
+            .collect();
+        accesses.sort_by_key(|(system, data, ..)| (*system, *data));
+        assert_eq!(
+            accesses,
+            vec![
+                ("enact_interaction", "GraphInteractionHistory", NodeKind::Resource, EdgeKind::Writes),
+                ("enact_interaction", "InteractionHistory", NodeKind::Resource, EdgeKind::Writes),
+                ("enact_interaction", "Theme", NodeKind::Resource, EdgeKind::Reads),
+                ("follow", "InteractionHistory", NodeKind::Resource, EdgeKind::Reads),
+                ("follow", "Transform", NodeKind::Component, EdgeKind::Reads),
+                ("highlight", "Events<Clicked>", NodeKind::Resource, EdgeKind::Reads),
+                ("highlight", "Node", NodeKind::Component, EdgeKind::Reads),
+                ("highlight", "Theme", NodeKind::Resource, EdgeKind::Reads),
+                ("highlight", "Transform", NodeKind::Component, EdgeKind::Writes),
+                ("setup", "Events<Clicked>", NodeKind::Resource, EdgeKind::Writes),
+            ]
+        );
+        // Only registered functions are systems.
+        assert!(graph.node_weights().all(|node| node.label != "not_a_system"));
+    }
+
+    #[test]
+    fn stages_are_recorded() {
+        let graph = graph();
+        assert_eq!(property(&graph, "follow", STAGE_PROPERTY), Some("PostUpdate"));
+        assert_eq!(property(&graph, "setup", STAGE_PROPERTY), Some("startup"));
+        assert_eq!(property(&graph, "enact_interaction", STAGE_PROPERTY), None);
+        assert_eq!(property(&graph, "highlight", STAGE_PROPERTY), None);
+    }
+
+    #[test]
+    fn conflicts_are_marked_where_something_writes() {
+        let graph = graph();
+        assert_eq!(
+            property(&graph, "InteractionHistory", CONFLICT_PROPERTY),
+            Some("enact_interaction, follow")
+        );
+        assert_eq!(property(&graph, "Transform", CONFLICT_PROPERTY), Some("follow, highlight"));
+        assert_eq!(property(&graph, "Events<Clicked>", CONFLICT_PROPERTY), Some("highlight, setup"));
+        // Written by a single system, or only read.
+        assert_eq!(property(&graph, "GraphInteractionHistory", CONFLICT_PROPERTY), None);
+        assert_eq!(property(&graph, "Theme", CONFLICT_PROPERTY), None);
+        assert_eq!(property(&graph, "Node", CONFLICT_PROPERTY), None);
+    }
+}
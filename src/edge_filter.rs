@@ -0,0 +1,49 @@
+//! The Edges window, for hiding edges by kind, so that for instance type usage can be looked at without the calls drawn over it. Hidden edges are left in the model, the culling just doesn't show them (and the edge bundles leave them out).
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use strum::IntoEnumIterator;
+
+use crate::model::EdgeKind;
+
+#[derive(Debug, Default)]
+pub struct EdgeFilter {
+    hidden: HashSet<EdgeKind>,
+}
+
+impl EdgeFilter {
+    pub fn shows(&self, kind: EdgeKind) -> bool {
+        !self.hidden.contains(&kind)
+    }
+}
+
+pub struct EdgeFilterPlugin;
+
+impl Plugin for EdgeFilterPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<EdgeFilter>()
+            .add_system(edge_filter_menu.system());
+    }
+}
+
+fn edge_filter_menu(egui_context: ResMut<EguiContext>, mut filter: ResMut<EdgeFilter>) {
+    let mut toggled: Option<EdgeKind> = None;
+
+    egui::Window::new("Edges").show(egui_context.ctx(), |ui| {
+        for kind in EdgeKind::iter() {
+            let mut shown = filter.shows(kind);
+            if ui.checkbox(&mut shown, format!("{:?}", kind)).changed() {
+                toggled = Some(kind);
+            }
+        }
+    });
+
+    // Only touched when something was toggled, so that the culling doesn't go over the whole canvas every frame.
+    if let Some(kind) = toggled {
+        if !filter.hidden.remove(&kind) {
+            filter.hidden.insert(kind);
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Bend points and routing for edges. With the selector, double-clicking an edge adds a bend point where it was clicked, bend points can be dragged around, and double-clicking one removes it again. The routing (straight or orthogonal) is chosen for all edges at once from the toolbox.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use bevy_prototype_lyon::prelude::*;
+use petgraph::stable_graph::EdgeIndex;
+
+use crate::camera::CanvasView;
+use crate::culling::CanvasIndex;
+use crate::edge_filter::EdgeFilter;
+use crate::geometry;
+use crate::grid::GridSettings;
+use crate::lod::{Detail, LevelOfDetail};
+use crate::model::{self, Routing};
+use crate::theme::Theme;
+use crate::{
+    adjust_cursor_position, edge_under_cursor, entity_under_cursor, forget_edge_drawing, Bounds, Edge, Graph, Placed, Route,
+    ToolHistory, Tools, DOUBLE_CLICK_SECONDS,
+};
+
+/// Radius of the handles drawn on bend points, in canvas units.
+const BEND_HANDLE_RADIUS: f32 = 4.0;
+
+/// How close the cursor has to be to an edge or bend point to hit it, in screen pixels.
+pub const EDGE_TOLERANCE: f32 = 5.0;
+
+/// Bend handles are drawn on top of the edges but below the nodes.
+const BEND_HANDLE_Z: f32 = -0.5;
+
+/// The routing given to edges drawn with the edge tool.
+#[derive(Debug, Default)]
+pub struct EdgeDefaults {
+    pub routing: Routing,
+}
+
+/// Sent by the toolbox to switch every edge (and the edges drawn from now on) to a routing.
+pub struct SetRouting(pub Routing);
+
+/// Tag for the handle drawn on bend point `bend` of `edge`.
+pub struct BendHandle {
+    pub edge: EdgeIndex,
+    pub bend: usize,
+}
+
+/// What was under the cursor when the selector was pressed on an edge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EdgeHit {
+    /// A bend point, by its index in `EdgeData::bends`.
+    Bend(EdgeIndex, usize),
+    /// The edge itself, on the leg leading up to bend point `leg` (or to the target, for the last leg).
+    Leg(EdgeIndex, usize),
+}
+
+#[derive(Default)]
+struct BendEditor {
+    dragging: Option<(EdgeIndex, usize)>,
+    /// Where the dragged bend point's handle has been moved to, until it is let go.
+    moved_to: Option<Vec2>,
+    last_click: Option<(EdgeHit, f64)>,
+}
+
+/// Everything an edge's bend handles are drawn from. They are only drawn again when this comes out differently.
+#[derive(Clone, PartialEq)]
+struct HandleLook {
+    bends: Vec<(f32, f32)>,
+    color: Color,
+}
+
+/// The bend handles on the canvas by their edge, with what they were drawn from.
+#[derive(Default)]
+struct DrawnHandles {
+    edges: HashMap<EdgeIndex, (Vec<Entity>, HandleLook)>,
+}
+
+pub struct EdgesPlugin;
+
+impl Plugin for EdgesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(EdgeDefaults::default())
+            .add_event::<SetRouting>()
+            .add_system(set_routing.system())
+            .add_system(draw_bend_handles.system())
+            .add_system(edit_bends.system());
+    }
+}
+
+fn set_routing(
+    mut commands: Commands,
+    mut events: EventReader<SetRouting>,
+    mut defaults: ResMut<EdgeDefaults>,
+    mut graph: ResMut<Graph>,
+) {
+    for SetRouting(routing) in events.iter() {
+        defaults.routing = *routing;
+        let edges: Vec<EdgeIndex> = graph.0.edge_indices().collect();
+        for edge in edges {
+            if graph.0[edge].routing != *routing {
+                graph.0[edge].routing = *routing;
+                forget_edge_drawing(&mut commands, &mut graph.0, edge);
+            }
+        }
+    }
+}
+
+/// Whenever the graph, the theme, the level of detail or the edge filter changes, brings the handles on the bend points of the visible edges in step with it. Only the handles of edges that come out differently are drawn again. Handles are only drawn at full detail.
+fn draw_bend_handles(
+    mut commands: Commands,
+    graph: Res<Graph>,
+    theme: Res<Theme>,
+    lod: Res<LevelOfDetail>,
+    filter: Res<EdgeFilter>,
+    mut drawn: Local<DrawnHandles>,
+) {
+    if !graph.is_changed() && !theme.is_changed() && !lod.is_changed() && !filter.is_changed() {
+        return;
+    }
+
+    let graph = &graph.0;
+    let mut looks: HashMap<EdgeIndex, HandleLook> = HashMap::new();
+    if lod.detail == Detail::Full {
+        for edge in graph.edge_indices() {
+            if graph[edge].bends.is_empty() || is_edge_hidden(graph, &lod, &filter, edge) {
+                continue;
+            }
+            let look = HandleLook {
+                bends: graph[edge].bends.clone(),
+                color: theme.edge_style(graph[edge].kind).color,
+            };
+            looks.insert(edge, look);
+        }
+    }
+
+    drawn.edges.retain(|edge, (entities, look)| {
+        let keep = looks.get(edge) == Some(look);
+        if !keep {
+            for entity in entities.iter() {
+                commands.entity(*entity).despawn();
+            }
+        }
+        keep
+    });
+    for (edge, look) in looks {
+        if !drawn.edges.contains_key(&edge) {
+            let entities = draw_handles(&mut commands, &theme, edge, &look);
+            drawn.edges.insert(edge, (entities, look));
+        }
+    }
+}
+
+fn draw_handles(commands: &mut Commands, theme: &Theme, edge: EdgeIndex, look: &HandleLook) -> Vec<Entity> {
+    let handle = shapes::Circle {
+        radius: BEND_HANDLE_RADIUS,
+        center: Vec2::ZERO,
+    };
+    look.bends
+        .iter()
+        .enumerate()
+        .map(|(bend, (x, y))| {
+            commands
+                .spawn_bundle(GeometryBuilder::build_as(
+                    &handle,
+                    ShapeColors::outlined(theme.background, look.color),
+                    DrawMode::Outlined {
+                        fill_options: FillOptions::default(),
+                        outline_options: StrokeOptions::default().with_line_width(1.5),
+                    },
+                    Transform::from_xyz(*x, *y, BEND_HANDLE_Z),
+                ))
+                .insert(BendHandle { edge, bend })
+                .id()
+        })
+        .collect()
+}
+
+fn is_edge_hidden(graph: &model::GraphModel, lod: &LevelOfDetail, filter: &EdgeFilter, edge: EdgeIndex) -> bool {
+    !filter.shows(graph[edge].kind)
+        || graph
+            .edge_endpoints(edge)
+            .map_or(true, |(a, b)| lod.is_hidden(graph, a) || lod.is_hidden(graph, b))
+}
+
+/// The bend point or edge under `cursor`, bend points first since they sit on top of their edge. Edges are drawn through their bend points, so only the edges the canvas index has near the cursor can have a bend point under it.
+fn edge_hit(
+    graph: &model::GraphModel,
+    cursor: Vec2,
+    tolerance: f32,
+    canvas_index: &CanvasIndex,
+    edges: &Query<(&Edge, &Visible)>,
+    routes: &Query<(&Route, &Visible)>,
+) -> Option<EdgeHit> {
+    let reach = Vec2::splat(tolerance.max(BEND_HANDLE_RADIUS));
+    for entity in canvas_index.0.in_rect(cursor - reach, cursor + reach) {
+        let (edge, visible) = match edges.get(entity) {
+            Ok(found) => found,
+            Err(_) => continue,
+        };
+        let index = match edge.graph_identity {
+            Some(index) if visible.is_visible && graph.contains_edge(index) => index,
+            _ => continue,
+        };
+        for (bend, (x, y)) in graph[index].bends.iter().enumerate() {
+            if cursor.distance(Vec2::new(*x, *y)) <= tolerance.max(BEND_HANDLE_RADIUS) {
+                return Some(EdgeHit::Bend(index, bend));
+            }
+        }
+    }
+
+    let (entity, leg) = edge_under_cursor((cursor.x, cursor.y), tolerance, canvas_index, routes)?;
+    let index = edges.get(entity).ok()?.0.graph_identity?;
+    Some(EdgeHit::Leg(index, leg))
+}
+
+fn edit_bends(
+    mut commands: Commands,
+    buttons: Res<Input<MouseButton>>,
+    time: Res<Time>,
+    windows: Res<Windows>,
+    view: Res<CanvasView>,
+    egui_context: ResMut<EguiContext>,
+    tool_history: Res<ToolHistory>,
+    grid: Res<GridSettings>,
+    canvas_index: Res<CanvasIndex>,
+    placed: Query<(&Placed, &Bounds, &Visible)>,
+    edges: Query<(&Edge, &Visible)>,
+    routes: Query<(&Route, &Visible)>,
+    mut handles: Query<(&BendHandle, &mut Transform)>,
+    mut graph: ResMut<Graph>,
+    mut editor: Local<BendEditor>,
+) {
+    if tool_history.current_tool != Tools::Selector {
+        let_go(&mut handles, &graph.0, &mut editor);
+        return;
+    }
+    let cursor = match adjust_cursor_position(&windows, &view, None) {
+        Some((x, y)) => Vec2::new(x, y),
+        None => return,
+    };
+
+    if buttons.just_pressed(MouseButton::Left) && !egui_context.ctx().is_pointer_over_area() {
+        // Nodes are on top of edges, so a click on a node is never a click on an edge running underneath it.
+        let on_node = matches!(entity_under_cursor((cursor.x, cursor.y), &canvas_index, &placed), Some((_, Tools::Node)));
+        let hit = if on_node {
+            None
+        } else {
+            edge_hit(&graph.0, cursor, EDGE_TOLERANCE * view.scale, &canvas_index, &edges, &routes)
+        };
+
+        let now = time.seconds_since_startup();
+        let double_click = match (hit, editor.last_click) {
+            (Some(hit), Some((last, at))) => hit == last && now - at < DOUBLE_CLICK_SECONDS,
+            _ => false,
+        };
+        editor.last_click = hit.map(|hit| (hit, now));
+
+        match hit {
+            Some(EdgeHit::Bend(edge, bend)) if double_click => {
+                graph.0[edge].bends.remove(bend);
+                forget_edge_drawing(&mut commands, &mut graph.0, edge);
+                editor.last_click = None;
+            }
+            Some(EdgeHit::Bend(edge, bend)) => {
+                editor.dragging = Some((edge, bend));
+            }
+            Some(EdgeHit::Leg(edge, leg)) if double_click => {
+                let point = grid.place(cursor);
+                graph.0[edge].bends.insert(leg, (point.x, point.y));
+                forget_edge_drawing(&mut commands, &mut graph.0, edge);
+                editor.last_click = None;
+            }
+            _ => {}
+        }
+    }
+
+    let (edge, bend) = match editor.dragging {
+        Some(dragging) => dragging,
+        None => return,
+    };
+    if buttons.pressed(MouseButton::Left) {
+        // Until it is let go, only the handle follows the cursor, so that nothing else is drawn again on the way.
+        let point = grid.place(cursor);
+        follow(&mut handles, edge, bend, point);
+        editor.moved_to = Some(point);
+        return;
+    }
+
+    let point = editor.moved_to.take();
+    editor.dragging = None;
+    let current = graph.0.edge_weight(edge).and_then(|weight| weight.bends.get(bend).copied());
+    if let (Some(point), Some(current)) = (point, current) {
+        if current != (point.x, point.y) {
+            graph.0[edge].bends[bend] = (point.x, point.y);
+            forget_edge_drawing(&mut commands, &mut graph.0, edge);
+        }
+    }
+}
+
+/// Moves the handle of bend point `bend` of `edge` to `to`, leaving the graph as it is.
+fn follow(handles: &mut Query<(&BendHandle, &mut Transform)>, edge: EdgeIndex, bend: usize, to: Vec2) {
+    for (handle, mut transform) in handles.iter_mut() {
+        if handle.edge == edge && handle.bend == bend {
+            transform.translation.x = to.x;
+            transform.translation.y = to.y;
+        }
+    }
+}
+
+/// Stops dragging without moving the bend point, putting its handle back where it was if it was already following the cursor.
+fn let_go(handles: &mut Query<(&BendHandle, &mut Transform)>, graph: &model::GraphModel, editor: &mut BendEditor) {
+    if let (Some((edge, bend)), Some(_)) = (editor.dragging, editor.moved_to) {
+        if let Some((x, y)) = graph.edge_weight(edge).and_then(|weight| weight.bends.get(bend).copied()) {
+            follow(handles, edge, bend, Vec2::new(x, y));
+        }
+    }
+    editor.dragging = None;
+    editor.moved_to = None;
+}
@@ -0,0 +1,357 @@
+//! Exporters from the graph model to text formats that can be pasted into design docs and PRs. DOT and Mermaid describe the whole model including nesting; SVG reproduces the canvas as it currently looks, collapsed containers and all.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use bevy::math::Vec2;
+use bevy::render::color::Color;
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use strum_macros::EnumIter;
+
+use crate::geometry;
+use crate::model::{self, GraphModel};
+use crate::theme::{EdgeStyle, Theme};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum ExportFormat {
+    Dot,
+    Mermaid,
+    Svg,
+}
+
+impl ExportFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Dot => "Graphviz DOT",
+            ExportFormat::Mermaid => "Mermaid",
+            ExportFormat::Svg => "SVG",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Dot => "dot",
+            ExportFormat::Mermaid => "mmd",
+            ExportFormat::Svg => "svg",
+        }
+    }
+
+    pub fn export(&self, graph: &GraphModel, theme: &Theme) -> String {
+        match self {
+            ExportFormat::Dot => to_dot(graph),
+            ExportFormat::Mermaid => to_mermaid(graph),
+            ExportFormat::Svg => to_svg(graph, theme),
+        }
+    }
+}
+
+fn node_id(index: NodeIndex) -> String {
+    format!("n{}", index.index())
+}
+
+fn top_level(graph: &GraphModel) -> impl Iterator<Item = NodeIndex> + '_ {
+    graph
+        .node_indices()
+        .filter(move |index| graph[*index].parent().is_none())
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Extra attributes (from an earlier import, for instance) written back out so that they survive a round trip.
+fn dot_properties(properties: &BTreeMap<String, String>) -> String {
+    properties
+        .iter()
+        .map(|(key, value)| format!(", {}=\"{}\"", key, escape_dot(value)))
+        .collect()
+}
+
+/// A Graphviz digraph. Containers become clusters (holding the container node itself, so edges to it still work), and each node carries its kind, uuid and position as attributes.
+pub fn to_dot(graph: &GraphModel) -> String {
+    let mut out = String::from("digraph {\n");
+
+    for index in top_level(graph) {
+        write_dot_node(graph, index, 1, &mut out);
+    }
+
+    for edge in graph.edge_references() {
+        let weight = edge.weight();
+        let _ = writeln!(
+            out,
+            "    {} -> {} [label=\"{}\", kind=\"{:?}\", id=\"{}\"{}];",
+            node_id(edge.source()),
+            node_id(edge.target()),
+            escape_dot(&weight.label),
+            weight.kind,
+            weight.id,
+            dot_properties(&weight.properties)
+        );
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn write_dot_node(graph: &GraphModel, index: NodeIndex, depth: usize, out: &mut String) {
+    let indent = "    ".repeat(depth);
+    let node = &graph[index];
+
+    let mut attributes = format!(
+        "label=\"{}\", kind=\"{:?}\", id=\"{}\"",
+        escape_dot(&node.label),
+        node.kind,
+        node.id
+    );
+    if let Some((x, y)) = node.position {
+        let _ = write!(attributes, ", pos=\"{},{}!\"", x, y);
+    }
+
+    let children = model::children(graph, index);
+    if !node.kind.is_container() || children.is_empty() {
+        attributes.push_str(&dot_properties(&node.properties));
+        let _ = writeln!(out, "{}{} [{}];", indent, node_id(index), attributes);
+        return;
+    }
+
+    // The container is drawn as a folder, whatever shape it was imported with.
+    let mut properties = node.properties.clone();
+    properties.remove("shape");
+    attributes.push_str(&dot_properties(&properties));
+
+    let _ = writeln!(out, "{}subgraph cluster_{} {{", indent, node_id(index));
+    let _ = writeln!(out, "{}    label=\"{}\";", indent, escape_dot(&node.label));
+    let _ = writeln!(out, "{}    {} [{}, shape=folder];", indent, node_id(index), attributes);
+    for child in children {
+        write_dot_node(graph, child, depth + 1, out);
+    }
+    let _ = writeln!(out, "{}}}", indent);
+}
+
+fn escape_mermaid(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+fn mermaid_class(kind: model::NodeKind) -> String {
+    format!("{:?}", kind).to_lowercase()
+}
+
+/// A Mermaid flowchart. Containers become subgraphs and the node kind is attached as a class, with a `class` statement for subgraphs.
+pub fn to_mermaid(graph: &GraphModel) -> String {
+    let mut out = String::from("flowchart TD\n");
+
+    for index in top_level(graph) {
+        write_mermaid_node(graph, index, 1, &mut out);
+    }
+
+    for edge in graph.edge_references() {
+        let label = &edge.weight().label;
+        if label.is_empty() {
+            let _ = writeln!(out, "    {} --> {}", node_id(edge.source()), node_id(edge.target()));
+        } else {
+            let _ = writeln!(
+                out,
+                "    {} -->|\"{}\"| {}",
+                node_id(edge.source()),
+                escape_mermaid(label),
+                node_id(edge.target())
+            );
+        }
+    }
+
+    out
+}
+
+fn write_mermaid_node(graph: &GraphModel, index: NodeIndex, depth: usize, out: &mut String) {
+    let indent = "    ".repeat(depth);
+    let node = &graph[index];
+    let children = model::children(graph, index);
+
+    if !node.kind.is_container() || children.is_empty() {
+        let _ = writeln!(
+            out,
+            "{}{}[\"{}\"]:::{}",
+            indent,
+            node_id(index),
+            escape_mermaid(&node.label),
+            mermaid_class(node.kind)
+        );
+        return;
+    }
+
+    let _ = writeln!(out, "{}subgraph {} [\"{}\"]", indent, node_id(index), escape_mermaid(&node.label));
+    for child in children {
+        write_mermaid_node(graph, child, depth + 1, out);
+    }
+    let _ = writeln!(out, "{}end", indent);
+    // Subgraphs can't take a `:::class`, so the kind of the container follows in a class statement.
+    let _ = writeln!(out, "{}class {} {}", indent, node_id(index), mermaid_class(node.kind));
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn rgb(color: Color) -> String {
+    format!(
+        "rgb({},{},{})",
+        (color.r() * 255.0).round() as u8,
+        (color.g() * 255.0).round() as u8,
+        (color.b() * 255.0).round() as u8
+    )
+}
+
+fn fill(color: Color) -> String {
+    format!("fill=\"{}\" fill-opacity=\"{}\"", rgb(color), color.a())
+}
+
+fn stroke(color: Color) -> String {
+    format!("stroke=\"{}\" stroke-opacity=\"{}\"", rgb(color), color.a())
+}
+
+/// A standalone SVG of the canvas as it currently looks: collapsed containers hide their children and the edges into them are merged, the same as on screen.
+pub fn to_svg(graph: &GraphModel, theme: &Theme) -> String {
+    const MARGIN: f32 = 20.0;
+
+    let (min, max) = geometry::canvas_bounds(graph, theme.node_size).unwrap_or((Vec2::ZERO, Vec2::ZERO));
+    let width = max.x - min.x + 2.0 * MARGIN;
+    let height = max.y - min.y + 2.0 * MARGIN;
+    // The canvas has y pointing up, SVG has it pointing down.
+    let to_svg_space = |point: Vec2| Vec2::new(point.x - min.x + MARGIN, max.y - point.y + MARGIN);
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        width, height, width, height
+    );
+    let _ = writeln!(out, "  <rect width=\"100%\" height=\"100%\" {}/>", fill(theme.background));
+
+    let visible: Vec<NodeIndex> = graph
+        .node_indices()
+        .filter(|index| !model::is_hidden(graph, *index))
+        .collect();
+
+    // Outer containers first so that inner ones and then the nodes are painted over them.
+    let mut containers: Vec<NodeIndex> = visible
+        .iter()
+        .copied()
+        .filter(|index| graph[*index].kind.is_container() && !graph[*index].collapsed)
+        .collect();
+    containers.sort_by_key(|index| model::depth(graph, *index));
+
+    for index in containers {
+        if let Some((node_min, node_max)) = geometry::extent(graph, index, theme.node_size) {
+            let top_left = to_svg_space(Vec2::new(node_min.x, node_max.y));
+            let size = node_max - node_min;
+            let _ = writeln!(
+                out,
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {} {} stroke-width=\"2\"/>",
+                top_left.x,
+                top_left.y,
+                size.x,
+                size.y,
+                fill(theme.node_body(graph[index].kind, false)),
+                stroke(theme.container_stroke)
+            );
+            let _ = writeln!(
+                out,
+                "  <text x=\"{}\" y=\"{}\" font-family=\"DejaVu Sans, sans-serif\" font-size=\"{}\" {}>{}</text>",
+                top_left.x + 4.0,
+                top_left.y + 4.0 + geometry::LABEL_FONT_SIZE,
+                geometry::LABEL_FONT_SIZE,
+                fill(theme.node_style(graph[index].kind).text),
+                escape_xml(&graph[index].label)
+            );
+        }
+    }
+
+    let write_path = |out: &mut String, points: &[Vec2], style: &EdgeStyle, width: f32| {
+        let points: Vec<String> = points
+            .iter()
+            .map(|point| {
+                let point = to_svg_space(*point);
+                format!("{},{}", point.x, point.y)
+            })
+            .collect();
+        let dash = style
+            .dash
+            .map(|[on, off]| format!(" stroke-dasharray=\"{} {}\"", on, off))
+            .unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "  <polyline points=\"{}\" fill=\"none\" {} stroke-width=\"{}\"{}/>",
+            points.join(" "),
+            stroke(style.color),
+            width,
+            dash
+        );
+    };
+
+    for edge in graph.edge_references() {
+        if model::is_hidden(graph, edge.source()) || model::is_hidden(graph, edge.target()) {
+            continue;
+        }
+        let style = theme.edge_style(edge.weight().kind);
+        if let Some(legs) = geometry::edge_route(graph, edge.id(), theme.node_size) {
+            let points = geometry::flatten(&legs);
+            write_path(&mut out, &points, style, style.width);
+
+            if let (false, Some(middle)) = (edge.weight().label.is_empty(), geometry::midpoint(&points)) {
+                let middle = to_svg_space(middle);
+                let _ = writeln!(
+                    out,
+                    "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-family=\"DejaVu Sans, sans-serif\" font-size=\"{}\" {}>{}</text>",
+                    middle.x,
+                    middle.y - 2.0,
+                    geometry::LABEL_FONT_SIZE * 0.85,
+                    fill(style.color),
+                    escape_xml(&edge.weight().label)
+                );
+            }
+        }
+    }
+    for aggregated in model::aggregated_edges(graph) {
+        if let Some((source, target)) = geometry::clipped_segment(graph, aggregated.source, aggregated.target, theme.node_size) {
+            write_path(&mut out, &[source, target], &theme.edge, 1.0 + aggregated.count as f32);
+        }
+    }
+
+    for index in visible {
+        let node = &graph[index];
+        if node.kind.is_container() && !node.collapsed {
+            continue;
+        }
+        if let Some((node_min, node_max)) = geometry::extent(graph, index, theme.node_size) {
+            let top_left = to_svg_space(Vec2::new(node_min.x, node_max.y));
+            let size = node_max - node_min;
+            let body = theme.node_body(node.kind, node.collapsed);
+            let _ = writeln!(
+                out,
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {} {}/>",
+                top_left.x,
+                top_left.y,
+                size.x,
+                size.y,
+                fill(body),
+                stroke(theme.node_style(node.kind).stroke)
+            );
+            let _ = writeln!(
+                out,
+                "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\" font-family=\"DejaVu Sans, sans-serif\" font-size=\"{}\" {}>{}</text>",
+                top_left.x + size.x / 2.0,
+                top_left.y + size.y / 2.0,
+                geometry::LABEL_FONT_SIZE,
+                fill(theme.node_style(node.kind).text),
+                escape_xml(&node.label)
+            );
+        }
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
@@ -0,0 +1,59 @@
+//! The egui Export window. The exporters themselves live in the library (`export`) so that they can be used without the editor.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use strum::IntoEnumIterator;
+
+use crate::export::ExportFormat;
+use crate::theme::Theme;
+use crate::Graph;
+
+/// The last export, kept around so that it can be copied out of the window or saved.
+#[derive(Default)]
+struct ExportState {
+    format: Option<ExportFormat>,
+    text: String,
+}
+
+pub struct ExportMenuPlugin;
+
+impl Plugin for ExportMenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(ExportState::default())
+            .add_system(export_menu.system());
+    }
+}
+
+fn export_menu(
+    egui_context: ResMut<EguiContext>,
+    graph: Res<Graph>,
+    theme: Res<Theme>,
+    mut state: ResMut<ExportState>,
+) {
+    egui::Window::new("Export").show(egui_context.ctx(), |ui| {
+        ui.horizontal(|ui| {
+            for format in ExportFormat::iter() {
+                if ui.button(format.name()).clicked() {
+                    state.text = format.export(&graph.0, &theme);
+                    state.format = Some(format);
+                }
+            }
+        });
+
+        if let Some(format) = state.format {
+            ui.separator();
+            // Saving only makes sense natively, in the browser the text can be copied out of the box below.
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let file_name = format!("diagram.{}", format.extension());
+                if ui.button(format!("Save to {}", file_name)).clicked() {
+                    match std::fs::write(&file_name, &state.text) {
+                        Ok(()) => info!("Exported the diagram to {}", file_name),
+                        Err(error) => error!("Couldn't write {}: {}", file_name, error),
+                    }
+                }
+            }
+            ui.add(egui::TextEdit::multiline(&mut state.text).desired_rows(10));
+        }
+    });
+}
@@ -0,0 +1,146 @@
+//! The Features window. A crate's features and the items behind a `cfg` (see `features`) are read from its `Cargo.toml` and sources, and picking features and a target grays out whatever that leaves out of the build.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use strum::IntoEnumIterator;
+
+use crate::features::{self, Manifest, Selection, Target};
+use crate::layout;
+use crate::model;
+use crate::rust_source::{self, SourceFile};
+use crate::theme::Theme;
+use crate::{forget_drawing, Graph};
+
+struct FeatureState {
+    manifest_path: String,
+    sources_path: String,
+    pasted: String,
+    /// The manifest the last view was built from, which the selection is made against.
+    manifest: Option<Manifest>,
+    selection: Selection,
+    /// The outcome of the last build, shown underneath the buttons.
+    status: String,
+}
+
+impl Default for FeatureState {
+    fn default() -> Self {
+        FeatureState {
+            manifest_path: String::from("Cargo.toml"),
+            sources_path: String::from("src"),
+            pasted: String::new(),
+            manifest: None,
+            selection: Selection::default(),
+            status: String::new(),
+        }
+    }
+}
+
+pub struct FeatureMenuPlugin;
+
+impl Plugin for FeatureMenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(FeatureState::default())
+            .add_system(feature_menu.system());
+    }
+}
+
+fn feature_menu(
+    mut commands: Commands,
+    egui_context: ResMut<EguiContext>,
+    mut graph: ResMut<Graph>,
+    theme: Res<Theme>,
+    mut state: ResMut<FeatureState>,
+) {
+    let mut read: Option<(String, Vec<SourceFile>)> = None;
+    let mut selection = state.selection.clone();
+
+    egui::Window::new("Features").show(egui_context.ctx(), |ui| {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.horizontal(|ui| {
+                ui.label("Manifest");
+                ui.text_edit_singleline(&mut state.manifest_path);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Sources");
+                ui.text_edit_singleline(&mut state.sources_path);
+            });
+            if ui.button("Read crate").clicked() {
+                let sources = rust_source::read_sources(std::path::Path::new(&state.sources_path));
+                match (std::fs::read_to_string(&state.manifest_path), sources) {
+                    (Ok(manifest), Ok(files)) => read = Some((manifest, files)),
+                    (Err(error), _) => state.status = format!("Couldn't read {}: {}", state.manifest_path, error),
+                    (_, Err(error)) => state.status = format!("Couldn't read {}: {}", state.sources_path, error),
+                }
+            }
+        }
+
+        ui.label("Or paste a Cargo.toml:");
+        ui.add(egui::TextEdit::multiline(&mut state.pasted).desired_rows(6));
+        if ui.button("Build from pasted manifest").clicked() {
+            read = Some((state.pasted.clone(), Vec::new()));
+        }
+
+        if let Some(manifest) = &state.manifest {
+            ui.separator();
+            ui.checkbox(&mut selection.default_features, "Default features");
+            for feature in manifest.features.keys().filter(|feature| *feature != "default") {
+                let mut enabled = selection.features.contains(feature);
+                if ui.checkbox(&mut enabled, feature).changed() {
+                    if enabled {
+                        selection.features.insert(feature.clone());
+                    } else {
+                        selection.features.remove(feature);
+                    }
+                }
+            }
+            ui.horizontal(|ui| {
+                for target in Target::iter() {
+                    if ui.selectable_label(selection.target == target, target.name()).clicked() {
+                        selection.target = target;
+                    }
+                }
+            });
+        }
+
+        if !state.status.is_empty() {
+            ui.label(state.status.clone());
+        }
+    });
+
+    let mut apply = selection != state.selection;
+    if let Some((manifest, files)) = read {
+        let built = Manifest::parse(&manifest)
+            .map_err(|error| error.to_string())
+            .and_then(|manifest| {
+                let parsed = rust_source::parse(&files).map_err(|error| error.to_string())?;
+                Ok((features::build(&manifest, &parsed), manifest))
+            });
+        match built {
+            Ok((view, manifest)) => {
+                // Laying out after merging keeps the new nodes clear of whatever is already on the canvas.
+                model::merge(&mut graph.0, &view);
+                layout::layout_unpositioned(&mut graph.0, theme.node_size);
+                state.status = format!(
+                    "Read {} features and {} files into {} nodes.",
+                    manifest.features.len(),
+                    files.len(),
+                    view.node_count()
+                );
+                state.manifest = Some(manifest);
+                apply = true;
+            }
+            Err(error) => state.status = format!("Couldn't build the view: {}", error),
+        }
+    }
+
+    if apply {
+        state.selection = selection;
+        if let Some(manifest) = &state.manifest {
+            // Excluded nodes are drawn differently, so the ones that changed are drawn again.
+            for index in features::apply(&mut graph.0, manifest, &state.selection) {
+                forget_drawing(&mut commands, &mut graph.0, index);
+            }
+        }
+    }
+}
@@ -0,0 +1,651 @@
+//! The feature view: a crate's Cargo features with `Enables` edges to the features and dependencies they turn on, and the items of its sources that are behind a `#[cfg(...)]`, with `Gates` edges from the features and other conditions (`target_arch = "wasm32"`) they depend on. `apply` marks everything a choice of features and target leaves out of the build with `model::EXCLUDED_PROPERTY`, which the canvas draws grayed out.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use petgraph::stable_graph::NodeIndex;
+use quote::ToTokens;
+use strum_macros::EnumIter;
+use syn::visit::{self, Visit};
+
+use crate::model::{self, EdgeKind, GraphModel, NodeKind};
+use crate::rust_source::{for_each_item, ParsedFile, ViewBuilder};
+
+/// The property naming the feature a node stands for.
+pub const FEATURE_PROPERTY: &str = "feature";
+
+/// The property naming the dependency a node stands for.
+pub const DEPENDENCY_PROPERTY: &str = "dependency";
+
+/// The property holding the `cfg` condition an item is behind, as written between the parentheses of `#[cfg(...)]`.
+pub const CFG_PROPERTY: &str = "cfg";
+
+/// Statements behind a `cfg` are labelled with their beginning, up to this many characters.
+const STATEMENT_LABEL_LENGTH: usize = 40;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestError(pub String);
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    pub name: String,
+    pub optional: bool,
+    /// The condition of the `[target.'cfg(...)'.dependencies]` table it is in, if any.
+    pub cfg: Option<String>,
+}
+
+/// What the feature view needs to know from a `Cargo.toml`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    pub features: BTreeMap<String, Vec<String>>,
+    pub dependencies: Vec<Dependency>,
+}
+
+/// What a feature can turn on, going by one entry of its list.
+enum Enabled<'a> {
+    Feature(&'a str),
+    /// A dependency, and the feature of it that is turned on along with it, if any.
+    Dependency(&'a str, Option<&'a str>),
+    /// A feature of a dependency that is only turned on if something else turns on the dependency (`dep?/feature`).
+    WeakFeature(&'a str, &'a str),
+}
+
+impl Manifest {
+    pub fn parse(source: &str) -> Result<Manifest, ManifestError> {
+        let value: toml::Value = toml::from_str(source).map_err(|error| ManifestError(error.to_string()))?;
+        let name = value
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(toml::Value::as_str)
+            .unwrap_or("crate")
+            .to_string();
+
+        let mut features: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        if let Some(table) = value.get("features").and_then(toml::Value::as_table) {
+            for (feature, enables) in table {
+                let enables: Vec<String> = enables
+                    .as_array()
+                    .map(|enables| enables.iter().filter_map(|entry| entry.as_str().map(str::to_string)).collect())
+                    .unwrap_or_default();
+                features.insert(feature.clone(), enables);
+            }
+        }
+
+        let mut dependencies = Vec::new();
+        let mut read_dependencies = |table: Option<&toml::Value>, cfg: Option<String>| {
+            for (name, dependency) in table.and_then(toml::Value::as_table).into_iter().flatten() {
+                let optional = dependency
+                    .get("optional")
+                    .and_then(toml::Value::as_bool)
+                    .unwrap_or(false);
+                dependencies.push(Dependency {
+                    name: name.clone(),
+                    optional,
+                    cfg: cfg.clone(),
+                });
+            }
+        };
+        read_dependencies(value.get("dependencies"), None);
+        if let Some(targets) = value.get("target").and_then(toml::Value::as_table) {
+            for (target, tables) in targets {
+                // Plain target triples aren't conditions that can be evaluated, so those dependencies are shown as if they were always there.
+                let cfg = target
+                    .strip_prefix("cfg(")
+                    .and_then(|cfg| cfg.strip_suffix(')'))
+                    .map(str::to_string);
+                read_dependencies(tables.get("dependencies"), cfg);
+            }
+        }
+
+        Ok(Manifest {
+            name,
+            features,
+            dependencies,
+        })
+    }
+
+    pub fn dependency(&self, name: &str) -> Option<&Dependency> {
+        self.dependencies.iter().find(|dependency| dependency.name == name)
+    }
+
+    fn enabled<'a>(&self, entry: &'a str) -> Enabled<'a> {
+        if let Some(dependency) = entry.strip_prefix("dep:") {
+            return Enabled::Dependency(dependency, None);
+        }
+        match entry.find('/') {
+            Some(slash) => {
+                let (dependency, feature) = (&entry[..slash], &entry[slash + 1..]);
+                match dependency.strip_suffix('?') {
+                    Some(dependency) => Enabled::WeakFeature(dependency, feature),
+                    None => Enabled::Dependency(dependency, Some(feature)),
+                }
+            }
+            None if self.features.contains_key(entry) => Enabled::Feature(entry),
+            // Optional dependencies are features of their own unless a feature of the same name says otherwise.
+            None => Enabled::Dependency(entry, None),
+        }
+    }
+
+    /// Every feature and optional dependency turned on by `selection`, directly or through other features.
+    pub fn resolve(&self, selection: &Selection) -> (BTreeSet<String>, BTreeSet<String>) {
+        let mut features = BTreeSet::new();
+        let mut dependencies = BTreeSet::new();
+        let mut pending: Vec<&str> = selection.features.iter().map(String::as_str).collect();
+        if selection.default_features {
+            pending.push("default");
+        }
+
+        while let Some(feature) = pending.pop() {
+            if !features.insert(feature.to_string()) {
+                continue;
+            }
+            for entry in self.features.get(feature).into_iter().flatten() {
+                match self.enabled(entry) {
+                    Enabled::Feature(feature) => pending.push(feature),
+                    Enabled::Dependency(dependency, _) => {
+                        dependencies.insert(dependency.to_string());
+                        if self.features.contains_key(dependency) {
+                            pending.push(dependency);
+                        }
+                    }
+                    Enabled::WeakFeature(..) => {}
+                }
+            }
+        }
+        for feature in &features {
+            if self.dependency(feature).map_or(false, |dependency| dependency.optional) {
+                dependencies.insert(feature.clone());
+            }
+        }
+        (features, dependencies)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum Target {
+    /// Whatever the editor itself was built for.
+    Native,
+    Web,
+}
+
+impl Target {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Target::Native => "Native",
+            Target::Web => "Web (wasm32)",
+        }
+    }
+
+    /// The value of a `cfg` key such as `target_arch` for this target, if it has one.
+    fn value(&self, key: &str) -> Option<&'static str> {
+        match (self, key) {
+            (Target::Native, "target_arch") => Some(std::env::consts::ARCH),
+            (Target::Native, "target_os") => Some(std::env::consts::OS),
+            (Target::Native, "target_family") => Some(std::env::consts::FAMILY),
+            (Target::Web, "target_arch") => Some("wasm32"),
+            (Target::Web, "target_os") => Some("unknown"),
+            (Target::Web, "target_family") => Some("wasm"),
+            _ => None,
+        }
+    }
+}
+
+/// A choice of features and target to see what is left of the crate with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub features: BTreeSet<String>,
+    pub default_features: bool,
+    pub target: Target,
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Selection {
+            features: BTreeSet::new(),
+            default_features: true,
+            target: Target::Native,
+        }
+    }
+}
+
+/// Whether the `cfg` condition `predicate` holds with `features` turned on, for `target`.
+pub fn cfg_holds(predicate: &syn::NestedMeta, features: &BTreeSet<String>, target: Target) -> bool {
+    let meta = match predicate {
+        syn::NestedMeta::Meta(meta) => meta,
+        syn::NestedMeta::Lit(_) => return false,
+    };
+    match meta {
+        syn::Meta::Path(path) => match path.get_ident().map(|ident| ident.to_string()) {
+            Some(family) if matches!(family.as_str(), "unix" | "windows" | "wasm") => {
+                target.value("target_family") == Some(family.as_str())
+            }
+            Some(name) => name == "debug_assertions",
+            None => false,
+        },
+        syn::Meta::NameValue(pair) => {
+            let value = match &pair.lit {
+                syn::Lit::Str(value) => value.value(),
+                _ => return false,
+            };
+            match pair.path.get_ident().map(|ident| ident.to_string()).as_deref() {
+                Some("feature") => features.contains(&value),
+                Some(key) => target.value(key) == Some(value.as_str()),
+                None => false,
+            }
+        }
+        syn::Meta::List(list) => {
+            let mut nested = list.nested.iter();
+            match list.path.get_ident().map(|ident| ident.to_string()).as_deref() {
+                Some("all") => nested.all(|predicate| cfg_holds(predicate, features, target)),
+                Some("any") => nested.any(|predicate| cfg_holds(predicate, features, target)),
+                Some("not") => !nested.next().map_or(false, |predicate| cfg_holds(predicate, features, target)),
+                _ => false,
+            }
+        }
+    }
+}
+
+/// The condition of a `#[cfg(...)]` among `attributes`, if there is one.
+fn cfg_predicate(attributes: &[syn::Attribute]) -> Option<syn::NestedMeta> {
+    attributes.iter().filter(|attribute| attribute.path.is_ident("cfg")).find_map(|attribute| {
+        match attribute.parse_meta().ok()? {
+            syn::Meta::List(list) => list.nested.into_iter().next(),
+            _ => None,
+        }
+    })
+}
+
+/// The conditions `predicate` is made of, without the `all`, `any` and `not` around them.
+fn cfg_atoms(predicate: &syn::NestedMeta, atoms: &mut Vec<syn::Meta>) {
+    match predicate {
+        syn::NestedMeta::Meta(syn::Meta::List(list)) => {
+            for nested in &list.nested {
+                cfg_atoms(nested, atoms);
+            }
+        }
+        syn::NestedMeta::Meta(meta) => atoms.push(meta.clone()),
+        syn::NestedMeta::Lit(_) => {}
+    }
+}
+
+fn expression_attributes(expression: &syn::Expr) -> &[syn::Attribute] {
+    match expression {
+        syn::Expr::Call(call) => &call.attrs,
+        syn::Expr::MethodCall(call) => &call.attrs,
+        syn::Expr::Macro(call) => &call.attrs,
+        syn::Expr::Assign(assign) => &assign.attrs,
+        syn::Expr::Block(block) => &block.attrs,
+        syn::Expr::If(expression) => &expression.attrs,
+        syn::Expr::Path(path) => &path.attrs,
+        _ => &[],
+    }
+}
+
+/// The statements behind a `cfg` in a function body, with their conditions.
+#[derive(Default)]
+struct GatedStatements {
+    found: Vec<(syn::NestedMeta, String)>,
+}
+
+impl<'ast> Visit<'ast> for GatedStatements {
+    fn visit_stmt(&mut self, statement: &'ast syn::Stmt) {
+        let attributes = match statement {
+            syn::Stmt::Local(local) => &local.attrs[..],
+            syn::Stmt::Expr(expression) | syn::Stmt::Semi(expression, _) => expression_attributes(expression),
+            syn::Stmt::Item(_) => &[],
+        };
+        if let Some(predicate) = cfg_predicate(attributes) {
+            let mut text = statement.to_token_stream().to_string();
+            // The attribute itself comes first in the tokens, and the condition is shown by the edges anyway.
+            if let Some(end) = text.find("] ") {
+                text = text[end + 2..].to_string();
+            }
+            if text.chars().count() > STATEMENT_LABEL_LENGTH {
+                text = format!("{}…", text.chars().take(STATEMENT_LABEL_LENGTH).collect::<String>());
+            }
+            self.found.push((predicate, text));
+        }
+        visit::visit_stmt(self, statement);
+    }
+}
+
+fn item_details(item: &syn::Item) -> Option<(&[syn::Attribute], String, NodeKind)> {
+    Some(match item {
+        syn::Item::Fn(item) => (&item.attrs[..], item.sig.ident.to_string(), NodeKind::Function),
+        syn::Item::Struct(item) => (&item.attrs[..], item.ident.to_string(), NodeKind::Struct),
+        syn::Item::Enum(item) => (&item.attrs[..], item.ident.to_string(), NodeKind::Enum),
+        syn::Item::Trait(item) => (&item.attrs[..], item.ident.to_string(), NodeKind::Trait),
+        syn::Item::Mod(item) => (&item.attrs[..], format!("mod {}", item.ident), NodeKind::Item),
+        syn::Item::Use(item) => (&item.attrs[..], item.tree.to_token_stream().to_string(), NodeKind::Item),
+        syn::Item::Const(item) => (&item.attrs[..], item.ident.to_string(), NodeKind::Item),
+        syn::Item::Static(item) => (&item.attrs[..], item.ident.to_string(), NodeKind::Item),
+        syn::Item::Impl(item) => (
+            &item.attrs[..],
+            format!("impl {}", item.self_ty.to_token_stream()),
+            NodeKind::Item,
+        ),
+        _ => return None,
+    })
+}
+
+fn feature_node(view: &mut ViewBuilder, name: &str) -> NodeIndex {
+    let index = view.node(&format!("feature {}", name), name, NodeKind::Feature);
+    view.graph[index]
+        .properties
+        .insert(FEATURE_PROPERTY.to_string(), name.to_string());
+    index
+}
+
+fn dependency_node(view: &mut ViewBuilder, manifest: &Manifest, name: &str) -> NodeIndex {
+    let index = view.node(&format!("dependency {}", name), name, NodeKind::Dependency);
+    let properties = &mut view.graph[index].properties;
+    properties.insert(DEPENDENCY_PROPERTY.to_string(), name.to_string());
+    if let Some(cfg) = manifest.dependency(name).and_then(|dependency| dependency.cfg.clone()) {
+        properties.insert(CFG_PROPERTY.to_string(), cfg);
+    }
+    index
+}
+
+/// Gates `gated` behind the conditions of `predicate`, by edges from the features and other conditions it is made of.
+fn gate(view: &mut ViewBuilder, gated: NodeIndex, predicate: &syn::NestedMeta) {
+    view.graph[gated]
+        .properties
+        .insert(CFG_PROPERTY.to_string(), predicate.to_token_stream().to_string());
+    let mut atoms = Vec::new();
+    cfg_atoms(predicate, &mut atoms);
+    for atom in atoms {
+        let condition = match &atom {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                path,
+                lit: syn::Lit::Str(feature),
+                ..
+            }) if path.is_ident("feature") => feature_node(view, &feature.value()),
+            _ => {
+                let text = atom.to_token_stream().to_string();
+                let index = view.node(&format!("cfg {}", text), &text, NodeKind::Cfg);
+                view.graph[index].properties.insert(CFG_PROPERTY.to_string(), text);
+                index
+            }
+        };
+        view.edge(condition, gated, EdgeKind::Gates, "");
+    }
+}
+
+/// The features of `manifest` and what they enable, and the items of `files` that are behind a `cfg`, all inside a container for the crate.
+pub fn build(manifest: &Manifest, files: &[ParsedFile]) -> GraphModel {
+    let mut view = ViewBuilder::default();
+    let krate = view.module(&manifest.name, &[]);
+
+    for (feature, entries) in &manifest.features {
+        let index = feature_node(&mut view, feature);
+        model::set_parent(&mut view.graph, index, Some(krate));
+        for entry in entries {
+            let (target, label) = match manifest.enabled(entry) {
+                Enabled::Feature(enabled) => (feature_node(&mut view, enabled), ""),
+                Enabled::Dependency(dependency, feature) => {
+                    (dependency_node(&mut view, manifest, dependency), feature.unwrap_or(""))
+                }
+                Enabled::WeakFeature(dependency, feature) => (dependency_node(&mut view, manifest, dependency), feature),
+            };
+            view.edge(index, target, EdgeKind::Enables, label);
+        }
+    }
+    // Optional and target specific dependencies are the ones that come and go with the selection, the others are always there.
+    for dependency in &manifest.dependencies {
+        if dependency.optional || dependency.cfg.is_some() {
+            dependency_node(&mut view, manifest, &dependency.name);
+        }
+    }
+
+    for file in files {
+        for_each_item(file, |module, item| {
+            let (attributes, label, kind) = match item_details(item) {
+                Some(details) => details,
+                None => return,
+            };
+            let mut statements = GatedStatements::default();
+            if let syn::Item::Fn(function) = item {
+                statements.visit_block(&function.block);
+            }
+            let predicate = cfg_predicate(attributes);
+            if predicate.is_none() && statements.found.is_empty() {
+                return;
+            }
+
+            let container = view.module(&manifest.name, module);
+            let key = format!("item {}::{}::{}", manifest.name, module.join("::"), label);
+            let index = view.node(&key, &label, kind);
+            model::set_parent(&mut view.graph, index, Some(container));
+            view.locate(index, file.location(item));
+            if let Some(predicate) = predicate {
+                gate(&mut view, index, &predicate);
+            }
+
+            for (predicate, text) in statements.found {
+                let key = format!("{} {}", key, text);
+                let statement = view.node(&key, &text, NodeKind::Item);
+                model::set_parent(&mut view.graph, statement, Some(container));
+                gate(&mut view, statement, &predicate);
+                view.edge(index, statement, EdgeKind::Link, "");
+            }
+        });
+    }
+
+    view.graph
+}
+
+/// Marks everything in `graph` that `selection` leaves out of the build with `model::EXCLUDED_PROPERTY`, and clears it from everything else: features that aren't turned on, optional dependencies nothing turns on, whatever is behind a `cfg` that doesn't hold, and the conditions that don't. Returns the nodes that changed.
+pub fn apply(graph: &mut GraphModel, manifest: &Manifest, selection: &Selection) -> Vec<NodeIndex> {
+    let (features, dependencies) = manifest.resolve(selection);
+    let mut changed = Vec::new();
+    for index in graph.node_indices().collect::<Vec<_>>() {
+        let properties = &graph[index].properties;
+        let feature_off = properties
+            .get(FEATURE_PROPERTY)
+            .map_or(false, |feature| !features.contains(feature));
+        let dependency_off = properties.get(DEPENDENCY_PROPERTY).map_or(false, |name| {
+            manifest.dependency(name).map_or(false, |dependency| dependency.optional) && !dependencies.contains(name)
+        });
+        let cfg_off = properties
+            .get(CFG_PROPERTY)
+            .and_then(|cfg| syn::parse_str::<syn::NestedMeta>(cfg).ok())
+            .map_or(false, |predicate| !cfg_holds(&predicate, &features, selection.target));
+        let excluded = feature_off || dependency_off || cfg_off;
+
+        if graph[index].is_excluded() != excluded {
+            graph[index].set_excluded(excluded);
+            changed.push(index);
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rust_source::parse_sources;
+
+    /// The features of this project's own manifest, with a few more to cover the other ways of enabling things.
+    const MANIFEST: &str = r#"
+        [package]
+        name = "app"
+
+        [features]
+        default = ["bevy/bevy_winit", "bevy/render"]
+        native = ["bevy/bevy_wgpu"]
+        web = ["bevy_webgl2"]
+        sound = ["dep:rodio", "extra"]
+        extra = ["serde?/derive"]
+
+        [dependencies]
+        bevy = { version = "0.5", default-features = false }
+        bevy_webgl2 = { version = "0.5", optional = true }
+        rodio = { version = "0.14", optional = true }
+        serde = { version = "1.0", optional = true }
+
+        [target.'cfg(target_arch = "wasm32")'.dependencies]
+        wasm-bindgen = "0.2"
+    "#;
+
+    const MAIN: &str = r#"
+        #[cfg(target_arch = "wasm32")]
+        use bevy_webgl2::*;
+
+        #[cfg(feature = "native")]
+        fn native_only() {}
+
+        fn main() {
+            #[cfg(target_arch = "wasm32")]
+            web();
+            #[cfg(not(target_arch = "wasm32"))]
+            native();
+        }
+    "#;
+
+    fn manifest() -> Manifest {
+        Manifest::parse(MANIFEST).unwrap()
+    }
+
+    fn selection(features: &[&str], default_features: bool, target: Target) -> Selection {
+        Selection {
+            features: features.iter().map(|feature| feature.to_string()).collect(),
+            default_features,
+            target,
+        }
+    }
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn holds(predicate: &str, features: &[&str], target: Target) -> bool {
+        cfg_holds(&syn::parse_str(predicate).unwrap(), &names(features), target)
+    }
+
+    /// The labels of the nodes `graph` has marked as excluded, or not.
+    fn labels(graph: &GraphModel, excluded: bool) -> Vec<String> {
+        let mut labels: Vec<String> = graph
+            .node_weights()
+            .filter(|node| !node.kind.is_container() && node.is_excluded() == excluded)
+            .map(|node| node.label.clone())
+            .collect();
+        labels.sort();
+        labels
+    }
+
+    #[test]
+    fn parse_manifest() {
+        let manifest = manifest();
+        assert_eq!(manifest.name, "app");
+        assert_eq!(
+            manifest.features.keys().collect::<Vec<_>>(),
+            vec!["default", "extra", "native", "sound", "web"]
+        );
+        assert_eq!(manifest.features["web"], vec!["bevy_webgl2"]);
+        assert!(!manifest.dependency("bevy").unwrap().optional);
+        assert!(manifest.dependency("bevy_webgl2").unwrap().optional);
+        let wasm = manifest.dependency("wasm-bindgen").unwrap();
+        assert_eq!(wasm.cfg.as_deref(), Some(r#"target_arch = "wasm32""#));
+        assert!(Manifest::parse("[features").is_err());
+    }
+
+    #[test]
+    fn enabled_entries() {
+        let manifest = manifest();
+        assert!(matches!(manifest.enabled("native"), Enabled::Feature("native")));
+        assert!(matches!(manifest.enabled("bevy_webgl2"), Enabled::Dependency("bevy_webgl2", None)));
+        assert!(matches!(manifest.enabled("dep:rodio"), Enabled::Dependency("rodio", None)));
+        assert!(matches!(manifest.enabled("bevy/render"), Enabled::Dependency("bevy", Some("render"))));
+        assert!(matches!(manifest.enabled("serde?/derive"), Enabled::WeakFeature("serde", "derive")));
+    }
+
+    #[test]
+    fn resolve_features() {
+        let manifest = manifest();
+        assert_eq!(
+            manifest.resolve(&selection(&[], true, Target::Native)),
+            (names(&["default"]), names(&["bevy"]))
+        );
+        assert_eq!(
+            manifest.resolve(&selection(&["web"], true, Target::Web)),
+            (names(&["default", "web"]), names(&["bevy", "bevy_webgl2"]))
+        );
+        // A weak feature doesn't turn its dependency on.
+        assert_eq!(
+            manifest.resolve(&selection(&["sound"], false, Target::Native)),
+            (names(&["extra", "sound"]), names(&["rodio"]))
+        );
+        // An optional dependency is a feature of its own.
+        assert_eq!(
+            manifest.resolve(&selection(&["serde"], false, Target::Native)),
+            (names(&["serde"]), names(&["serde"]))
+        );
+    }
+
+    #[test]
+    fn cfg_conditions() {
+        let wasm = r#"target_arch = "wasm32""#;
+        assert!(holds(wasm, &[], Target::Web));
+        assert!(!holds(wasm, &[], Target::Native));
+        assert!(holds(r#"not(target_arch = "wasm32")"#, &[], Target::Native));
+        assert!(holds(r#"feature = "web""#, &["web"], Target::Native));
+        assert!(!holds(r#"feature = "web""#, &["native"], Target::Web));
+        assert!(holds(r#"all(feature = "web", target_arch = "wasm32")"#, &["web"], Target::Web));
+        assert!(!holds(r#"all(feature = "web", target_arch = "wasm32")"#, &["web"], Target::Native));
+        assert!(holds(r#"any(feature = "native", target_arch = "wasm32")"#, &[], Target::Web));
+        assert!(!holds(r#"any(feature = "native", target_arch = "wasm32")"#, &[], Target::Native));
+        assert!(holds("wasm", &[], Target::Web));
+        assert!(!holds("wasm", &[], Target::Native));
+        assert!(holds("debug_assertions", &[], Target::Web));
+        assert!(!holds(r#"target_os = "unknown""#, &[], Target::Native));
+    }
+
+    #[test]
+    fn apply_native_and_web() {
+        let manifest = manifest();
+        let files = parse_sources(&[("app/src/main.rs", MAIN)]);
+        let mut graph = build(&manifest, &files);
+        let wasm = r#"target_arch = "wasm32""#;
+
+        let native = selection(&["native"], true, Target::Native);
+        assert!(!apply(&mut graph, &manifest, &native).is_empty());
+        assert_eq!(
+            labels(&graph, true),
+            vec![
+                "bevy_webgl2",
+                "bevy_webgl2 :: *",
+                "extra",
+                "rodio",
+                "serde",
+                "sound",
+                wasm,
+                "wasm-bindgen",
+                "web",
+                "web () ;",
+            ]
+        );
+        assert_eq!(
+            labels(&graph, false),
+            vec!["bevy", "default", "main", "native", "native () ;", "native_only"]
+        );
+        // Nothing changes when the selection doesn't.
+        assert!(apply(&mut graph, &manifest, &native).is_empty());
+
+        let web = selection(&["web"], true, Target::Web);
+        let changed = apply(&mut graph, &manifest, &web);
+        assert_eq!(changed.len(), 9);
+        assert_eq!(
+            labels(&graph, true),
+            vec!["extra", "native", "native () ;", "native_only", "rodio", "serde", "sound"]
+        );
+    }
+}
@@ -0,0 +1,641 @@
+//! Where the nodes of the graph model end up on the canvas. Shared between the canvas itself and the exporters so that an exported picture matches what is on screen.
+
+use bevy::math::Vec2;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+
+use crate::model::{self, GraphModel, Routing};
+
+/// Space left between a container's frame and the children inside of it.
+pub const GROUP_PADDING: f32 = 25.0;
+
+/// Space kept between an orthogonally routed edge and the nodes it goes around.
+pub const ROUTING_MARGIN: f32 = 10.0;
+
+/// Distance between the middles of neighbouring parallel edges.
+pub const PARALLEL_SPACING: f32 = 16.0;
+
+/// How far the smallest self-loop reaches out of its node. Every further loop on the same node reaches out this much more.
+pub const LOOP_SIZE: f32 = 20.0;
+
+/// Number of straight pieces a curved edge is drawn with.
+const CURVE_SEGMENTS: usize = 16;
+
+/// Font size of node labels, in canvas units.
+pub const LABEL_FONT_SIZE: f32 = 14.0;
+
+/// Space left on either side of a label inside its node.
+pub const LABEL_PADDING: f32 = 8.0;
+
+/// Roughly how wide `label` is when drawn at `LABEL_FONT_SIZE`. The text isn't laid out until after the node is drawn, so the node is sized from the average advance of the label font (DejaVu Sans) instead.
+pub fn label_width(label: &str) -> f32 {
+    label.chars().count() as f32 * LABEL_FONT_SIZE * 0.6
+}
+
+/// Width and height of a node with `label`: at least `node_size` square, and wide enough for the label.
+pub fn node_dimensions(label: &str, node_size: f32) -> Vec2 {
+    Vec2::new(node_size.max(label_width(label) + 2.0 * LABEL_PADDING), node_size)
+}
+
+/// The rectangle taken up by `index` on the canvas, as (min, max) corners.
+pub fn extent(graph: &GraphModel, index: NodeIndex, node_size: f32) -> Option<(Vec2, Vec2)> {
+    let node = graph.node_weight(index)?;
+    let half = node_dimensions(&node.label, node_size) / 2.0;
+
+    if node.kind.is_container() {
+        let (min, max) = expanded_extent(graph, index, node_size)?;
+        if node.collapsed {
+            let center = (min + max) / 2.0;
+            return Some((center - half, center + half));
+        }
+        return Some((min, max));
+    }
+
+    let (x, y) = node.position?;
+    let center = Vec2::new(x, y);
+    Some((center - half, center + half))
+}
+
+/// Where a node's visible representative is drawn.
+pub fn center(graph: &GraphModel, index: NodeIndex, node_size: f32) -> Option<Vec2> {
+    extent(graph, index, node_size).map(|(min, max)| (min + max) / 2.0)
+}
+
+/// The extent a container has when it is expanded, whether or not it currently is. A collapsed container sits in the middle of this so that collapsing and expanding doesn't make it jump around.
+fn expanded_extent(graph: &GraphModel, index: NodeIndex, node_size: f32) -> Option<(Vec2, Vec2)> {
+    let mut bounds: Option<(Vec2, Vec2)> = None;
+
+    for child in model::children(graph, index) {
+        if let Some((child_min, child_max)) = extent(graph, child, node_size) {
+            bounds = Some(match bounds {
+                Some((min, max)) => (min.min(child_min), max.max(child_max)),
+                None => (child_min, child_max),
+            });
+        }
+    }
+
+    if let Some((min, max)) = bounds {
+        let padding = Vec2::splat(GROUP_PADDING);
+        return Some((min - padding, max + padding));
+    }
+
+    // An empty container is drawn as a plain node at wherever it was placed.
+    let (x, y) = graph[index].position?;
+    let center = Vec2::new(x, y);
+    let half = node_dimensions(&graph[index].label, node_size) / 2.0;
+    Some((center - half, center + half))
+}
+
+/// The rectangle enclosing every visible node, as (min, max) corners.
+pub fn canvas_bounds(graph: &GraphModel, node_size: f32) -> Option<(Vec2, Vec2)> {
+    graph
+        .node_indices()
+        .filter(|index| !model::is_hidden(graph, *index))
+        .filter_map(|index| extent(graph, index, node_size))
+        .fold(None, |bounds, (node_min, node_max)| {
+            Some(match bounds {
+                Some((min, max)) => (Vec2::min(min, node_min), Vec2::max(max, node_max)),
+                None => (node_min, node_max),
+            })
+        })
+}
+
+/// Splits the segment from `a` to `b` into dashes of length `on` separated by gaps of length `off`.
+pub fn dashes(a: Vec2, b: Vec2, on: f32, off: f32) -> Vec<(Vec2, Vec2)> {
+    let length = a.distance(b);
+    if length == 0.0 || on <= 0.0 {
+        return vec![(a, b)];
+    }
+    let direction = (b - a) / length;
+    let period = on + off.max(0.0);
+
+    let mut dashes = Vec::new();
+    let mut start = 0.0;
+    while start < length {
+        let end = (start + on).min(length);
+        dashes.push((a + direction * start, a + direction * end));
+        start += period;
+    }
+    dashes
+}
+
+/// The grid point closest to `point`, for a grid with lines every `spacing` units through the origin.
+pub fn snap(point: Vec2, spacing: f32) -> Vec2 {
+    if spacing <= 0.0 {
+        return point;
+    }
+    (point / spacing).round() * spacing
+}
+
+fn contains(min: Vec2, max: Vec2, point: Vec2) -> bool {
+    point.x >= min.x && point.x <= max.x && point.y >= min.y && point.y <= max.y
+}
+
+/// Where the line from `inside` towards `toward` leaves the rectangle `min`..`max`. This is what stops edges at the boundary of their nodes instead of at their centers. `inside` is returned as it is when it isn't actually inside, or when `toward` is inside as well.
+pub fn exit_point(min: Vec2, max: Vec2, inside: Vec2, toward: Vec2) -> Vec2 {
+    if !contains(min, max, inside) {
+        return inside;
+    }
+    let direction = toward - inside;
+    let mut t = f32::INFINITY;
+    if direction.x > 0.0 {
+        t = t.min((max.x - inside.x) / direction.x);
+    } else if direction.x < 0.0 {
+        t = t.min((min.x - inside.x) / direction.x);
+    }
+    if direction.y > 0.0 {
+        t = t.min((max.y - inside.y) / direction.y);
+    } else if direction.y < 0.0 {
+        t = t.min((min.y - inside.y) / direction.y);
+    }
+    if t > 1.0 {
+        return inside;
+    }
+    inside + direction * t
+}
+
+/// Whether the segment from `a` to `b` passes through (or touches) the rectangle `min`..`max`.
+pub fn segment_crosses_rect(a: Vec2, b: Vec2, min: Vec2, max: Vec2) -> bool {
+    // Liang-Barsky: narrow down the part of the segment that is on the inside of all four sides.
+    let d = b - a;
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    let sides = [
+        (-d.x, a.x - min.x),
+        (d.x, max.x - a.x),
+        (-d.y, a.y - min.y),
+        (d.y, max.y - a.y),
+    ];
+    for &(p, q) in sides.iter() {
+        if p == 0.0 {
+            if q < 0.0 {
+                return false;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                t0 = t0.max(r);
+            } else {
+                t1 = t1.min(r);
+            }
+            if t0 > t1 {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+pub fn distance_to_segment(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+    if length_squared == 0.0 {
+        return point.distance(a);
+    }
+    let t = ((point - a).dot(ab) / length_squared).max(0.0).min(1.0);
+    point.distance(a + ab * t)
+}
+
+pub fn distance_to_polyline(point: Vec2, points: &[Vec2]) -> f32 {
+    match points {
+        [] => f32::INFINITY,
+        [only] => point.distance(*only),
+        _ => points
+            .windows(2)
+            .map(|pair| distance_to_segment(point, pair[0], pair[1]))
+            .fold(f32::INFINITY, f32::min),
+    }
+}
+
+/// The route and the leg of it (see `edge_route`) closest to `point`, if one passes within `tolerance` of it. Of legs equally close, the first one wins.
+pub fn nearest_leg<'a, K>(point: Vec2, tolerance: f32, routes: impl IntoIterator<Item = (K, &'a [Vec<Vec2>])>) -> Option<(K, usize)>
+where
+    K: Copy,
+{
+    let mut hit: Option<(K, usize, f32)> = None;
+    for (key, legs) in routes {
+        for (leg, points) in legs.iter().enumerate() {
+            let distance = distance_to_polyline(point, points);
+            if distance <= tolerance && hit.as_ref().map_or(true, |(_, _, closest)| distance < *closest) {
+                hit = Some((key, leg, distance));
+            }
+        }
+    }
+    hit.map(|(key, leg, _)| (key, leg))
+}
+
+/// The point halfway along a polyline, which is where edge labels go.
+pub fn midpoint(points: &[Vec2]) -> Option<Vec2> {
+    let length: f32 = points.windows(2).map(|pair| pair[0].distance(pair[1])).sum();
+    let mut remaining = length / 2.0;
+    for pair in points.windows(2) {
+        let segment = pair[0].distance(pair[1]);
+        if segment > 0.0 && remaining <= segment {
+            return Some(pair[0] + (pair[1] - pair[0]) * (remaining / segment));
+        }
+        remaining -= segment;
+    }
+    points.first().copied()
+}
+
+/// The legs of a route joined into a single polyline.
+pub fn flatten(legs: &[Vec<Vec2>]) -> Vec<Vec2> {
+    let mut points: Vec<Vec2> = Vec::new();
+    for leg in legs {
+        for point in leg {
+            if points.last() != Some(point) {
+                points.push(*point);
+            }
+        }
+    }
+    points
+}
+
+/// The rectangles an orthogonal edge should go around: every node on the canvas except `skip` (the edge's own endpoints), grown by half the routing margin. Expanded containers are left out since edges have to go into them to reach their children.
+fn obstacles(graph: &GraphModel, skip: &[NodeIndex], node_size: f32) -> Vec<(Vec2, Vec2)> {
+    let margin = Vec2::splat(ROUTING_MARGIN / 2.0);
+    graph
+        .node_indices()
+        .filter(|index| !skip.contains(index) && !model::is_hidden(graph, *index))
+        .filter(|index| !graph[*index].kind.is_container() || graph[*index].collapsed)
+        .filter_map(|index| extent(graph, index, node_size))
+        .map(|(min, max)| (min - margin, max + margin))
+        .collect()
+}
+
+/// A path from `a` to `b` made of horizontal and vertical segments. The candidates are the two L-shapes and Z-shapes running along the sides of the obstacles near the way; the one crossing the fewest obstacles wins, with ties going to the shortest path with the fewest corners.
+pub fn orthogonal_leg(a: Vec2, b: Vec2, obstacles: &[(Vec2, Vec2)]) -> Vec<Vec2> {
+    let mut candidates: Vec<Vec<Vec2>> = vec![
+        vec![a, Vec2::new(b.x, a.y), b],
+        vec![a, Vec2::new(a.x, b.y), b],
+    ];
+
+    // Only the obstacles near the straight way between the two points offer useful channels to go around them.
+    let (low, high) = (a.min(b) - Vec2::splat(ROUTING_MARGIN), a.max(b) + Vec2::splat(ROUTING_MARGIN));
+    let nearby = obstacles
+        .iter()
+        .filter(|(min, max)| min.x <= high.x && max.x >= low.x && min.y <= high.y && max.y >= low.y);
+
+    let mut xs = vec![(a.x + b.x) / 2.0];
+    let mut ys = vec![(a.y + b.y) / 2.0];
+    for (min, max) in nearby {
+        xs.push(min.x - ROUTING_MARGIN);
+        xs.push(max.x + ROUTING_MARGIN);
+        ys.push(min.y - ROUTING_MARGIN);
+        ys.push(max.y + ROUTING_MARGIN);
+    }
+    for x in xs {
+        candidates.push(vec![a, Vec2::new(x, a.y), Vec2::new(x, b.y), b]);
+    }
+    for y in ys {
+        candidates.push(vec![a, Vec2::new(a.x, y), Vec2::new(b.x, y), b]);
+    }
+
+    let cost = |path: &[Vec2]| -> (usize, f32) {
+        let crossings = path
+            .windows(2)
+            .filter(|pair| pair[0] != pair[1])
+            .map(|pair| {
+                obstacles
+                    .iter()
+                    .filter(|(min, max)| segment_crosses_rect(pair[0], pair[1], *min, *max))
+                    .count()
+            })
+            .sum();
+        let length: f32 = path.windows(2).map(|pair| pair[0].distance(pair[1])).sum();
+        (crossings, length + ROUTING_MARGIN * path.len() as f32)
+    };
+
+    let mut best = candidates
+        .into_iter()
+        .map(|path| (cost(&path), path))
+        .min_by(|(a, _), (b, _)| a.0.cmp(&b.0).then(a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal)))
+        .map(|(_, path)| path)
+        .unwrap_or_else(|| vec![a, b]);
+    best.dedup();
+    best
+}
+
+/// The path an edge is drawn along, split into legs: one for each stretch between consecutive waypoints (the endpoints, then the user's bend points). The ends are clipped to the boundaries of the end nodes so that the edge stops where the nodes start.
+pub fn edge_route(graph: &GraphModel, edge: EdgeIndex, node_size: f32) -> Option<Vec<Vec<Vec2>>> {
+    let (source, target) = graph.edge_endpoints(edge)?;
+    let (source_min, source_max) = extent(graph, source, node_size)?;
+    let (target_min, target_max) = extent(graph, target, node_size)?;
+    let weight = graph.edge_weight(edge)?;
+
+    let mut waypoints = vec![(source_min + source_max) / 2.0];
+    waypoints.extend(weight.bends.iter().map(|(x, y)| Vec2::new(*x, *y)));
+    waypoints.push((target_min + target_max) / 2.0);
+
+    // Edges the user hasn't bent themselves are curved apart when they share their endpoints with other edges.
+    if weight.bends.is_empty() && weight.routing == Routing::Straight {
+        let (slot, count) = parallel_slot(graph, edge);
+        if source == target {
+            return Some(vec![self_loop(source_min, source_max, LOOP_SIZE * (slot + 1) as f32)]);
+        }
+        if count > 1 {
+            let offset = (slot as f32 - (count - 1) as f32 / 2.0) * PARALLEL_SPACING;
+            let curve = parallel_curve(waypoints[0], waypoints[1], source < target, offset);
+            return Some(vec![clip_ends(&curve, (source_min, source_max), (target_min, target_max))]);
+        }
+    }
+
+    let mut legs: Vec<Vec<Vec2>> = match weight.routing {
+        Routing::Straight => waypoints.windows(2).map(|pair| vec![pair[0], pair[1]]).collect(),
+        Routing::Orthogonal => {
+            let obstacles = obstacles(graph, &[source, target], node_size);
+            waypoints
+                .windows(2)
+                .map(|pair| orthogonal_leg(pair[0], pair[1], &obstacles))
+                .collect()
+        }
+    };
+
+    if let Some(first) = legs.first_mut() {
+        if first.len() >= 2 {
+            first[0] = exit_point(source_min, source_max, first[0], first[1]);
+        }
+    }
+    if let Some(last) = legs.last_mut() {
+        let end = last.len();
+        if end >= 2 {
+            last[end - 1] = exit_point(target_min, target_max, last[end - 1], last[end - 2]);
+        }
+    }
+    Some(legs)
+}
+
+/// A straight line between the centers of two nodes, clipped to their boundaries.
+pub fn clipped_segment(graph: &GraphModel, source: NodeIndex, target: NodeIndex, node_size: f32) -> Option<(Vec2, Vec2)> {
+    let (source_min, source_max) = extent(graph, source, node_size)?;
+    let (target_min, target_max) = extent(graph, target, node_size)?;
+    let a = (source_min + source_max) / 2.0;
+    let b = (target_min + target_max) / 2.0;
+    Some((
+        exit_point(source_min, source_max, a, b),
+        exit_point(target_min, target_max, b, a),
+    ))
+}
+
+/// Where `edge` sits among all the edges between the same two nodes, in either direction: its position in that list and the length of the list.
+pub fn parallel_slot(graph: &GraphModel, edge: EdgeIndex) -> (usize, usize) {
+    let (a, b) = match graph.edge_endpoints(edge) {
+        Some(endpoints) => endpoints,
+        None => return (0, 1),
+    };
+    let siblings = model::edges_between(graph, a, b);
+    let slot = siblings.iter().position(|other| *other == edge).unwrap_or(0);
+    (slot, siblings.len().max(1))
+}
+
+fn quadratic(a: Vec2, control: Vec2, b: Vec2, t: f32) -> Vec2 {
+    let u = 1.0 - t;
+    a * (u * u) + control * (2.0 * u * t) + b * (t * t)
+}
+
+fn cubic(a: Vec2, c1: Vec2, c2: Vec2, b: Vec2, t: f32) -> Vec2 {
+    let u = 1.0 - t;
+    a * (u * u * u) + c1 * (3.0 * u * u * t) + c2 * (3.0 * u * t * t) + b * (t * t * t)
+}
+
+/// A curve from `a` to `b` bowing `offset` to the side. The side is taken relative to the direction from the lower to the higher node index (`forward` says whether that is `a` to `b`), so that edges running in opposite directions between the same two nodes still end up on different sides.
+fn parallel_curve(a: Vec2, b: Vec2, forward: bool, offset: f32) -> Vec<Vec2> {
+    let direction = if forward { b - a } else { a - b };
+    let length = direction.length();
+    if length == 0.0 {
+        return vec![a, b];
+    }
+    let normal = Vec2::new(-direction.y, direction.x) / length;
+    // A quadratic curve only gets halfway to its control point.
+    let control = (a + b) / 2.0 + normal * (offset * 2.0);
+    (0..=CURVE_SEGMENTS)
+        .map(|step| quadratic(a, control, b, step as f32 / CURVE_SEGMENTS as f32))
+        .collect()
+}
+
+/// A loop leaving the top of the node near its top right corner and coming back in on the right side, reaching `size` out of the node.
+fn self_loop(min: Vec2, max: Vec2, size: f32) -> Vec<Vec2> {
+    let inset = ((max.y - min.y) / 4.0).min((max.x - min.x) / 4.0);
+    let start = Vec2::new(max.x - inset, max.y);
+    let end = Vec2::new(max.x, max.y - inset);
+    let c1 = start + Vec2::new(0.0, size * 1.5);
+    let c2 = end + Vec2::new(size * 1.5, 0.0);
+    (0..=CURVE_SEGMENTS)
+        .map(|step| cubic(start, c1, c2, end, step as f32 / CURVE_SEGMENTS as f32))
+        .collect()
+}
+
+/// Cuts a polyline running from inside the source box to inside the target box down to the part between the two boxes.
+fn clip_ends(points: &[Vec2], source: (Vec2, Vec2), target: (Vec2, Vec2)) -> Vec<Vec2> {
+    let first_outside = points
+        .iter()
+        .position(|point| !contains(source.0, source.1, *point))
+        .unwrap_or(points.len());
+    let last_outside = points.iter().rposition(|point| !contains(target.0, target.1, *point));
+
+    let (first_outside, last_outside) = match last_outside {
+        Some(last) if first_outside > 0 && first_outside <= last && last + 1 < points.len() => (first_outside, last),
+        // The boxes overlap, there is nothing sensible to cut away.
+        _ => return points.to_vec(),
+    };
+
+    let mut clipped = vec![exit_point(source.0, source.1, points[first_outside - 1], points[first_outside])];
+    clipped.extend_from_slice(&points[first_outside..=last_outside]);
+    clipped.push(exit_point(target.0, target.1, points[last_outside + 1], points[last_outside]));
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{EdgeData, EdgeKind, NodeData, NodeKind};
+
+    fn link(graph: &mut GraphModel, a: NodeIndex, b: NodeIndex) -> EdgeIndex {
+        graph.add_edge(a, b, EdgeData::new(EdgeKind::Link))
+    }
+
+    #[test]
+    fn parallel_slots() {
+        let mut graph = GraphModel::default();
+        let a = graph.add_node(NodeData::new("a", NodeKind::Item));
+        let b = graph.add_node(NodeData::new("b", NodeKind::Item));
+        let c = graph.add_node(NodeData::new("c", NodeKind::Item));
+        let d = graph.add_node(NodeData::new("d", NodeKind::Item));
+
+        let single = link(&mut graph, a, b);
+        assert_eq!(parallel_slot(&graph, single), (0, 1));
+
+        // Either direction counts, in the order the edges were added.
+        let there = link(&mut graph, b, c);
+        let back = link(&mut graph, c, b);
+        assert_eq!(parallel_slot(&graph, there), (0, 2));
+        assert_eq!(parallel_slot(&graph, back), (1, 2));
+
+        let three: Vec<EdgeIndex> = (0..3).map(|_| link(&mut graph, c, d)).collect();
+        for (slot, edge) in three.iter().enumerate() {
+            assert_eq!(parallel_slot(&graph, *edge), (slot, 3));
+        }
+
+        // A self-loop is only counted once, and apart from the edges to other nodes.
+        let to_itself = link(&mut graph, a, a);
+        assert_eq!(parallel_slot(&graph, to_itself), (0, 1));
+        assert_eq!(parallel_slot(&graph, single), (0, 1));
+    }
+
+    /// Whether every segment of `points` is horizontal or vertical.
+    fn orthogonal(points: &[Vec2]) -> bool {
+        points.windows(2).all(|pair| pair[0].x == pair[1].x || pair[0].y == pair[1].y)
+    }
+
+    fn on_boundary(min: Vec2, max: Vec2, point: Vec2) -> bool {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-3;
+        contains(min - Vec2::splat(1e-3), max + Vec2::splat(1e-3), point)
+            && (close(point.x, min.x) || close(point.x, max.x) || close(point.y, min.y) || close(point.y, max.y))
+    }
+
+    #[test]
+    fn edges_stop_at_node_boundaries() {
+        let mut graph = GraphModel::default();
+        let a = graph.add_node(NodeData::new("a", NodeKind::Item).at(0.0, 0.0));
+        let b = graph.add_node(NodeData::new("b", NodeKind::Item).at(200.0, 0.0));
+        let c = graph.add_node(NodeData::new("c", NodeKind::Item).at(200.0, 200.0));
+        let overlapping = graph.add_node(NodeData::new("d", NodeKind::Item).at(10.0, 0.0));
+
+        assert_eq!(
+            clipped_segment(&graph, a, b, 40.0),
+            Some((Vec2::new(20.0, 0.0), Vec2::new(180.0, 0.0)))
+        );
+        assert_eq!(
+            clipped_segment(&graph, a, c, 40.0),
+            Some((Vec2::new(20.0, 20.0), Vec2::new(180.0, 180.0)))
+        );
+        // Nodes on top of each other have no boundary in between, so the ends stay at the centers.
+        assert_eq!(
+            clipped_segment(&graph, a, overlapping, 40.0),
+            Some((Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)))
+        );
+
+        let (min, max) = (Vec2::splat(-20.0), Vec2::splat(20.0));
+        assert_eq!(exit_point(min, max, Vec2::new(0.0, 0.0), Vec2::new(0.0, -100.0)), Vec2::new(0.0, -20.0));
+        assert_eq!(exit_point(min, max, Vec2::new(50.0, 0.0), Vec2::ZERO), Vec2::new(50.0, 0.0));
+    }
+
+    #[test]
+    fn orthogonal_legs_go_around_obstacles() {
+        let (a, b) = (Vec2::new(0.0, 0.0), Vec2::new(200.0, 0.0));
+        let obstacle = (Vec2::new(90.0, -20.0), Vec2::new(110.0, 20.0));
+        assert!(segment_crosses_rect(a, b, obstacle.0, obstacle.1));
+
+        let leg = orthogonal_leg(a, b, &[obstacle]);
+        assert_eq!((leg.first(), leg.last()), (Some(&a), Some(&b)));
+        assert!(orthogonal(&leg));
+        assert!(leg
+            .windows(2)
+            .all(|pair| !segment_crosses_rect(pair[0], pair[1], obstacle.0, obstacle.1)));
+
+        // With nothing in the way, a single corner does.
+        let leg = orthogonal_leg(a, Vec2::new(100.0, 50.0), &[]);
+        assert_eq!(leg, vec![a, Vec2::new(100.0, 0.0), Vec2::new(100.0, 50.0)]);
+        // And a straight line needs none.
+        assert_eq!(orthogonal_leg(a, b, &[]), vec![a, b]);
+    }
+
+    #[test]
+    fn bends_split_routes_into_legs() {
+        let mut graph = GraphModel::default();
+        let a = graph.add_node(NodeData::new("a", NodeKind::Item).at(0.0, 0.0));
+        let b = graph.add_node(NodeData::new("b", NodeKind::Item).at(200.0, 0.0));
+        let edge = link(&mut graph, a, b);
+        graph[edge].bends = vec![(100.0, 100.0)];
+
+        let legs = edge_route(&graph, edge, 40.0).unwrap();
+        assert_eq!(
+            legs,
+            vec![
+                vec![Vec2::new(20.0, 20.0), Vec2::new(100.0, 100.0)],
+                vec![Vec2::new(100.0, 100.0), Vec2::new(180.0, 20.0)],
+            ]
+        );
+
+        graph[edge].routing = Routing::Orthogonal;
+        let legs = edge_route(&graph, edge, 40.0).unwrap();
+        assert_eq!(legs.len(), 2);
+        assert!(legs.iter().all(|leg| orthogonal(leg)));
+        assert_eq!(legs[0].last(), Some(&Vec2::new(100.0, 100.0)));
+        assert_eq!(legs[1].first(), Some(&Vec2::new(100.0, 100.0)));
+        let (a_min, a_max) = extent(&graph, a, 40.0).unwrap();
+        let (b_min, b_max) = extent(&graph, b, 40.0).unwrap();
+        assert!(on_boundary(a_min, a_max, legs[0][0]));
+        assert!(on_boundary(b_min, b_max, *legs[1].last().unwrap()));
+    }
+
+    #[test]
+    fn parallel_edges_and_loops_stay_outside_their_nodes() {
+        let mut graph = GraphModel::default();
+        let a = graph.add_node(NodeData::new("a", NodeKind::Item).at(0.0, 0.0));
+        let b = graph.add_node(NodeData::new("b", NodeKind::Item).at(200.0, 0.0));
+        let (a_min, a_max) = extent(&graph, a, 40.0).unwrap();
+        let (b_min, b_max) = extent(&graph, b, 40.0).unwrap();
+
+        let there = link(&mut graph, a, b);
+        let back = link(&mut graph, b, a);
+        let there = flatten(&edge_route(&graph, there, 40.0).unwrap());
+        let back = flatten(&edge_route(&graph, back, 40.0).unwrap());
+        for route in &[&there, &back] {
+            assert!(on_boundary(a_min, a_max, route[0]) || on_boundary(b_min, b_max, route[0]));
+            assert!(route[1..route.len() - 1]
+                .iter()
+                .all(|point| !contains(a_min, a_max, *point) && !contains(b_min, b_max, *point)));
+        }
+        // The two curve apart, to either side of the straight line.
+        let middle = |route: &[Vec2]| route[route.len() / 2].y;
+        assert!(middle(&there) * middle(&back) < 0.0);
+
+        let (first, second) = (link(&mut graph, a, a), link(&mut graph, a, a));
+        let small = flatten(&edge_route(&graph, first, 40.0).unwrap());
+        let large = flatten(&edge_route(&graph, second, 40.0).unwrap());
+        for route in &[&small, &large] {
+            assert!(on_boundary(a_min, a_max, route[0]));
+            assert!(on_boundary(a_min, a_max, *route.last().unwrap()));
+            assert!(route[1..route.len() - 1].iter().all(|point| !contains(a_min, a_max, *point)));
+        }
+        // Every further loop on the same node reaches out further.
+        let reach = |route: &[Vec2]| route.iter().map(|point| point.x.max(point.y)).fold(f32::MIN, f32::max);
+        assert!(reach(&large) > reach(&small));
+    }
+
+    #[test]
+    fn distance_to_segments() {
+        let (a, b) = (Vec2::new(0.0, 0.0), Vec2::new(100.0, 0.0));
+        assert_eq!(distance_to_segment(Vec2::new(50.0, 10.0), a, b), 10.0);
+        assert_eq!(distance_to_segment(Vec2::new(50.0, 0.0), a, b), 0.0);
+        // Beyond either end, it is the distance to that end.
+        assert_eq!(distance_to_segment(Vec2::new(-30.0, 40.0), a, b), 50.0);
+        assert_eq!(distance_to_segment(Vec2::new(130.0, -40.0), a, b), 50.0);
+        assert_eq!(distance_to_segment(b, a, b), 0.0);
+        // A segment without length is a point.
+        assert_eq!(distance_to_segment(Vec2::new(3.0, 4.0), a, a), 5.0);
+
+        let polyline = [a, b, Vec2::new(100.0, 100.0)];
+        assert_eq!(distance_to_polyline(Vec2::new(90.0, 50.0), &polyline), 10.0);
+        assert_eq!(distance_to_polyline(Vec2::new(50.0, -5.0), &polyline), 5.0);
+        assert_eq!(distance_to_polyline(Vec2::new(3.0, 4.0), &[a]), 5.0);
+        assert_eq!(distance_to_polyline(a, &[]), f32::INFINITY);
+    }
+
+    #[test]
+    fn nearest_leg_within_tolerance() {
+        let near = vec![vec![Vec2::new(0.0, 4.0), Vec2::new(100.0, 4.0)]];
+        let nearer = vec![
+            vec![Vec2::new(0.0, 50.0), Vec2::new(100.0, 50.0)],
+            vec![Vec2::new(0.0, -2.0), Vec2::new(100.0, -2.0)],
+        ];
+        let far = vec![vec![Vec2::new(0.0, 20.0), Vec2::new(100.0, 20.0)]];
+        let routes = || vec![("near", near.as_slice()), ("nearer", nearer.as_slice()), ("far", far.as_slice())];
+
+        assert_eq!(nearest_leg(Vec2::new(50.0, 0.0), 5.0, routes()), Some(("nearer", 1)));
+        assert_eq!(nearest_leg(Vec2::new(50.0, 8.0), 5.0, routes()), Some(("near", 0)));
+        assert_eq!(nearest_leg(Vec2::new(50.0, 12.0), 5.0, routes()), None);
+        assert_eq!(nearest_leg(Vec2::new(50.0, 12.0), 10.0, routes()), Some(("near", 0)));
+        // Right in between two legs, the first one found wins.
+        assert_eq!(nearest_leg(Vec2::new(50.0, 1.0), 5.0, routes()), Some(("near", 0)));
+    }
+}
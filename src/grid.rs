@@ -0,0 +1,164 @@
+//! The background grid, snapping to it, and the commands that tidy up the selected nodes (align, distribute, snap).
+
+use bevy::prelude::*;
+use bevy::window::WindowResized;
+use bevy_prototype_lyon::prelude::*;
+use petgraph::stable_graph::NodeIndex;
+
+use crate::camera::{window_size, CanvasView};
+use crate::geometry;
+use crate::layout::{self, Axis};
+use crate::selection::Selection;
+use crate::theme::Theme;
+use crate::{forget_drawing, Graph};
+
+/// The grid sits behind the container frames, edges and nodes.
+const GRID_Z: f32 = -10.0;
+
+/// Past this many lines across the screen the grid only draws every second (fourth, ...) line, so that zooming far out doesn't draw thousands of them.
+const MAX_LINES: f32 = 200.0;
+
+pub struct GridSettings {
+    /// Distance between grid lines, in canvas units.
+    pub spacing: f32,
+    pub visible: bool,
+    /// Whether placed and dragged nodes land on the nearest grid point.
+    pub snap: bool,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        GridSettings {
+            spacing: 40.0,
+            visible: true,
+            snap: false,
+        }
+    }
+}
+
+impl GridSettings {
+    /// `point`, snapped to the grid if snapping is on.
+    pub fn place(&self, point: Vec2) -> Vec2 {
+        if self.snap {
+            geometry::snap(point, self.spacing)
+        } else {
+            point
+        }
+    }
+}
+
+/// The toolbox commands that rearrange the selected nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrangeSelected {
+    Align(Axis),
+    Distribute(Axis),
+    SnapToGrid,
+}
+
+/// Tag for the entity drawing the grid lines.
+struct GridLines;
+
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(GridSettings::default())
+            .add_event::<ArrangeSelected>()
+            .add_system(draw_grid.system())
+            .add_system(arrange_selected.system());
+    }
+}
+
+/// The grid is only ever drawn across the part of the canvas that is on screen, and redrawn whenever that changes, which is what makes it look infinite.
+fn draw_grid(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    view: Res<CanvasView>,
+    grid: Res<GridSettings>,
+    theme: Res<Theme>,
+    mut resized: EventReader<WindowResized>,
+    existing: Query<Entity, With<GridLines>>,
+) {
+    let resized = resized.iter().count() > 0;
+    let drawn = existing.iter().next().is_some();
+    if drawn && !resized && !view.is_changed() && !grid.is_changed() && !theme.is_changed() {
+        return;
+    }
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn();
+    }
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    if grid.spacing <= 0.0 {
+        return;
+    }
+
+    let (min, max) = view.visible_rect(window_size(window));
+    let mut step = grid.spacing;
+    while (max.x - min.x).max(max.y - min.y) / step > MAX_LINES {
+        step *= 2.0;
+    }
+
+    let mut builder = PathBuilder::new();
+    let mut x = (min.x / step).floor() * step;
+    while x <= max.x {
+        builder.move_to(Vec2::new(x, min.y));
+        builder.line_to(Vec2::new(x, max.y));
+        x += step;
+    }
+    let mut y = (min.y / step).floor() * step;
+    while y <= max.y {
+        builder.move_to(Vec2::new(min.x, y));
+        builder.line_to(Vec2::new(max.x, y));
+        y += step;
+    }
+
+    let mut lines = commands.spawn_bundle(GeometryBuilder::build_as(
+        &builder.build(),
+        ShapeColors::new(theme.grid),
+        // One screen pixel wide, however far the canvas is zoomed.
+        DrawMode::Stroke(StrokeOptions::default().with_line_width(view.scale)),
+        Transform::from_xyz(0.0, 0.0, GRID_Z),
+    ));
+    lines.insert(GridLines);
+    if !grid.visible {
+        // Spawned anyway so that the check above doesn't redraw it every frame.
+        lines.insert(Visible {
+            is_visible: false,
+            is_transparent: true,
+        });
+    }
+}
+
+fn arrange_selected(
+    mut commands: Commands,
+    mut events: EventReader<ArrangeSelected>,
+    selection: Res<Selection>,
+    grid: Res<GridSettings>,
+    mut graph: ResMut<Graph>,
+) {
+    for event in events.iter() {
+        let nodes: Vec<NodeIndex> = selection.nodes.iter().copied().collect();
+        match event {
+            ArrangeSelected::Align(axis) => layout::align(&mut graph.0, &nodes, *axis),
+            ArrangeSelected::Distribute(axis) => layout::distribute(&mut graph.0, &nodes, *axis),
+            ArrangeSelected::SnapToGrid => {
+                for index in &nodes {
+                    if let Some((x, y)) = graph.0.node_weight(*index).and_then(|node| node.position) {
+                        let snapped = geometry::snap(Vec2::new(x, y), grid.spacing);
+                        graph.0[*index].position = Some((snapped.x, snapped.y));
+                    }
+                }
+            }
+        }
+        // Moved nodes are redrawn from the model along with the edges attached to them.
+        for index in nodes {
+            if graph.0.contains_node(index) {
+                forget_drawing(&mut commands, &mut graph.0, index);
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Container nodes (crate → module → item) that enclose their children on the canvas and can be collapsed down to a single node. The nesting itself lives in the `Graph` model (`NodeData::parent`), this module only keeps the canvas in step with it.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_prototype_lyon::prelude::*;
+use petgraph::stable_graph::NodeIndex;
+
+use crate::diff;
+use crate::edge_filter::EdgeFilter;
+use crate::geometry::{clipped_segment, extent};
+use crate::lod::LevelOfDetail;
+use crate::model::{self, GraphModel};
+use crate::theme::Theme;
+use crate::{Bounds, Graph, Placed, Position, Tools};
+
+/// Tag for the rectangle drawn around a container's children, or in place of them when the container is collapsed.
+pub struct GroupFrame {
+    pub group: NodeIndex,
+}
+
+/// Tag for the stand-in lines drawn between collapsed containers, and for the edge bundles drawn when zoomed far out.
+pub struct AggregatedEdgeView;
+
+pub struct GroupsPlugin;
+
+impl Plugin for GroupsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(refresh_groups.system())
+            .add_system(group_menu.system());
+    }
+}
+
+/// Everything a container's frame is drawn from. Frames are only drawn again when this comes out differently.
+#[derive(Clone, PartialEq)]
+struct FrameLook {
+    extent: (Vec2, Vec2),
+    z: f32,
+    fill: Color,
+    stroke: Color,
+}
+
+/// The frames and aggregated edges on the canvas, with what they were drawn from.
+#[derive(Default)]
+struct DrawnGroups {
+    frames: HashMap<NodeIndex, (Entity, FrameLook)>,
+    /// By their endpoints, with the line they were drawn along and how many edges they stand for.
+    aggregated: HashMap<(NodeIndex, NodeIndex), (Entity, (Vec2, Vec2, usize))>,
+}
+
+/// Whenever the graph, the theme, the level of detail or the edge filter changes, brings the container frames and the aggregated edges standing in for the nodes and edges hidden inside containers drawn collapsed in step with it. Only the ones that come out differently are drawn again. Hiding what is inside is left to the culling.
+fn refresh_groups(
+    mut commands: Commands,
+    graph: Res<Graph>,
+    theme: Res<Theme>,
+    lod: Res<LevelOfDetail>,
+    filter: Res<EdgeFilter>,
+    mut drawn: Local<DrawnGroups>,
+) {
+    if !graph.is_changed() && !theme.is_changed() && !lod.is_changed() && !filter.is_changed() {
+        return;
+    }
+
+    let drawn = &mut *drawn;
+    let graph = &graph.0;
+
+    let mut frames: HashMap<NodeIndex, FrameLook> = HashMap::new();
+    for index in graph.node_indices() {
+        if !graph[index].kind.is_container() || lod.is_hidden(graph, index) {
+            continue;
+        }
+        if let Some(extent) = extent(graph, index, theme.node_size) {
+            // Summarized containers keep the area of their children, so that zooming out doesn't move things around.
+            let collapsed = lod.is_collapsed(graph, index);
+            let node = &graph[index];
+            frames.insert(
+                index,
+                FrameLook {
+                    extent,
+                    // Inner frames are drawn on top of outer ones, but all of them stay behind the nodes and edges.
+                    z: -3.0 + 0.1 * model::depth(graph, index) as f32,
+                    fill: theme.node_body(node.kind, collapsed),
+                    // The fill of an expanded container is faint, so a container that changed in a diff shows it in its outline.
+                    stroke: diff::Change::of(&node.properties).map_or(theme.container_stroke, |change| theme.change_color(change)),
+                },
+            );
+        }
+    }
+    drawn.frames.retain(|index, (entity, look)| {
+        let keep = frames.get(index) == Some(look);
+        if !keep {
+            commands.entity(*entity).despawn();
+        }
+        keep
+    });
+    for (index, look) in frames {
+        if !drawn.frames.contains_key(&index) {
+            let entity = draw_frame(&mut commands, index, &look);
+            drawn.frames.insert(index, (entity, look));
+        }
+    }
+
+    let mut aggregated: HashMap<(NodeIndex, NodeIndex), (Vec2, Vec2, usize)> = HashMap::new();
+    for edge in lod.aggregated_edges(graph, |kind| filter.shows(kind)) {
+        if let Some((source, target)) = clipped_segment(graph, edge.source, edge.target, theme.node_size) {
+            aggregated.insert((edge.source, edge.target), (source, target, edge.count));
+        }
+    }
+    drawn.aggregated.retain(|ends, (entity, line)| {
+        let keep = aggregated.get(ends) == Some(line);
+        if !keep {
+            commands.entity(*entity).despawn();
+        }
+        keep
+    });
+    for (ends, line) in aggregated {
+        if !drawn.aggregated.contains_key(&ends) {
+            let entity = draw_aggregated_edge(&mut commands, &theme, line);
+            drawn.aggregated.insert(ends, (entity, line));
+        }
+    }
+}
+
+fn draw_aggregated_edge(commands: &mut Commands, theme: &Theme, (source, target, count): (Vec2, Vec2, usize)) -> Entity {
+    let line = shapes::Line(source, target);
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &line,
+            ShapeColors::new(theme.edge.color),
+            DrawMode::Stroke(StrokeOptions::default().with_line_width(1.0 + count as f32)),
+            Transform::from_xyz(0.0, 0.0, -1.0),
+        ))
+        .insert(Bounds {
+            min: source.min(target),
+            max: source.max(target),
+        })
+        .insert(AggregatedEdgeView)
+        .id()
+}
+
+fn draw_frame(commands: &mut Commands, group: NodeIndex, look: &FrameLook) -> Entity {
+    let (min, max) = look.extent;
+    let size = max - min;
+    let center = (min + max) / 2.0;
+    let rectangle = shapes::Rectangle {
+        width: size.x,
+        height: size.y,
+        origin: shapes::RectangleOrigin::Center,
+    };
+
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &rectangle,
+            ShapeColors::outlined(look.fill, look.stroke),
+            DrawMode::Outlined {
+                fill_options: FillOptions::default(),
+                outline_options: StrokeOptions::default().with_line_width(2.0),
+            },
+            Transform::from_xyz(center.x, center.y, look.z),
+        ))
+        .insert(Placed {
+            position: Position {
+                x: center.x,
+                y: center.y,
+                z: look.z,
+            },
+            entity_type: Tools::Group,
+        })
+        .insert(Bounds { min, max })
+        .insert(GroupFrame { group })
+        .id()
+}
+
+/// Lists the container hierarchy with a collapse/expand toggle for each container.
+fn group_menu(egui_context: ResMut<EguiContext>, mut graph: ResMut<Graph>) {
+    let mut toggled: Option<NodeIndex> = None;
+    let mut set_all: Option<bool> = None;
+
+    {
+        let model = &graph.0;
+        egui::Window::new("Groups").show(egui_context.ctx(), |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Collapse all").clicked() {
+                    set_all = Some(true);
+                }
+                if ui.button("Expand all").clicked() {
+                    set_all = Some(false);
+                }
+            });
+            ui.separator();
+
+            let roots = model
+                .node_indices()
+                .filter(|index| model[*index].parent().is_none() && model[*index].kind.is_container());
+            for root in roots {
+                group_tree(ui, model, root, &mut toggled);
+            }
+        });
+    }
+
+    if let Some(index) = toggled {
+        let group = &mut graph.0[index];
+        group.collapsed = !group.collapsed;
+    }
+
+    if let Some(collapsed) = set_all {
+        let containers: Vec<NodeIndex> = graph
+            .0
+            .node_indices()
+            .filter(|index| graph.0[*index].kind.is_container())
+            .collect();
+        for index in containers {
+            graph.0[index].collapsed = collapsed;
+        }
+    }
+}
+
+fn group_tree(ui: &mut egui::Ui, graph: &GraphModel, index: NodeIndex, toggled: &mut Option<NodeIndex>) {
+    let node = &graph[index];
+    let children = model::children(graph, index);
+    let arrow = if node.collapsed { "▸" } else { "▾" };
+
+    let button = ui.button(format!("{} {} ({:?}, {} children)", arrow, node.label, node.kind, children.len()));
+    if button.clicked() {
+        *toggled = Some(index);
+    }
+
+    ui.indent(index.index(), |ui| {
+        for child in children {
+            if graph[child].kind.is_container() {
+                group_tree(ui, graph, child, toggled);
+            }
+        }
+    });
+}
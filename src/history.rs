@@ -0,0 +1,214 @@
+//! A project's architecture over time: one of the Rust source views (see `rust_source::SourceView`) built at a number of commits of a local git repository. The repository is read with the `git` command, from the objects already on disk, so nothing is fetched.
+//!
+//! The graphs of a timeline share their positions (see `place`), so that scrubbing through it only adds and removes nodes rather than moving the ones that stay.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::diff;
+use crate::layout;
+use crate::model::GraphModel;
+use crate::rust_source::SourceError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::rust_source::{SourceFile, SourceView, ViewOptions};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    pub id: String,
+    /// The day it was committed, as `yyyy-mm-dd`.
+    pub date: String,
+    /// The first line of its message.
+    pub summary: String,
+}
+
+impl Commit {
+    pub fn short_id(&self) -> &str {
+        &self.id[..self.id.len().min(7)]
+    }
+}
+
+impl fmt::Display for Commit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.short_id(), self.date, self.summary)
+    }
+}
+
+#[derive(Debug)]
+pub enum HistoryError {
+    /// `git` couldn't be run, or failed, with what it had to say.
+    Git(String),
+    /// The sources at a commit couldn't be parsed.
+    Source { commit: String, error: SourceError },
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::Git(message) => write!(f, "git: {}", message),
+            HistoryError::Source { commit, error } => write!(f, "at {}: {}", commit, error),
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+/// The graph of the project at each of `commits`, in the same order.
+pub struct Timeline {
+    pub commits: Vec<Commit>,
+    pub graphs: Vec<GraphModel>,
+}
+
+/// Runs `git` in `repo`, feeding it `input` if there is any, and returns what it wrote.
+#[cfg(not(target_arch = "wasm32"))]
+fn git(repo: &std::path::Path, args: &[&str], input: Option<Vec<u8>>) -> Result<Vec<u8>, HistoryError> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| HistoryError::Git(format!("couldn't run git: {}", error)))?;
+    // Written from another thread, since git may fill up its output before it has read all of its input.
+    let writer = match (input, child.stdin.take()) {
+        (Some(input), Some(mut stdin)) => Some(std::thread::spawn(move || stdin.write_all(&input))),
+        _ => None,
+    };
+    let output = child
+        .wait_with_output()
+        .map_err(|error| HistoryError::Git(error.to_string()))?;
+    if let Some(writer) = writer {
+        writer
+            .join()
+            .map_err(|_| HistoryError::Git(String::from("couldn't write to git")))?
+            .map_err(|error| HistoryError::Git(error.to_string()))?;
+    }
+    if !output.status.success() {
+        return Err(HistoryError::Git(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(output.stdout)
+}
+
+/// The last `limit` commits of `repo` leading up to `HEAD`, following first parents only, oldest first.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log(repo: &std::path::Path, limit: usize) -> Result<Vec<Commit>, HistoryError> {
+    let limit = limit.to_string();
+    let output = git(
+        repo,
+        &["log", "--first-parent", "-n", &limit, "--date=short", "--format=%H%x09%ad%x09%s"],
+        None,
+    )?;
+    let mut commits: Vec<Commit> = String::from_utf8_lossy(&output)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            Some(Commit {
+                id: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                summary: fields.next().unwrap_or("").to_string(),
+            })
+        })
+        .collect();
+    commits.reverse();
+    Ok(commits)
+}
+
+/// Every `.rs` file of `repo` at `commit`, with its path in the repository, leaving out build output and hidden directories as `rust_source::read_sources` does.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn sources_at(repo: &std::path::Path, commit: &str) -> Result<Vec<SourceFile>, HistoryError> {
+    use std::fmt::Write;
+
+    let listing = git(repo, &["ls-tree", "-r", "-z", "--name-only", commit], None)?;
+    let paths: Vec<String> = listing
+        .split(|byte| *byte == 0)
+        .map(|path| String::from_utf8_lossy(path).into_owned())
+        .filter(|path| path.ends_with(".rs"))
+        .filter(|path| {
+            path.split('/')
+                .rev()
+                .skip(1)
+                .all(|directory| directory != "target" && !directory.starts_with('.'))
+        })
+        .collect();
+
+    let mut request = String::new();
+    for path in &paths {
+        let _ = writeln!(request, "{}:{}", commit, path);
+    }
+    let output = git(repo, &["cat-file", "--batch"], Some(request.into_bytes()))?;
+
+    // Each object comes as a `<id> blob <size>` line, its contents, and a newline.
+    let truncated = || HistoryError::Git(String::from("cat-file stopped short"));
+    let mut rest = &output[..];
+    let mut files = Vec::new();
+    for path in paths {
+        let end = rest.iter().position(|byte| *byte == b'\n').ok_or_else(truncated)?;
+        let header = String::from_utf8_lossy(&rest[..end]).into_owned();
+        rest = &rest[end + 1..];
+        if header.ends_with(" missing") {
+            continue;
+        }
+        let size: usize = header
+            .rsplit(' ')
+            .next()
+            .and_then(|size| size.parse().ok())
+            .ok_or_else(|| HistoryError::Git(format!("unexpected cat-file output: {}", header)))?;
+        if rest.len() < size + 1 {
+            return Err(truncated());
+        }
+        files.push(SourceFile {
+            path,
+            text: String::from_utf8_lossy(&rest[..size]).into_owned(),
+        });
+        rest = &rest[size + 1..];
+    }
+    Ok(files)
+}
+
+/// Builds `view` at each of `commits`, and places the graphs.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn timeline(
+    repo: &std::path::Path,
+    commits: Vec<Commit>,
+    view: SourceView,
+    options: &ViewOptions,
+    node_size: f32,
+) -> Result<Timeline, HistoryError> {
+    let mut graphs = Vec::new();
+    for commit in &commits {
+        let files = sources_at(repo, &commit.id)?;
+        let graph = view.build(&files, options).map_err(|error| HistoryError::Source {
+            commit: commit.short_id().to_string(),
+            error,
+        })?;
+        graphs.push(graph);
+    }
+    place(&mut graphs, node_size);
+    Ok(Timeline { commits, graphs })
+}
+
+/// Lays out every node that is in any of `graphs` once, and gives it that position in each of them.
+pub fn place(graphs: &mut [GraphModel], node_size: f32) {
+    // Diffing each graph against everything before it adds up to every node there ever was.
+    let mut everything = GraphModel::default();
+    for graph in graphs.iter() {
+        everything = diff::diff(&everything, graph);
+    }
+    layout::layout_unpositioned(&mut everything, node_size);
+
+    let positions: HashMap<String, (f32, f32)> = everything
+        .node_weights()
+        .filter_map(|node| Some((diff::identity(node), node.position?)))
+        .collect();
+    for graph in graphs.iter_mut() {
+        for node in graph.node_weights_mut() {
+            if let Some(position) = positions.get(&diff::identity(node)) {
+                node.position = Some(*position);
+            }
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! The egui History window: one of the Rust source views at a number of commits of a local git repository (see `history`). The slider scrubs through the commits, and the canvas follows it, with the nodes that come and go between two commits growing in and shrinking away.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+#[cfg(not(target_arch = "wasm32"))]
+use strum::IntoEnumIterator;
+
+use crate::diff;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::history;
+use crate::history::{Commit, Timeline};
+use crate::lod::{self, LevelOfDetail};
+use crate::metrics_overlay::MetricOverlay;
+use crate::model::{self, EdgeKind, GraphModel};
+use crate::rust_source::SourceView;
+use crate::selection::Selection;
+use crate::theme::Theme;
+use crate::{forget_drawing, forget_edge_drawing, Bounds, Graph, Node, Placed};
+
+/// How long a node takes to grow in or shrink away.
+const TRANSITION_SECONDS: f64 = 0.4;
+
+/// A node growing in or shrinking away, since `since`. A node that is shrinking away is no longer in the model, and is despawned once it is gone.
+pub struct Transition {
+    since: f64,
+    appearing: bool,
+}
+
+struct HistoryState {
+    repo: String,
+    /// How many commits are read from the log.
+    limit: usize,
+    view: SourceView,
+    /// The commits read from the log, oldest first, with whether each is in the timeline.
+    commits: Vec<(Commit, bool)>,
+    timeline: Option<Timeline>,
+    /// The commit picked with the slider.
+    position: usize,
+    /// The commit shown on the canvas.
+    shown: Option<usize>,
+    /// The nodes the timeline put on the canvas, by their identity (see `diff::identity`).
+    nodes: HashMap<String, NodeIndex>,
+    /// Nodes added for the commit shown that haven't been drawn yet, to grow in once they are.
+    appearing: HashSet<NodeIndex>,
+    /// The outcome of the last thing done, shown at the bottom.
+    status: String,
+}
+
+impl Default for HistoryState {
+    fn default() -> Self {
+        HistoryState {
+            repo: String::from("."),
+            limit: 20,
+            view: SourceView::Traits,
+            commits: Vec::new(),
+            timeline: None,
+            position: 0,
+            shown: None,
+            nodes: HashMap::new(),
+            appearing: HashSet::new(),
+            status: String::new(),
+        }
+    }
+}
+
+pub struct HistoryMenuPlugin;
+
+impl Plugin for HistoryMenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(HistoryState::default())
+            .add_system(history_menu.system())
+            .add_system(show_commit.system())
+            // After the level of detail has scaled the nodes, so that the transitions have the last word.
+            .add_system_to_stage(CoreStage::PostUpdate, animate_transitions.system());
+    }
+}
+
+fn history_menu(
+    egui_context: ResMut<EguiContext>,
+    #[cfg(not(target_arch = "wasm32"))] theme: Res<Theme>,
+    mut state: ResMut<HistoryState>,
+) {
+    #[cfg(not(target_arch = "wasm32"))]
+    let (mut read_log, mut build) = (false, false);
+
+    egui::Window::new("History").show(egui_context.ctx(), |ui| {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.horizontal(|ui| {
+                ui.label("Repository");
+                ui.text_edit_singleline(&mut state.repo);
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::Slider::new(&mut state.limit, 1..=200).text("commits"));
+                read_log = ui.button("Read log").clicked();
+            });
+
+            if !state.commits.is_empty() {
+                egui::ScrollArea::from_max_height(160.0).show(ui, |ui| {
+                    for (commit, picked) in state.commits.iter_mut() {
+                        ui.checkbox(picked, commit.to_string());
+                    }
+                });
+                ui.horizontal(|ui| {
+                    for view in SourceView::iter() {
+                        if ui.selectable_label(state.view == view, view.name()).clicked() {
+                            state.view = view;
+                        }
+                    }
+                });
+                build = ui.button("Build timeline").clicked();
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        ui.label("Reading a git repository needs the native build.");
+
+        if let Some(count) = state.timeline.as_ref().map(|timeline| timeline.commits.len()) {
+            ui.separator();
+            ui.add(egui::Slider::new(&mut state.position, 0..=count.saturating_sub(1)).text("commit"));
+            if let Some(commit) = state.timeline.as_ref().and_then(|timeline| timeline.commits.get(state.position)) {
+                ui.label(commit.to_string());
+            }
+        }
+
+        if !state.status.is_empty() {
+            ui.label(state.status.clone());
+        }
+    });
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let repo = std::path::PathBuf::from(&state.repo);
+        if read_log {
+            match history::log(&repo, state.limit) {
+                Ok(commits) => {
+                    state.status = format!("Read {} commits.", commits.len());
+                    state.commits = commits.into_iter().map(|commit| (commit, true)).collect();
+                }
+                Err(error) => state.status = format!("Couldn't read the log of {}: {}", state.repo, error),
+            }
+        }
+        if build {
+            let picked: Vec<Commit> = state
+                .commits
+                .iter()
+                .filter(|(_, picked)| *picked)
+                .map(|(commit, _)| commit.clone())
+                .collect();
+            match history::timeline(&repo, picked, state.view, &Default::default(), theme.node_size) {
+                Ok(timeline) => {
+                    state.status = format!("Built the {} view at {} commits.", state.view.name(), timeline.commits.len());
+                    // Starting out at the latest commit.
+                    state.position = timeline.commits.len().saturating_sub(1);
+                    state.shown = None;
+                    state.timeline = Some(timeline);
+                }
+                Err(error) => state.status = format!("Couldn't build the timeline: {}", error),
+            }
+        }
+    }
+}
+
+/// Brings the canvas in step with the commit picked, whenever that changes.
+fn show_commit(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut graph: ResMut<Graph>,
+    mut selection: ResMut<Selection>,
+    mut state: ResMut<HistoryState>,
+) {
+    let state = &mut *state;
+    let target = match &state.timeline {
+        Some(timeline) if state.shown != Some(state.position) => match timeline.graphs.get(state.position) {
+            Some(target) => target,
+            None => return,
+        },
+        _ => return,
+    };
+
+    // Forgetting whatever was removed from the canvas in the meantime.
+    let graph = &mut graph.0;
+    state.nodes.retain(|identity, index| {
+        graph
+            .node_weight(*index)
+            .map_or(false, |node| diff::identity(node) == *identity)
+    });
+    let wanted: HashSet<String> = target.node_weights().map(diff::identity).collect();
+
+    let gone: Vec<(String, NodeIndex)> = state
+        .nodes
+        .iter()
+        .filter(|(identity, _)| !wanted.contains(*identity))
+        .map(|(identity, index)| (identity.clone(), *index))
+        .collect();
+    for (identity, index) in gone {
+        // The entity is left to shrink away on its own, no longer standing for a node.
+        if let Some(entity) = graph[index].bevy_identity.take() {
+            commands
+                .entity(entity)
+                .remove::<Node>()
+                .remove::<Placed>()
+                .insert(Transition {
+                    since: time.seconds_since_startup(),
+                    appearing: false,
+                });
+        }
+        forget_drawing(&mut commands, graph, index);
+        model::remove_node(graph, index);
+        selection.nodes.remove(&index);
+        state.nodes.remove(&identity);
+        state.appearing.remove(&index);
+    }
+
+    for index in target.node_indices() {
+        let node = &target[index];
+        let identity = diff::identity(node);
+        match state.nodes.get(&identity).copied() {
+            Some(existing) => {
+                let shown = &graph[existing];
+                if shown.label != node.label || shown.kind != node.kind || shown.properties != node.properties {
+                    let shown = &mut graph[existing];
+                    shown.label = node.label.clone();
+                    shown.kind = node.kind;
+                    shown.properties = node.properties.clone();
+                    forget_drawing(&mut commands, graph, existing);
+                }
+            }
+            None => {
+                let added = graph.add_node(node.detached());
+                if !node.kind.is_container() {
+                    state.appearing.insert(added);
+                }
+                state.nodes.insert(identity, added);
+            }
+        }
+    }
+
+    let canvas_index = |nodes: &HashMap<String, NodeIndex>, index: NodeIndex| nodes.get(&diff::identity(&target[index])).copied();
+    for index in target.node_indices() {
+        let parent = target[index].parent().and_then(|parent| canvas_index(&state.nodes, parent));
+        if let Some(child) = canvas_index(&state.nodes, index) {
+            if graph[child].parent() != parent {
+                model::set_parent(graph, child, parent);
+            }
+        }
+    }
+
+    sync_edges(&mut commands, graph, target, &state.nodes);
+    state.shown = Some(state.position);
+}
+
+/// Makes the edges between the nodes of the timeline on the canvas the ones of `target`.
+fn sync_edges(commands: &mut Commands, graph: &mut GraphModel, target: &GraphModel, nodes: &HashMap<String, NodeIndex>) {
+    type EdgeKey = (NodeIndex, NodeIndex, EdgeKind, String);
+
+    let mut wanted: HashMap<EdgeKey, EdgeIndex> = HashMap::new();
+    for edge in target.edge_references() {
+        let source = nodes.get(&diff::identity(&target[edge.source()]));
+        let target_node = nodes.get(&diff::identity(&target[edge.target()]));
+        if let (Some(source), Some(target_node)) = (source, target_node) {
+            wanted.insert((*source, *target_node, edge.weight().kind, edge.weight().label.clone()), edge.id());
+        }
+    }
+
+    let timeline_nodes: HashSet<NodeIndex> = nodes.values().copied().collect();
+    let shown: Vec<(EdgeIndex, EdgeKey)> = graph
+        .edge_references()
+        .filter(|edge| timeline_nodes.contains(&edge.source()) && timeline_nodes.contains(&edge.target()))
+        .map(|edge| (edge.id(), (edge.source(), edge.target(), edge.weight().kind, edge.weight().label.clone())))
+        .collect();
+    for (edge, key) in shown {
+        if wanted.remove(&key).is_none() {
+            forget_edge_drawing(commands, graph, edge);
+            graph.remove_edge(edge);
+        }
+    }
+
+    for ((source, target_node, ..), edge) in wanted {
+        let mut weight = target[edge].clone();
+        weight.bevy_identity = None;
+        graph.add_edge(source, target_node, weight);
+    }
+}
+
+/// Starts nodes that were just drawn for the commit shown growing in, and grows and shrinks every node in transition.
+fn animate_transitions(
+    mut commands: Commands,
+    time: Res<Time>,
+    lod: Res<LevelOfDetail>,
+    theme: Res<Theme>,
+    overlay: Res<MetricOverlay>,
+    graph: Res<Graph>,
+    mut state: ResMut<HistoryState>,
+    mut nodes: QuerySet<(
+        Query<(Entity, &Node, &mut Transform), Added<Node>>,
+        Query<(Entity, &Transition, &Bounds, Option<&Node>, &mut Transform)>,
+    )>,
+) {
+    let now = time.seconds_since_startup();
+    if !state.appearing.is_empty() {
+        for (entity, node, mut transform) in nodes.q0_mut().iter_mut() {
+            if node.identity.map_or(false, |index| state.appearing.remove(&index)) {
+                transform.scale = Vec3::new(0.0, 0.0, 1.0);
+                commands.entity(entity).insert(Transition {
+                    since: now,
+                    appearing: true,
+                });
+            }
+        }
+    }
+
+    for (entity, transition, bounds, node, mut transform) in nodes.q1_mut().iter_mut() {
+        let progress = ((now - transition.since) / TRANSITION_SECONDS).min(1.0) as f32;
+        let data = node.and_then(|node| node.identity).and_then(|index| graph.0.node_weight(index));
+        let full = lod::node_scale(&lod, &theme, &overlay, data, bounds);
+        let factor = if transition.appearing { progress } else { 1.0 - progress };
+        transform.scale = Vec3::new(full.x * factor, full.y * factor, 1.0);
+
+        if progress >= 1.0 {
+            if transition.appearing {
+                commands.entity(entity).remove::<Transition>();
+            } else {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
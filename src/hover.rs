@@ -0,0 +1,245 @@
+//! Hovering: whatever is under the cursor is highlighted together with its immediate neighbours, and described in a tooltip. The lookup only runs when the cursor moves or the graph changes, and the highlight is only redrawn when what is hovered changes, so it stays cheap on large graphs.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_prototype_lyon::prelude::*;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+
+use crate::camera::CanvasView;
+use crate::culling::CanvasIndex;
+use crate::diff;
+use crate::edges::EDGE_TOLERANCE;
+use crate::geometry;
+use crate::groups::GroupFrame;
+use crate::model;
+use crate::theme::Theme;
+use crate::{adjust_cursor_position, clicked_entity, Bounds, Edge, Graph, Node, Placed, Route};
+
+/// The highlight is drawn over nodes and edges but under the labels.
+const HIGHLIGHT_Z: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hovered {
+    Nothing,
+    /// A node, or a container by its frame.
+    Node(NodeIndex),
+    Edge(EdgeIndex),
+}
+
+impl Default for Hovered {
+    fn default() -> Self {
+        Hovered::Nothing
+    }
+}
+
+/// Tag for the shapes drawn to highlight the hovered item and its neighbours.
+struct HoverHighlight;
+
+pub struct HoverPlugin;
+
+impl Plugin for HoverPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Hovered::default())
+            .add_system(update_hover.system())
+            .add_system(highlight_hovered.system())
+            .add_system(hover_tooltip.system());
+    }
+}
+
+fn update_hover(
+    windows: Res<Windows>,
+    view: Res<CanvasView>,
+    egui_context: ResMut<EguiContext>,
+    graph: Res<Graph>,
+    canvas_index: Res<CanvasIndex>,
+    placed: Query<(&Placed, &Bounds, &Visible)>,
+    routes: Query<(&Route, &Visible)>,
+    nodes: Query<&Node>,
+    edges: Query<&Edge>,
+    frames: Query<&GroupFrame>,
+    mut hovered: ResMut<Hovered>,
+    mut last_cursor: Local<Option<(f32, f32)>>,
+) {
+    // Nothing on the canvas is hovered while the pointer is over an egui window.
+    let cursor = if egui_context.ctx().is_pointer_over_area() {
+        None
+    } else {
+        adjust_cursor_position(&windows, &view, None)
+    };
+    if cursor == *last_cursor && !graph.is_changed() && !view.is_changed() {
+        return;
+    }
+    *last_cursor = cursor;
+
+    let under = cursor.and_then(|cursor| clicked_entity(cursor, EDGE_TOLERANCE * view.scale, &canvas_index, &placed, &routes));
+    let next = match under {
+        Some((entity, _)) => {
+            if let Some(index) = nodes.get(entity).ok().and_then(|node| node.identity) {
+                Hovered::Node(index)
+            } else if let Some(index) = edges.get(entity).ok().and_then(|edge| edge.graph_identity) {
+                Hovered::Edge(index)
+            } else if let Ok(frame) = frames.get(entity) {
+                Hovered::Node(frame.group)
+            } else {
+                Hovered::Nothing
+            }
+        }
+        None => Hovered::Nothing,
+    };
+
+    if *hovered != next {
+        *hovered = next;
+    }
+}
+
+/// The nodes and edges to highlight for `hovered`: a node with its edges and the nodes at their other ends, or an edge with its two endpoints.
+fn neighbourhood(graph: &model::GraphModel, hovered: Hovered) -> (Vec<NodeIndex>, Vec<EdgeIndex>) {
+    match hovered {
+        Hovered::Nothing => (Vec::new(), Vec::new()),
+        Hovered::Node(index) if graph.contains_node(index) => {
+            let mut nodes = vec![index];
+            let mut edges = Vec::new();
+            for edge in graph
+                .edges_directed(index, Direction::Outgoing)
+                .chain(graph.edges_directed(index, Direction::Incoming))
+            {
+                edges.push(edge.id());
+                let other = if edge.source() == index { edge.target() } else { edge.source() };
+                if !nodes.contains(&other) {
+                    nodes.push(other);
+                }
+            }
+            (nodes, edges)
+        }
+        Hovered::Edge(index) => match graph.edge_endpoints(index) {
+            Some((source, target)) => (vec![source, target], vec![index]),
+            None => (Vec::new(), Vec::new()),
+        },
+        Hovered::Node(_) => (Vec::new(), Vec::new()),
+    }
+}
+
+fn highlight_hovered(
+    mut commands: Commands,
+    hovered: Res<Hovered>,
+    graph: Res<Graph>,
+    theme: Res<Theme>,
+    routes: Query<&Route>,
+    existing: Query<Entity, With<HoverHighlight>>,
+) {
+    if !hovered.is_changed() && !graph.is_changed() && !theme.is_changed() {
+        return;
+    }
+    for entity in existing.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let graph = &graph.0;
+    let (nodes, edges) = neighbourhood(graph, *hovered);
+
+    // The hovered item itself gets the full selection color, its neighbours a fainter one.
+    let mut faint = theme.selection;
+    faint.set_a(theme.selection.a() * 0.5);
+    let color_for = |is_hovered: bool| if is_hovered { theme.selection } else { faint };
+
+    for index in nodes {
+        if model::is_hidden(graph, index) {
+            continue;
+        }
+        if let Some((min, max)) = geometry::extent(graph, index, theme.node_size) {
+            let grown = Vec2::splat(3.0);
+            let size = max - min + grown * 2.0;
+            let center = (min + max) / 2.0;
+            let outline = shapes::Rectangle {
+                width: size.x,
+                height: size.y,
+                origin: shapes::RectangleOrigin::Center,
+            };
+            commands
+                .spawn_bundle(GeometryBuilder::build_as(
+                    &outline,
+                    ShapeColors::new(color_for(*hovered == Hovered::Node(index))),
+                    DrawMode::Stroke(StrokeOptions::default().with_line_width(2.0)),
+                    Transform::from_xyz(center.x, center.y, HIGHLIGHT_Z),
+                ))
+                .insert(HoverHighlight);
+        }
+    }
+
+    for index in edges {
+        let route = graph[index].bevy_identity.and_then(|entity| routes.get(entity).ok());
+        let points = match route {
+            Some(route) => geometry::flatten(&route.legs),
+            None => continue,
+        };
+        let mut builder = PathBuilder::new();
+        if let Some(first) = points.first() {
+            builder.move_to(*first);
+        }
+        for point in points.iter().skip(1) {
+            builder.line_to(*point);
+        }
+        let width = theme.edge_style(graph[index].kind).width + 4.0;
+        commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &builder.build(),
+                ShapeColors::new(color_for(*hovered == Hovered::Edge(index))),
+                DrawMode::Stroke(StrokeOptions::default().with_line_width(width)),
+                Transform::from_xyz(0.0, 0.0, HIGHLIGHT_Z),
+            ))
+            .insert(HoverHighlight);
+    }
+}
+
+fn hover_tooltip(egui_context: ResMut<EguiContext>, hovered: Res<Hovered>, graph: Res<Graph>) {
+    let graph = &graph.0;
+    match *hovered {
+        Hovered::Node(index) if graph.contains_node(index) => {
+            let node = &graph[index];
+            let outgoing = graph.edges_directed(index, Direction::Outgoing).count();
+            let incoming = graph.edges_directed(index, Direction::Incoming).count();
+            egui::show_tooltip(egui_context.ctx(), egui::Id::new("canvas_hover"), |ui| {
+                ui.label(format!("{} ({:?})", node.label, node.kind));
+                ui.label(format!("degree {} ({} in, {} out)", incoming + outgoing, incoming, outgoing));
+                if node.kind.is_container() {
+                    ui.label(format!("{} children", model::children(graph, index).len()));
+                }
+                if let Some(source) = node.properties.get(model::SOURCE_PROPERTY) {
+                    ui.label(format!("from {}", source));
+                }
+                change_label(ui, &node.properties);
+            });
+        }
+        Hovered::Edge(index) => {
+            if let (Some(edge), Some((source, target))) = (graph.edge_weight(index), graph.edge_endpoints(index)) {
+                egui::show_tooltip(egui_context.ctx(), egui::Id::new("canvas_hover"), |ui| {
+                    if edge.label.is_empty() {
+                        ui.label(format!("{:?}", edge.kind));
+                    } else {
+                        ui.label(format!("{} ({:?})", edge.label, edge.kind));
+                    }
+                    ui.label(format!("{} → {}", graph[source].label, graph[target].label));
+                    if let Some(source) = edge.properties.get(model::SOURCE_PROPERTY) {
+                        ui.label(format!("from {}", source));
+                    }
+                    change_label(ui, &edge.properties);
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// How a node or edge changed in a diff, with what changed about it.
+fn change_label(ui: &mut egui::Ui, properties: &BTreeMap<String, String>) {
+    if let Some(change) = diff::Change::of(properties) {
+        match properties.get(diff::CHANGED_PROPERTY) {
+            Some(changed) => ui.label(format!("{}: {}", change.name(), changed)),
+            None => ui.label(change.name()),
+        };
+    }
+}
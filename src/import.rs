@@ -0,0 +1,753 @@
+//! Importers from Graphviz DOT (for instance the output of `cargo depgraph`) and Mermaid flowcharts into the graph model, which also take snapshots of running apps (see `introspection`). Attributes that match a field of the model (label, kind, position, id) are mapped onto it and everything else is kept in the node or edge properties. Nodes that didn't come with a position are left for `layout::layout_unpositioned`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use petgraph::stable_graph::NodeIndex;
+use uuid::Uuid;
+
+use crate::introspection;
+use crate::model::{self, EdgeData, EdgeKind, GraphModel, NodeData, NodeKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Dot,
+    Mermaid,
+    EcsSnapshot,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    /// The text is neither a DOT graph, a Mermaid flowchart nor an ECS snapshot.
+    UnknownFormat,
+    Syntax { line: usize, message: String },
+    /// An ECS snapshot that couldn't be read.
+    Snapshot(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::UnknownFormat => write!(f, "not a DOT graph, a Mermaid flowchart or an ECS snapshot"),
+            ImportError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            ImportError::Snapshot(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Guesses the format from the first meaningful line of `source`.
+pub fn detect_format(source: &str) -> Option<ImportFormat> {
+    let first_line = source
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("//") && !line.starts_with('#') && !line.starts_with("%%"))?;
+    let mut words = first_line.split_whitespace();
+    let first_word = words.next()?;
+
+    match first_word {
+        "flowchart" => Some(ImportFormat::Mermaid),
+        "digraph" | "strict" => Some(ImportFormat::Dot),
+        // Both formats can start with `graph`: Mermaid follows it with a direction, DOT with an optional name and a brace.
+        "graph" => match words.next() {
+            Some("TD") | Some("TB") | Some("BT") | Some("LR") | Some("RL") => Some(ImportFormat::Mermaid),
+            _ => Some(ImportFormat::Dot),
+        },
+        _ if first_word.starts_with("digraph") || first_word.starts_with("graph{") => Some(ImportFormat::Dot),
+        _ if first_word.starts_with(introspection::SNAPSHOT_HEADER) => Some(ImportFormat::EcsSnapshot),
+        _ => None,
+    }
+}
+
+/// Imports `source`, detecting its format.
+pub fn import(source: &str) -> Result<GraphModel, ImportError> {
+    match detect_format(source) {
+        Some(ImportFormat::Dot) => import_dot(source),
+        Some(ImportFormat::Mermaid) => import_mermaid(source),
+        Some(ImportFormat::EcsSnapshot) => introspection::load_snapshot(source)
+            .map(|snapshot| snapshot.to_graph())
+            .map_err(|error| ImportError::Snapshot(error.to_string())),
+        None => Err(ImportError::UnknownFormat),
+    }
+}
+
+fn syntax_error(line: usize, message: impl Into<String>) -> ImportError {
+    ImportError::Syntax {
+        line,
+        message: message.into(),
+    }
+}
+
+/// Applies the attributes of a DOT node statement (or a Mermaid class) to a node.
+fn apply_node_attributes(node: &mut NodeData, attributes: &BTreeMap<String, String>) {
+    for (key, value) in attributes {
+        match key.as_str() {
+            "label" => node.label = value.clone(),
+            "kind" => match NodeKind::from_str(value) {
+                Ok(kind) => node.kind = kind,
+                Err(_) => {
+                    node.properties.insert(key.clone(), value.clone());
+                }
+            },
+            "pos" => node.position = parse_position(value),
+            // Our own exporter draws containers as folders, so a folder is read back as a container rather than kept as a property.
+            "shape" if value == "folder" => {
+                if !node.kind.is_container() {
+                    node.kind = NodeKind::Group;
+                }
+            }
+            "id" => match Uuid::parse_str(value) {
+                Ok(id) => node.id = id,
+                Err(_) => {
+                    node.properties.insert(key.clone(), value.clone());
+                }
+            },
+            _ => {
+                node.properties.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn apply_edge_attributes(edge: &mut EdgeData, attributes: &BTreeMap<String, String>) {
+    for (key, value) in attributes {
+        match key.as_str() {
+            "label" => edge.label = value.clone(),
+            "kind" => match EdgeKind::from_str(value) {
+                Ok(kind) => edge.kind = kind,
+                Err(_) => {
+                    edge.properties.insert(key.clone(), value.clone());
+                }
+            },
+            "id" => match Uuid::parse_str(value) {
+                Ok(id) => edge.id = id,
+                Err(_) => {
+                    edge.properties.insert(key.clone(), value.clone());
+                }
+            },
+            _ => {
+                edge.properties.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Graphviz positions look like `"x,y"`, with a trailing `!` when the position is pinned.
+fn parse_position(value: &str) -> Option<(f32, f32)> {
+    let mut parts = value.trim().trim_end_matches('!').split(',');
+    let x = parts.next()?.trim().parse().ok()?;
+    let y = parts.next()?.trim().parse().ok()?;
+    Some((x, y))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DotToken {
+    Id(String),
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Equals,
+    Semicolon,
+    Comma,
+    Colon,
+    EdgeOperator,
+}
+
+fn tokenize_dot(source: &str) -> Result<Vec<(DotToken, usize)>, ImportError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '#' => {
+                while chars.peek().map_or(false, |next| *next != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().map_or(false, |next| *next != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if last == '*' => break,
+                        Some(next) => {
+                            if next == '\n' {
+                                line += 1;
+                            }
+                            last = next;
+                        }
+                        None => return Err(syntax_error(line, "unterminated comment")),
+                    }
+                }
+            }
+            '{' => tokens.push((DotToken::LeftBrace, line)),
+            '}' => tokens.push((DotToken::RightBrace, line)),
+            '[' => tokens.push((DotToken::LeftBracket, line)),
+            ']' => tokens.push((DotToken::RightBracket, line)),
+            '=' => tokens.push((DotToken::Equals, line)),
+            ';' => tokens.push((DotToken::Semicolon, line)),
+            ',' => tokens.push((DotToken::Comma, line)),
+            ':' => tokens.push((DotToken::Colon, line)),
+            '-' if chars.peek() == Some(&'>') || chars.peek() == Some(&'-') => {
+                chars.next();
+                tokens.push((DotToken::EdgeOperator, line));
+            }
+            '"' => {
+                let start = line;
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('"') => value.push('"'),
+                            Some('\\') => value.push('\\'),
+                            Some('n') | Some('l') | Some('r') => value.push('\n'),
+                            Some('\n') => line += 1,
+                            Some(other) => {
+                                value.push('\\');
+                                value.push(other);
+                            }
+                            None => return Err(syntax_error(start, "unterminated string")),
+                        },
+                        Some(next) => {
+                            if next == '\n' {
+                                line += 1;
+                            }
+                            value.push(next);
+                        }
+                        None => return Err(syntax_error(start, "unterminated string")),
+                    }
+                }
+                tokens.push((DotToken::Id(value), start));
+            }
+            '<' => {
+                // HTML-like labels are kept as they are, markup included.
+                let start = line;
+                let mut depth = 1;
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('<') => {
+                            depth += 1;
+                            value.push('<');
+                        }
+                        Some('>') => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                            value.push('>');
+                        }
+                        Some(next) => {
+                            if next == '\n' {
+                                line += 1;
+                            }
+                            value.push(next);
+                        }
+                        None => return Err(syntax_error(start, "unterminated HTML string")),
+                    }
+                }
+                tokens.push((DotToken::Id(value), start));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+                let mut value = c.to_string();
+                while let Some(next) = chars.peek() {
+                    if next.is_alphanumeric() || *next == '_' || *next == '.' {
+                        value.push(*next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push((DotToken::Id(value), line));
+            }
+            other => return Err(syntax_error(line, format!("unexpected character '{}'", other))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// What a DOT statement list is nested in: the container that new nodes go into, and the `node [...]`/`edge [...]` defaults in effect.
+#[derive(Clone, Default)]
+struct DotScope {
+    container: Option<NodeIndex>,
+    /// Whether these are the statements of the cluster of `container` itself, rather than of a plain subgraph nested in it.
+    is_cluster: bool,
+    node_defaults: BTreeMap<String, String>,
+    edge_defaults: BTreeMap<String, String>,
+}
+
+struct DotParser<'a> {
+    tokens: &'a [(DotToken, usize)],
+    position: usize,
+    graph: GraphModel,
+    ids: HashMap<String, NodeIndex>,
+}
+
+impl<'a> DotParser<'a> {
+    fn peek(&self) -> Option<&DotToken> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&DotToken> {
+        self.tokens.get(self.position + offset).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn next(&mut self) -> Option<DotToken> {
+        let token = self.tokens.get(self.position).map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: DotToken) -> Result<(), ImportError> {
+        let line = self.line();
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(syntax_error(line, format!("expected {:?}, found {:?}", expected, token))),
+            None => Err(syntax_error(line, format!("expected {:?}, found the end of the file", expected))),
+        }
+    }
+
+    fn expect_id(&mut self) -> Result<String, ImportError> {
+        let line = self.line();
+        match self.next() {
+            Some(DotToken::Id(id)) => Ok(id),
+            Some(token) => Err(syntax_error(line, format!("expected an identifier, found {:?}", token))),
+            None => Err(syntax_error(line, "expected an identifier, found the end of the file")),
+        }
+    }
+
+    fn parse_graph(&mut self) -> Result<(), ImportError> {
+        if self.peek() == Some(&DotToken::Id("strict".to_string())) {
+            self.next();
+        }
+        match self.expect_id()?.as_str() {
+            "graph" | "digraph" => {}
+            other => return Err(syntax_error(self.line(), format!("expected graph or digraph, found {}", other))),
+        }
+        if let Some(DotToken::Id(_)) = self.peek() {
+            self.next();
+        }
+        self.expect(DotToken::LeftBrace)?;
+        self.parse_statements(&mut DotScope::default())?;
+        self.expect(DotToken::RightBrace)
+    }
+
+    /// Parses statements up to (but not including) the closing brace. Returns every node mentioned, which is what an edge to a subgraph connects to.
+    fn parse_statements(&mut self, scope: &mut DotScope) -> Result<Vec<NodeIndex>, ImportError> {
+        let mut mentioned = Vec::new();
+
+        loop {
+            match self.peek() {
+                None | Some(DotToken::RightBrace) => return Ok(mentioned),
+                Some(DotToken::Semicolon) | Some(DotToken::Comma) => {
+                    self.next();
+                }
+                Some(DotToken::Id(keyword))
+                    if (keyword == "graph" || keyword == "node" || keyword == "edge")
+                        && self.peek_at(1) == Some(&DotToken::LeftBracket) =>
+                {
+                    let keyword = keyword.clone();
+                    self.next();
+                    let attributes = self.parse_attribute_lists()?;
+                    match keyword.as_str() {
+                        "node" => scope.node_defaults.extend(attributes),
+                        "edge" => scope.edge_defaults.extend(attributes),
+                        _ => self.apply_graph_attributes(scope, &attributes),
+                    }
+                }
+                Some(DotToken::Id(_)) if self.peek_at(1) == Some(&DotToken::Equals) => {
+                    let key = self.expect_id()?;
+                    self.expect(DotToken::Equals)?;
+                    let value = self.expect_id()?;
+                    let attributes: BTreeMap<String, String> = std::iter::once((key, value)).collect();
+                    self.apply_graph_attributes(scope, &attributes);
+                }
+                _ => {
+                    let nodes = self.parse_edge_statement(scope)?;
+                    mentioned.extend(nodes);
+                }
+            }
+        }
+    }
+
+    /// A node, a subgraph, or a chain of them joined by edge operators.
+    fn parse_edge_statement(&mut self, scope: &mut DotScope) -> Result<Vec<NodeIndex>, ImportError> {
+        let mut groups = vec![self.parse_endpoint(scope)?];
+        while self.peek() == Some(&DotToken::EdgeOperator) {
+            self.next();
+            groups.push(self.parse_endpoint(scope)?);
+        }
+
+        let attributes = self.parse_attribute_lists()?;
+
+        if groups.len() == 1 {
+            // A lone node statement: its attributes describe the node(s) themselves.
+            for index in &groups[0] {
+                apply_node_attributes(&mut self.graph[*index], &attributes);
+            }
+            return Ok(groups.remove(0));
+        }
+
+        let mut edge_attributes = scope.edge_defaults.clone();
+        edge_attributes.extend(attributes);
+        for pair in groups.windows(2) {
+            for source in &pair[0] {
+                for target in &pair[1] {
+                    let mut weight = EdgeData::new(EdgeKind::Link);
+                    apply_edge_attributes(&mut weight, &edge_attributes);
+                    self.graph.add_edge(*source, *target, weight);
+                }
+            }
+        }
+
+        Ok(groups.into_iter().flatten().collect())
+    }
+
+    fn parse_endpoint(&mut self, scope: &mut DotScope) -> Result<Vec<NodeIndex>, ImportError> {
+        match self.peek() {
+            Some(DotToken::LeftBrace) => self.parse_subgraph(scope, None),
+            Some(DotToken::Id(keyword)) if keyword == "subgraph" => {
+                self.next();
+                let name = match self.peek() {
+                    Some(DotToken::Id(_)) => Some(self.expect_id()?),
+                    _ => None,
+                };
+                self.parse_subgraph(scope, name)
+            }
+            _ => {
+                let id = self.expect_id()?;
+                // Ports (`node:port:compass`) only say where on the node an edge attaches.
+                while self.peek() == Some(&DotToken::Colon) {
+                    self.next();
+                    self.expect_id()?;
+                }
+                Ok(vec![self.node(&id, scope)])
+            }
+        }
+    }
+
+    /// Clusters become containers, other subgraphs only scope defaults and are otherwise flattened.
+    fn parse_subgraph(&mut self, scope: &DotScope, name: Option<String>) -> Result<Vec<NodeIndex>, ImportError> {
+        let mut inner = scope.clone();
+        inner.is_cluster = false;
+
+        if let Some(cluster) = name.as_deref().filter(|name| name.starts_with("cluster")) {
+            // Our own exporter writes `cluster_n3` around the container node `n3`, so that node is the container rather than a child of it.
+            let key = cluster.trim_start_matches("cluster").trim_start_matches('_');
+            let key = if key.is_empty() { cluster } else { key };
+            let container = match self.ids.get(key) {
+                Some(index) => *index,
+                None => {
+                    let index = self.graph.add_node(NodeData::new(key, NodeKind::Group));
+                    model::set_parent(&mut self.graph, index, scope.container);
+                    self.ids.insert(key.to_string(), index);
+                    index
+                }
+            };
+            if !self.graph[container].kind.is_container() {
+                self.graph[container].kind = NodeKind::Group;
+            }
+            inner.container = Some(container);
+            inner.is_cluster = true;
+        }
+
+        self.expect(DotToken::LeftBrace)?;
+        let mentioned = self.parse_statements(&mut inner)?;
+        self.expect(DotToken::RightBrace)?;
+
+        Ok(match inner.container {
+            Some(container) if inner.container != scope.container => vec![container],
+            _ => mentioned,
+        })
+    }
+
+    fn parse_attribute_lists(&mut self) -> Result<BTreeMap<String, String>, ImportError> {
+        let mut attributes = BTreeMap::new();
+        while self.peek() == Some(&DotToken::LeftBracket) {
+            self.next();
+            loop {
+                match self.peek() {
+                    Some(DotToken::RightBracket) => {
+                        self.next();
+                        break;
+                    }
+                    Some(DotToken::Comma) | Some(DotToken::Semicolon) => {
+                        self.next();
+                    }
+                    _ => {
+                        let key = self.expect_id()?;
+                        let value = if self.peek() == Some(&DotToken::Equals) {
+                            self.next();
+                            self.expect_id()?
+                        } else {
+                            "true".to_string()
+                        };
+                        attributes.insert(key, value);
+                    }
+                }
+            }
+        }
+        Ok(attributes)
+    }
+
+    /// Graph attributes only mean something to us in a cluster itself, where they describe the container. Those of a plain subgraph inside it don't.
+    fn apply_graph_attributes(&mut self, scope: &DotScope, attributes: &BTreeMap<String, String>) {
+        if let Some(container) = scope.container.filter(|_| scope.is_cluster) {
+            let container = &mut self.graph[container];
+            apply_node_attributes(container, attributes);
+            if !container.kind.is_container() {
+                container.kind = NodeKind::Group;
+            }
+        }
+    }
+
+    /// The node called `id`, created inside the current container if this is the first time it is mentioned.
+    fn node(&mut self, id: &str, scope: &DotScope) -> NodeIndex {
+        if let Some(index) = self.ids.get(id) {
+            return *index;
+        }
+        let mut weight = NodeData::new(id, NodeKind::Item);
+        apply_node_attributes(&mut weight, &scope.node_defaults);
+        let index = self.graph.add_node(weight);
+        model::set_parent(&mut self.graph, index, scope.container);
+        self.ids.insert(id.to_string(), index);
+        index
+    }
+}
+
+pub fn import_dot(source: &str) -> Result<GraphModel, ImportError> {
+    let tokens = tokenize_dot(source)?;
+    let mut parser = DotParser {
+        tokens: &tokens,
+        position: 0,
+        graph: GraphModel::default(),
+        ids: HashMap::new(),
+    };
+    parser.parse_graph()?;
+    Ok(parser.graph)
+}
+
+/// Mermaid node shapes, as (opening, closing) delimiters. Longer openings come first so that `[[` isn't read as `[`.
+const MERMAID_SHAPES: &[(&str, &str)] = &[
+    ("(((", ")))"),
+    ("[[", "]]"),
+    ("[(", ")]"),
+    ("[/", "/]"),
+    ("[\\", "\\]"),
+    ("((", "))"),
+    ("([", "])"),
+    ("{{", "}}"),
+    ("[", "]"),
+    ("(", ")"),
+    ("{", "}"),
+    (">", "]"),
+];
+
+struct MermaidParser {
+    graph: GraphModel,
+    ids: HashMap<String, NodeIndex>,
+    containers: Vec<NodeIndex>,
+}
+
+impl MermaidParser {
+    fn node(&mut self, id: &str) -> NodeIndex {
+        if let Some(index) = self.ids.get(id) {
+            return *index;
+        }
+        let index = self.graph.add_node(NodeData::new(id, NodeKind::Item));
+        model::set_parent(&mut self.graph, index, self.containers.last().copied());
+        self.ids.insert(id.to_string(), index);
+        index
+    }
+
+    /// Parses a node reference such as `a`, `a["label"]` or `a(label):::struct` from the front of `text`. Returns the node and the rest of the line.
+    fn parse_node<'t>(&mut self, text: &'t str, line: usize) -> Result<(NodeIndex, &'t str), ImportError> {
+        let text = text.trim_start();
+        let id_length = text
+            .char_indices()
+            .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+            .map_or(text.len(), |(position, _)| position);
+        let id = &text[..id_length];
+        if id.is_empty() {
+            return Err(syntax_error(line, format!("expected a node at '{}'", text)));
+        }
+        let index = self.node(id);
+        let mut rest = &text[id.len()..];
+
+        if let Some((open, close)) = MERMAID_SHAPES.iter().find(|(open, _)| rest.starts_with(*open)) {
+            let inner = &rest[open.len()..];
+            let end = inner
+                .find(*close)
+                .ok_or_else(|| syntax_error(line, format!("missing '{}' for node {}", close, id)))?;
+            let label = inner[..end].trim().trim_matches('"');
+            self.graph[index].label = label.replace("#quot;", "\"");
+            rest = &inner[end + close.len()..];
+        }
+
+        if let Some(class) = rest.strip_prefix(":::") {
+            let length = class
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(class.len());
+            self.apply_class(index, &class[..length]);
+            rest = &class[length..];
+        }
+
+        Ok((index, rest))
+    }
+
+    /// Node kinds are carried as classes (`:::struct` or `class a,b struct`). Classes that aren't a kind are kept as a property.
+    fn apply_class(&mut self, index: NodeIndex, class: &str) {
+        let attributes: BTreeMap<String, String> =
+            std::iter::once(("kind".to_string(), class.to_string())).collect();
+        let node = &mut self.graph[index];
+        apply_node_attributes(node, &attributes);
+        if let Some(class) = node.properties.remove("kind") {
+            node.properties.insert("class".to_string(), class);
+        }
+    }
+
+    /// Parses a link such as `-->`, `-.->`, `==>`, `-->|label|` or `-- label -->` from the front of `text`. Returns the label (if any), whether the link is dotted, and the rest of the line.
+    fn parse_link<'t>(text: &'t str) -> Option<(Option<String>, bool, &'t str)> {
+        let text = text.trim_start();
+        let is_link_char = |c: char| matches!(c, '-' | '=' | '.' | '<' | '>' | '~');
+        let operator_length = text.find(|c: char| !is_link_char(c)).unwrap_or(text.len());
+        if operator_length < 2 {
+            return None;
+        }
+        let operator = &text[..operator_length];
+        let mut rest = &text[operator_length..];
+        let mut label = None;
+        let mut dotted = operator.contains('.');
+
+        // `-- label -->` puts the label in the middle of the link.
+        if !operator.ends_with('>') && matches!(operator, "--" | "==" | "-.") {
+            let closing = ["-->", "==>", ".->", "---", "===", "-.-"]
+                .iter()
+                .filter_map(|closing| rest.find(closing).map(|position| (position, *closing)))
+                .min_by_key(|(position, _)| *position)?;
+            label = Some(rest[..closing.0].trim().to_string());
+            dotted |= closing.1.contains('.');
+            rest = &rest[closing.0..];
+            let closing_length = rest.find(|c: char| !is_link_char(c)).unwrap_or(rest.len());
+            rest = &rest[closing_length..];
+        }
+
+        if let Some(labelled) = rest.trim_start().strip_prefix('|') {
+            let end = labelled.find('|')?;
+            label = Some(labelled[..end].trim().trim_matches('"').to_string());
+            rest = &labelled[end + 1..];
+        }
+
+        Some((label.map(|label| label.replace("#quot;", "\"")), dotted, rest))
+    }
+
+    /// A group of nodes joined with `&`.
+    fn parse_nodes<'t>(&mut self, text: &'t str, line: usize) -> Result<(Vec<NodeIndex>, &'t str), ImportError> {
+        let (first, mut rest) = self.parse_node(text, line)?;
+        let mut nodes = vec![first];
+        while let Some(more) = rest.trim_start().strip_prefix('&') {
+            let (next, after) = self.parse_node(more, line)?;
+            nodes.push(next);
+            rest = after;
+        }
+        Ok((nodes, rest))
+    }
+
+    fn parse_line(&mut self, text: &str, line: usize) -> Result<(), ImportError> {
+        let text = text.trim().trim_end_matches(';');
+        let keyword = text.split_whitespace().next().unwrap_or("");
+
+        match keyword {
+            "" | "flowchart" | "graph" | "direction" | "classDef" | "style" | "linkStyle" | "click" => return Ok(()),
+            _ if text.starts_with("%%") => return Ok(()),
+            "end" => {
+                self.containers.pop();
+                return Ok(());
+            }
+            "subgraph" => {
+                let rest = text["subgraph".len()..].trim();
+                // Either `subgraph id [label]` or just `subgraph some title`.
+                let (id, label) = match rest.find('[') {
+                    Some(open) => (
+                        rest[..open].trim().to_string(),
+                        rest[open + 1..].trim_end_matches(']').trim().trim_matches('"').to_string(),
+                    ),
+                    None => (rest.replace(' ', "_"), rest.trim_matches('"').to_string()),
+                };
+                let index = self.node(&id);
+                let node = &mut self.graph[index];
+                node.kind = NodeKind::Group;
+                node.label = label.replace("#quot;", "\"");
+                self.containers.push(index);
+                return Ok(());
+            }
+            "class" => {
+                let mut parts = text["class".len()..].split_whitespace();
+                if let (Some(ids), Some(class)) = (parts.next(), parts.next()) {
+                    for id in ids.split(',') {
+                        let index = self.node(id.trim());
+                        self.apply_class(index, class);
+                    }
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let (mut sources, mut rest) = self.parse_nodes(text, line)?;
+        while let Some((label, dotted, after)) = Self::parse_link(rest) {
+            let (targets, after) = self.parse_nodes(after, line)?;
+            for source in &sources {
+                for target in &targets {
+                    let mut weight = EdgeData::new(EdgeKind::Link);
+                    if let Some(label) = &label {
+                        weight.label = label.clone();
+                    }
+                    if dotted {
+                        weight.properties.insert("style".to_string(), "dashed".to_string());
+                    }
+                    self.graph.add_edge(*source, *target, weight);
+                }
+            }
+            sources = targets;
+            rest = after;
+        }
+
+        if !rest.trim().is_empty() {
+            return Err(syntax_error(line, format!("unexpected '{}'", rest.trim())));
+        }
+        Ok(())
+    }
+}
+
+pub fn import_mermaid(source: &str) -> Result<GraphModel, ImportError> {
+    let mut parser = MermaidParser {
+        graph: GraphModel::default(),
+        ids: HashMap::new(),
+        containers: Vec::new(),
+    };
+    for (number, line) in source.lines().enumerate() {
+        parser.parse_line(line, number + 1)?;
+    }
+    Ok(parser.graph)
+}
@@ -0,0 +1,91 @@
+//! The egui Import window. Diagrams can be pasted in, or (natively) loaded from a file, and are added to the current graph.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::import;
+use crate::layout;
+use crate::model;
+use crate::theme::Theme;
+use crate::Graph;
+
+#[derive(Default)]
+struct ImportState {
+    source: String,
+    path: String,
+    /// The file `source` was loaded from, if it wasn't pasted in.
+    loaded_from: Option<String>,
+    /// The outcome of the last import, shown underneath the buttons.
+    status: String,
+}
+
+pub struct ImportMenuPlugin;
+
+impl Plugin for ImportMenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(ImportState::default())
+            .add_system(import_menu.system());
+    }
+}
+
+fn import_menu(
+    egui_context: ResMut<EguiContext>,
+    mut graph: ResMut<Graph>,
+    theme: Res<Theme>,
+    mut state: ResMut<ImportState>,
+) {
+    let mut import_now = false;
+
+    egui::Window::new("Import").show(egui_context.ctx(), |ui| {
+        ui.label("Paste a Graphviz DOT graph, a Mermaid flowchart or an ECS snapshot:");
+        ui.add(egui::TextEdit::multiline(&mut state.source).desired_rows(6));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut state.path);
+            if ui.button("Load file").clicked() {
+                match std::fs::read_to_string(&state.path) {
+                    Ok(source) => {
+                        state.source = source;
+                        state.loaded_from = Some(state.path.clone());
+                        import_now = true;
+                    }
+                    Err(error) => state.status = format!("Couldn't read {}: {}", state.path, error),
+                }
+            }
+        });
+
+        if ui.button("Import").clicked() {
+            state.loaded_from = None;
+            import_now = true;
+        }
+        if !state.status.is_empty() {
+            ui.label(state.status.clone());
+        }
+    });
+
+    if import_now {
+        match import::import(&state.source) {
+            Ok(mut imported) => {
+                // Imported items remember the file they came from, unless the diagram already said where they came from.
+                if let Some(path) = state.loaded_from.clone() {
+                    for index in imported.node_indices().collect::<Vec<_>>() {
+                        imported[index]
+                            .properties
+                            .entry(model::SOURCE_PROPERTY.to_string())
+                            .or_insert_with(|| path.clone());
+                    }
+                }
+                // Laying out after merging keeps the new nodes clear of whatever is already on the canvas.
+                model::merge(&mut graph.0, &imported);
+                layout::layout_unpositioned(&mut graph.0, theme.node_size);
+                state.status = format!(
+                    "Imported {} nodes and {} edges.",
+                    imported.node_count(),
+                    imported.edge_count()
+                );
+            }
+            Err(error) => state.status = format!("Couldn't import: {}", error),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Live introspection of a running Bevy app. `IntrospectionPlugin` can be added to any app (this one adds it to itself): pressing its key writes a snapshot of the app's schedule, resources and component counts to a RON file, and the editor imports such a snapshot (see `import::import`) as a diagram with a container per stage holding its systems in order, and containers for the resources and the components.
+//!
+//! The schedule lives in the `App` rather than in the world, so the plugin takes its snapshot just before the app starts running. Systems are listed in the order they were added to their stage, which is the order the stage runs them in unless they were ordered explicitly.
+
+use std::collections::BTreeMap;
+
+use bevy::ecs::schedule::{Schedule, SystemContainer, SystemStage};
+use bevy::prelude::*;
+use petgraph::stable_graph::NodeIndex;
+use serde::{Deserialize, Serialize};
+
+use crate::model::{self, EdgeData, EdgeKind, GraphModel, NodeData, NodeKind};
+
+/// Snapshots start with the name of the struct, which is how the importer tells them apart from DOT and Mermaid.
+pub const SNAPSHOT_HEADER: &str = "EcsSnapshot";
+
+/// The property holding the full type name (or system name) behind a node shortened to its last path segments.
+pub const TYPE_PROPERTY: &str = "type";
+
+/// The property holding the number of entities that have a component.
+pub const ENTITIES_PROPERTY: &str = "entities";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EcsSnapshot {
+    pub entities: u32,
+    /// In the order the app runs them. Stages of nested schedules (such as the startup stages) are named after the stage they are in as well.
+    pub stages: Vec<StageSnapshot>,
+    pub resources: Vec<String>,
+    pub components: Vec<ComponentCount>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StageSnapshot {
+    pub name: String,
+    pub systems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ComponentCount {
+    pub name: String,
+    pub entities: usize,
+}
+
+pub fn load_snapshot(source: &str) -> Result<EcsSnapshot, ron::Error> {
+    ron::from_str(source)
+}
+
+pub fn save_snapshot(snapshot: &EcsSnapshot) -> Result<String, ron::Error> {
+    let body = ron::ser::to_string_pretty(snapshot, ron::ser::PrettyConfig::new())?;
+    Ok(format!("{}{}", SNAPSHOT_HEADER, body))
+}
+
+/// Type names with their module paths left off, `bevy_ecs::event::Events<bevy_input::keyboard::KeyboardInput>` becoming `Events<KeyboardInput>`.
+pub fn short_name(name: &str) -> String {
+    let mut short = String::new();
+    let mut word = String::new();
+    for character in name.chars().chain(std::iter::once(' ')) {
+        if character.is_alphanumeric() || character == '_' || character == ':' {
+            word.push(character);
+        } else {
+            short.push_str(word.rsplit("::").next().unwrap_or(&word));
+            word.clear();
+            short.push(character);
+        }
+    }
+    short.pop();
+    short
+}
+
+impl EcsSnapshot {
+    /// The snapshot as a diagram: the app as a crate, a container per stage with its systems chained by `RunsBefore` edges (running on from the last system of a stage to the first of the next), and containers for the resources and the components, the latter labelled with how many entities have them.
+    pub fn to_graph(&self) -> GraphModel {
+        let mut graph = GraphModel::default();
+        let add = |graph: &mut GraphModel, label: String, kind: NodeKind, parent: NodeIndex| {
+            let index = graph.add_node(NodeData::new(label, kind));
+            model::set_parent(graph, index, Some(parent));
+            index
+        };
+        let app = graph.add_node(NodeData::new(format!("app ({} entities)", self.entities), NodeKind::Crate));
+
+        let mut previous: Option<NodeIndex> = None;
+        for stage in &self.stages {
+            let container = add(&mut graph, stage.name.clone(), NodeKind::Group, app);
+            for system in &stage.systems {
+                let index = add(&mut graph, short_name(system), NodeKind::Function, container);
+                graph[index].properties.insert(TYPE_PROPERTY.to_string(), system.clone());
+                if let Some(previous) = previous {
+                    graph.add_edge(previous, index, EdgeData::new(EdgeKind::RunsBefore));
+                }
+                previous = Some(index);
+            }
+        }
+
+        let resources = add(&mut graph, String::from("Resources"), NodeKind::Group, app);
+        for resource in &self.resources {
+            let index = add(&mut graph, short_name(resource), NodeKind::Resource, resources);
+            graph[index].properties.insert(TYPE_PROPERTY.to_string(), resource.clone());
+        }
+
+        let components = add(&mut graph, String::from("Components"), NodeKind::Group, app);
+        for component in &self.components {
+            let label = format!("{} ({})", short_name(&component.name), component.entities);
+            let index = add(&mut graph, label, NodeKind::Component, components);
+            graph[index].properties.insert(TYPE_PROPERTY.to_string(), component.name.clone());
+            graph[index]
+                .properties
+                .insert(ENTITIES_PROPERTY.to_string(), component.entities.to_string());
+        }
+        graph
+    }
+}
+
+/// The stages of `schedule` in the order they run, with their systems.
+pub fn schedule_snapshot(schedule: &Schedule) -> Vec<StageSnapshot> {
+    fn visit(schedule: &Schedule, prefix: &str, stages: &mut Vec<StageSnapshot>) {
+        for (label, stage) in schedule.iter_stages() {
+            let name = format!("{}{:?}", prefix, label);
+            if let Some(nested) = stage.downcast_ref::<Schedule>() {
+                visit(nested, &format!("{} / ", name), stages);
+            } else if let Some(stage) = stage.downcast_ref::<SystemStage>() {
+                let mut systems = names(stage.exclusive_at_start_systems());
+                systems.extend(names(stage.parallel_systems()));
+                systems.extend(names(stage.exclusive_before_commands_systems()));
+                systems.extend(names(stage.exclusive_at_end_systems()));
+                stages.push(StageSnapshot { name, systems });
+            }
+        }
+    }
+
+    fn names(systems: &[impl SystemContainer]) -> Vec<String> {
+        systems.iter().map(|system| system.name().to_string()).collect()
+    }
+
+    let mut stages = Vec::new();
+    visit(schedule, "", &mut stages);
+    stages
+}
+
+/// The resources in `world` and the number of entities having each component, with the schedule taken from `stages`.
+pub fn world_snapshot(world: &World, stages: Vec<StageSnapshot>) -> EcsSnapshot {
+    let components = world.components();
+    let name = |id| components.get_info(id).map(|info| info.name().to_string());
+
+    let archetypes = world.archetypes();
+    let resource_archetype = archetypes.resource();
+    let mut resources: Vec<String> = resource_archetype.components().filter_map(name).collect();
+    resources.sort();
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for archetype in archetypes.iter() {
+        if archetype.id() == resource_archetype.id() {
+            continue;
+        }
+        for component in archetype.components().filter_map(name) {
+            *counts.entry(component).or_insert(0) += archetype.len();
+        }
+    }
+
+    EcsSnapshot {
+        entities: world.entities().len(),
+        stages,
+        resources,
+        components: counts
+            .into_iter()
+            .map(|(name, entities)| ComponentCount { name, entities })
+            .collect(),
+    }
+}
+
+/// Writes a snapshot of the app to `path` whenever `key` is pressed. It has to be added after the plugin that sets the runner (`DefaultPlugins`), since it takes the snapshot of the schedule from inside of the runner.
+#[derive(Debug, Clone)]
+pub struct IntrospectionPlugin {
+    pub path: String,
+    pub key: KeyCode,
+}
+
+impl Default for IntrospectionPlugin {
+    fn default() -> Self {
+        IntrospectionPlugin {
+            path: String::from("ecs_snapshot.ron"),
+            key: KeyCode::F12,
+        }
+    }
+}
+
+/// The schedule as it was when the app started running.
+struct ScheduleSnapshot(Vec<StageSnapshot>);
+
+impl Plugin for IntrospectionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let runner = std::mem::replace(&mut app.app.runner, Box::new(|_| {}));
+        app.set_runner(move |mut app: App| {
+            let stages = schedule_snapshot(&app.schedule);
+            app.world.insert_resource(ScheduleSnapshot(stages));
+            runner(app);
+        })
+        .insert_resource(self.clone())
+        .add_system(write_snapshot.exclusive_system());
+    }
+}
+
+fn write_snapshot(world: &mut World) {
+    let settings = match world.get_resource::<IntrospectionPlugin>() {
+        Some(settings) => settings.clone(),
+        None => return,
+    };
+    let pressed = world
+        .get_resource::<Input<KeyCode>>()
+        .map_or(false, |keys| keys.just_pressed(settings.key));
+    if !pressed {
+        return;
+    }
+
+    let stages = world
+        .get_resource::<ScheduleSnapshot>()
+        .map(|schedule| schedule.0.clone())
+        .unwrap_or_default();
+    let snapshot = world_snapshot(world, stages);
+    match save_snapshot(&snapshot) {
+        #[cfg(not(target_arch = "wasm32"))]
+        Ok(text) => match std::fs::write(&settings.path, text) {
+            Ok(()) => info!("Wrote an ECS snapshot to {}", settings.path),
+            Err(error) => warn!("Couldn't write {}: {}", settings.path, error),
+        },
+        // There is no file system on the web, so the snapshot goes to the console to be copied from there.
+        #[cfg(target_arch = "wasm32")]
+        Ok(text) => info!("{}", text),
+        Err(error) => warn!("Couldn't save the ECS snapshot: {}", error),
+    }
+}
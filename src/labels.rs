@@ -0,0 +1,404 @@
+//! Node labels drawn on the canvas, and editing them in place: double-click a node (or a container) with the selector, type, then Enter to keep the new label or Esc to throw it away.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use bevy::ecs::component::Component;
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+use bevy_egui::EguiContext;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+
+use crate::camera::CanvasView;
+use crate::culling::CanvasIndex;
+use crate::edge_filter::EdgeFilter;
+use crate::geometry::{self, LABEL_FONT_SIZE};
+use crate::groups::GroupFrame;
+use crate::lod::{Detail, LevelOfDetail};
+use crate::model::{self, GraphModel};
+use crate::theme::Theme;
+use crate::{
+    adjust_cursor_position, entity_under_cursor, forget_drawing, Bounds, Graph, GraphInteractionHistory, Interaction, Node,
+    Placed, ToolHistory, Tools, DOUBLE_CLICK_SECONDS,
+};
+
+/// Labels are drawn on top of the nodes they belong to.
+const LABEL_Z: f32 = 1.0;
+
+pub struct LabelFont(pub Handle<Font>);
+
+/// Tag for the text entity drawing the label of `node`.
+pub struct NodeLabel {
+    pub node: NodeIndex,
+}
+
+/// Tag for the text entity drawing the label of `edge`.
+pub struct EdgeLabel {
+    pub edge: EdgeIndex,
+}
+
+/// The label being edited on the canvas, if any, with the text typed so far.
+#[derive(Debug, Default)]
+pub struct LabelEditor {
+    pub editing: Option<(NodeIndex, String)>,
+}
+
+#[derive(Default)]
+struct LastClick {
+    node: Option<NodeIndex>,
+    at: f64,
+}
+
+pub struct LabelsPlugin;
+
+impl Plugin for LabelsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(LabelEditor::default())
+            .add_startup_system(load_font.system())
+            .add_system(refresh_labels.system())
+            .add_system(start_editing.system())
+            .add_system(type_label.system());
+    }
+}
+
+fn load_font(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(LabelFont(asset_server.load("fonts/DejaVuSans.ttf")));
+}
+
+/// Where a label sits relative to the point it is drawn at.
+#[derive(Clone, Copy, PartialEq)]
+enum Anchor {
+    /// In the middle of a node.
+    Center,
+    /// In the top left corner of a container's frame.
+    TopLeft,
+    /// Just above the middle of an edge.
+    Above,
+}
+
+impl Anchor {
+    fn alignment(&self) -> TextAlignment {
+        let (vertical, horizontal) = match self {
+            Anchor::Center => (VerticalAlign::Center, HorizontalAlign::Center),
+            Anchor::TopLeft => (VerticalAlign::Top, HorizontalAlign::Left),
+            Anchor::Above => (VerticalAlign::Bottom, HorizontalAlign::Center),
+        };
+        TextAlignment { vertical, horizontal }
+    }
+}
+
+/// Everything a label is drawn from. Labels are only drawn again when this comes out differently.
+#[derive(Clone, PartialEq)]
+struct LabelLook {
+    text: String,
+    font_size: f32,
+    color: Color,
+    anchor: Anchor,
+    translation: Vec3,
+    scale: f32,
+    bounds: (Vec2, Vec2),
+}
+
+/// The labels on the canvas, with what they were drawn from.
+#[derive(Default)]
+struct DrawnLabels {
+    nodes: HashMap<NodeIndex, (Entity, LabelLook)>,
+    edges: HashMap<EdgeIndex, (Entity, LabelLook)>,
+    /// The node whose label was being edited the last time round.
+    edited: Option<NodeIndex>,
+}
+
+/// Keeps the labels in step with the graph, the theme, the level of detail, the edge filter and the label being edited. Whatever changed, only the labels that come out differently are drawn again, and typing only looks at the label being edited.
+fn refresh_labels(
+    mut commands: Commands,
+    graph: Res<Graph>,
+    theme: Res<Theme>,
+    view: Res<CanvasView>,
+    lod: Res<LevelOfDetail>,
+    filter: Res<EdgeFilter>,
+    font: Res<LabelFont>,
+    editor: Res<LabelEditor>,
+    mut drawn: Local<DrawnLabels>,
+) {
+    let zoomed_out = lod.detail != Detail::Full;
+    let everything = graph.is_changed()
+        || theme.is_changed()
+        || lod.is_changed()
+        || filter.is_changed()
+        || (zoomed_out && view.is_changed());
+    if !everything && !editor.is_changed() {
+        return;
+    }
+
+    let drawn = &mut *drawn;
+    let graph = &graph.0;
+    let edited = editor.editing.as_ref().map(|(index, _)| *index);
+
+    let nodes: Vec<NodeIndex> = if everything {
+        let gone: Vec<NodeIndex> = drawn
+            .nodes
+            .keys()
+            .filter(|index| graph.node_weight(**index).is_none())
+            .copied()
+            .collect();
+        gone.into_iter().chain(graph.node_indices()).collect()
+    } else {
+        drawn.edited.into_iter().chain(edited).collect()
+    };
+    for index in nodes {
+        let look = graph
+            .node_weight(index)
+            .and_then(|_| node_label(graph, index, &theme, &view, &lod, &editor));
+        update(&mut commands, &font, &mut drawn.nodes, index, look, || NodeLabel { node: index });
+    }
+    drawn.edited = edited;
+
+    if !everything {
+        return;
+    }
+    let edges: Vec<EdgeIndex> = drawn
+        .edges
+        .keys()
+        .filter(|edge| graph.edge_weight(**edge).is_none())
+        .copied()
+        .chain(graph.edge_indices())
+        .collect();
+    for edge in edges {
+        let look = if zoomed_out || graph.edge_weight(edge).is_none() {
+            None
+        } else {
+            edge_label(graph, edge, &theme, &lod, &filter)
+        };
+        update(&mut commands, &font, &mut drawn.edges, edge, look, || EdgeLabel { edge });
+    }
+}
+
+/// The label of `index`, if it has one at the current level of detail. Expanded containers carry their label in the top left corner of their frame, everything else has it in the middle. Labels get the bounds of what they label, so that the culling hides them along with it.
+///
+/// Below full detail only the containers drawn collapsed are labelled, with their number of children, and at a size that stays readable however far out the view is.
+fn node_label(
+    graph: &GraphModel,
+    index: NodeIndex,
+    theme: &Theme,
+    view: &CanvasView,
+    lod: &LevelOfDetail,
+    editor: &LabelEditor,
+) -> Option<LabelLook> {
+    if lod.is_hidden(graph, index) {
+        return None;
+    }
+    let zoomed_out = lod.detail != Detail::Full;
+    let node = &graph[index];
+    let collapsed = node.kind.is_container() && lod.is_collapsed(graph, index);
+    if zoomed_out && !collapsed {
+        return None;
+    }
+    let (min, max) = geometry::extent(graph, index, theme.node_size)?;
+
+    let text = match &editor.editing {
+        Some((editing, buffer)) if *editing == index => format!("{}|", buffer),
+        _ if zoomed_out => format!("{} ({})", node.label, model::children(graph, index).len()),
+        _ => node.label.clone(),
+    };
+    let scale = if zoomed_out { view.scale } else { 1.0 };
+
+    let (anchor, translation) = if node.kind.is_container() && !collapsed {
+        // Just above the frame itself, which sits behind everything else.
+        let z = -3.0 + 0.1 * model::depth(graph, index) as f32 + 0.05;
+        (Anchor::TopLeft, Vec3::new(min.x + 4.0, max.y - 4.0, z))
+    } else {
+        let middle = (min + max) / 2.0;
+        (Anchor::Center, Vec3::new(middle.x, middle.y, LABEL_Z))
+    };
+
+    Some(LabelLook {
+        text,
+        font_size: LABEL_FONT_SIZE,
+        color: theme.node_style(node.kind).text,
+        anchor,
+        translation,
+        scale,
+        bounds: (min, max),
+    })
+}
+
+/// The label of `edge`, halfway along it, if it has one and both ends and the edge itself are shown.
+fn edge_label(graph: &GraphModel, edge: EdgeIndex, theme: &Theme, lod: &LevelOfDetail, filter: &EdgeFilter) -> Option<LabelLook> {
+    let label = &graph[edge].label;
+    let hidden = graph
+        .edge_endpoints(edge)
+        .map_or(true, |(a, b)| lod.is_hidden(graph, a) || lod.is_hidden(graph, b));
+    if label.is_empty() || hidden || !filter.shows(graph[edge].kind) {
+        return None;
+    }
+    let middle = geometry::edge_route(graph, edge, theme.node_size)
+        .and_then(|legs| geometry::midpoint(&geometry::flatten(&legs)))?;
+    let half = Vec2::new(geometry::label_width(label) / 2.0, LABEL_FONT_SIZE);
+    Some(LabelLook {
+        text: label.clone(),
+        font_size: LABEL_FONT_SIZE * 0.85,
+        color: theme.edge_style(graph[edge].kind).color,
+        anchor: Anchor::Above,
+        translation: Vec3::new(middle.x, middle.y + 2.0, LABEL_Z),
+        scale: 1.0,
+        bounds: (middle - half, middle + half),
+    })
+}
+
+/// Brings the label drawn for `key` in line with `look`, drawing it again only if it comes out differently, and tagging it with `tag`.
+fn update<K: Copy + Eq + Hash, T: Component>(
+    commands: &mut Commands,
+    font: &LabelFont,
+    drawn: &mut HashMap<K, (Entity, LabelLook)>,
+    key: K,
+    look: Option<LabelLook>,
+    tag: impl FnOnce() -> T,
+) {
+    let unchanged = match (drawn.get(&key), &look) {
+        (Some((_, old)), Some(look)) => old == look,
+        (None, None) => true,
+        _ => false,
+    };
+    if unchanged {
+        return;
+    }
+    if let Some((entity, _)) = drawn.remove(&key) {
+        commands.entity(entity).despawn();
+    }
+    if let Some(look) = look {
+        let entity = commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    look.text.clone(),
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: look.font_size,
+                        color: look.color,
+                    },
+                    look.anchor.alignment(),
+                ),
+                transform: Transform {
+                    translation: look.translation,
+                    scale: Vec3::new(look.scale, look.scale, 1.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(Bounds {
+                min: look.bounds.0,
+                max: look.bounds.1,
+            })
+            .insert(tag())
+            .id();
+        drawn.insert(key, (entity, look));
+    }
+}
+
+/// Opens the editor on a double-click with the selector, and keeps whatever was typed when something else is clicked while editing.
+fn start_editing(
+    mut commands: Commands,
+    buttons: Res<Input<MouseButton>>,
+    time: Res<Time>,
+    windows: Res<Windows>,
+    view: Res<CanvasView>,
+    egui_context: ResMut<EguiContext>,
+    tool_history: Res<ToolHistory>,
+    canvas_index: Res<CanvasIndex>,
+    placed: Query<(&Placed, &Bounds, &Visible)>,
+    nodes: Query<&Node>,
+    frames: Query<&GroupFrame>,
+    mut graph: ResMut<Graph>,
+    mut graph_interaction_history: ResMut<GraphInteractionHistory>,
+    mut editor: ResMut<LabelEditor>,
+    mut last_click: Local<LastClick>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) || egui_context.ctx().is_pointer_over_area() {
+        return;
+    }
+
+    let clicked = adjust_cursor_position(&windows, &view, None)
+        .and_then(|cursor| entity_under_cursor(cursor, &canvas_index, &placed))
+        .and_then(|(entity, _)| {
+            nodes
+                .get(entity)
+                .ok()
+                .and_then(|node| node.identity)
+                .or_else(|| frames.get(entity).ok().map(|frame| frame.group))
+        });
+
+    let editing = editor.editing.as_ref().map(|(index, _)| *index);
+    if editing.is_some() && editing != clicked {
+        commit(&mut commands, &mut editor, &mut graph, &mut graph_interaction_history);
+    }
+
+    if tool_history.current_tool != Tools::Selector {
+        return;
+    }
+
+    let now = time.seconds_since_startup();
+    if clicked.is_some() && clicked == last_click.node && now - last_click.at < DOUBLE_CLICK_SECONDS {
+        if let Some(index) = clicked {
+            editor.editing = Some((index, graph.0[index].label.clone()));
+        }
+        *last_click = LastClick::default();
+    } else {
+        *last_click = LastClick { node: clicked, at: now };
+    }
+}
+
+fn type_label(
+    mut commands: Commands,
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut graph: ResMut<Graph>,
+    mut graph_interaction_history: ResMut<GraphInteractionHistory>,
+    mut editor: ResMut<LabelEditor>,
+) {
+    // Read even when not editing, so that earlier typing doesn't end up in the next label that gets edited.
+    let typed: String = characters
+        .iter()
+        .map(|event| event.char)
+        .filter(|character| !character.is_control())
+        .collect();
+
+    if editor.editing.is_none() {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        editor.editing = None;
+        return;
+    }
+    if keys.just_pressed(KeyCode::Return) || keys.just_pressed(KeyCode::NumpadEnter) {
+        commit(&mut commands, &mut editor, &mut graph, &mut graph_interaction_history);
+        return;
+    }
+
+    let backspace = keys.just_pressed(KeyCode::Back);
+    if typed.is_empty() && !backspace {
+        return;
+    }
+    if let Some((_, buffer)) = editor.editing.as_mut() {
+        if backspace {
+            buffer.pop();
+        }
+        buffer.push_str(&typed);
+    }
+}
+
+/// Writes the edited label into the graph, recording the change so that it can be undone. The node is redrawn because its size follows its label.
+fn commit(
+    commands: &mut Commands,
+    editor: &mut LabelEditor,
+    graph: &mut Graph,
+    graph_interaction_history: &mut GraphInteractionHistory,
+) {
+    if let Some((index, label)) = editor.editing.take() {
+        let unchanged = graph.0.node_weight(index).map_or(true, |node| node.label == label);
+        if unchanged {
+            return;
+        }
+        let previous = std::mem::replace(&mut graph.0[index].label, label.clone());
+        graph_interaction_history.0.push(Interaction::Relabeled(index, previous, label));
+        forget_drawing(commands, &mut graph.0, index);
+    }
+}
@@ -0,0 +1,184 @@
+//! Automatic placement for nodes that arrive without a position, such as nodes imported from a diagram that was never laid out.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use crate::geometry;
+use crate::model::{self, GraphModel};
+
+/// Distance between the centers of neighbouring nodes placed by the auto-layout.
+pub const LAYOUT_SPACING: f32 = 80.0;
+
+/// Gives every node without a position one, leaving positioned nodes alone.
+///
+/// The unpositioned nodes are placed in layers, top to bottom, with every edge pointing from a layer to one further down wherever the graph allows it. Nodes that share a container are kept next to each other within a layer. The whole block is put to the right of whatever was already positioned so that nothing ends up on top of an existing node.
+pub fn layout_unpositioned(graph: &mut GraphModel, node_size: f32) {
+    // Containers take their extent from their children, so only leaves (and empty containers) need a place of their own.
+    let pending: Vec<NodeIndex> = graph
+        .node_indices()
+        .filter(|index| graph[*index].position.is_none())
+        .filter(|index| !graph[*index].kind.is_container() || model::children(graph, *index).is_empty())
+        .collect();
+    if pending.is_empty() {
+        return;
+    }
+
+    let origin_x = geometry::canvas_bounds(graph, node_size)
+        .map(|(_, max)| max.x + LAYOUT_SPACING)
+        .unwrap_or(0.0);
+
+    let ranks = rank(graph, &pending);
+
+    let mut layers: Vec<Vec<NodeIndex>> = Vec::new();
+    for index in &pending {
+        let rank = ranks[index];
+        if layers.len() <= rank {
+            layers.resize(rank + 1, Vec::new());
+        }
+        layers[rank].push(*index);
+    }
+
+    for (rank, layer) in layers.iter_mut().enumerate() {
+        // Sorting by the path of containers (outermost first) groups siblings together.
+        layer.sort_by_cached_key(|index| {
+            let mut path: Vec<usize> = model::ancestors(graph, *index)
+                .into_iter()
+                .rev()
+                .map(|ancestor| ancestor.index())
+                .collect();
+            path.push(index.index());
+            path
+        });
+
+        // Nodes are as wide as their labels, so long labels push their neighbours further along the row.
+        let y = -(rank as f32) * LAYOUT_SPACING;
+        let mut previous: Option<(f32, f32)> = None;
+        for index in layer.iter() {
+            let width = geometry::node_dimensions(&graph[*index].label, node_size).x;
+            let x = match previous {
+                Some((x, previous_width)) => x + LAYOUT_SPACING.max((previous_width + width) / 2.0 + node_size),
+                None => origin_x + width / 2.0,
+            };
+            graph[*index].position = Some((x, y));
+            previous = Some((x, width));
+        }
+    }
+}
+
+/// The layer of each node: the length of the longest chain of edges leading to it, with cycles broken wherever they are found.
+fn rank(graph: &GraphModel, nodes: &[NodeIndex]) -> HashMap<NodeIndex, usize> {
+    let members: HashSet<NodeIndex> = nodes.iter().copied().collect();
+
+    let mut in_degree: HashMap<NodeIndex, usize> = nodes.iter().map(|index| (*index, 0)).collect();
+    for edge in graph.edge_references() {
+        if edge.source() != edge.target() && members.contains(&edge.source()) && members.contains(&edge.target()) {
+            *in_degree.get_mut(&edge.target()).unwrap() += 1;
+        }
+    }
+
+    let mut ranks: HashMap<NodeIndex, usize> = HashMap::new();
+    let mut queue: VecDeque<NodeIndex> = nodes
+        .iter()
+        .copied()
+        .filter(|index| in_degree[index] == 0)
+        .collect();
+    let mut done: HashSet<NodeIndex> = HashSet::new();
+
+    while done.len() < nodes.len() {
+        let next = match queue.pop_front() {
+            Some(next) => next,
+            // Everything left is part of a cycle, so the cycle is broken at the node with the fewest remaining incoming edges.
+            None => *nodes
+                .iter()
+                .filter(|index| !done.contains(*index))
+                .min_by_key(|index| in_degree[*index])
+                .unwrap(),
+        };
+        if !done.insert(next) {
+            continue;
+        }
+
+        let next_rank = *ranks.entry(next).or_insert(0);
+        for edge in graph.edges(next) {
+            let target = edge.target();
+            if target == next || !members.contains(&target) || done.contains(&target) {
+                continue;
+            }
+            let target_rank = ranks.entry(target).or_insert(0);
+            *target_rank = (*target_rank).max(next_rank + 1);
+
+            let degree = in_degree.get_mut(&target).unwrap();
+            *degree = degree.saturating_sub(1);
+            if *degree == 0 {
+                queue.push_back(target);
+            }
+        }
+    }
+
+    ranks
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// The positioned nodes among `nodes`, with their positions.
+fn positioned(graph: &GraphModel, nodes: &[NodeIndex]) -> Vec<(NodeIndex, (f32, f32))> {
+    nodes
+        .iter()
+        .filter_map(|index| graph.node_weight(*index).and_then(|node| node.position).map(|position| (*index, position)))
+        .collect()
+}
+
+/// Lines the nodes up along `axis`: aligning horizontally puts them all on the same row (their average y), vertically in the same column (their average x).
+pub fn align(graph: &mut GraphModel, nodes: &[NodeIndex], axis: Axis) {
+    let positioned = positioned(graph, nodes);
+    if positioned.len() < 2 {
+        return;
+    }
+    let count = positioned.len() as f32;
+
+    match axis {
+        Axis::Horizontal => {
+            let y = positioned.iter().map(|(_, (_, y))| y).sum::<f32>() / count;
+            for (index, (x, _)) in positioned {
+                graph[index].position = Some((x, y));
+            }
+        }
+        Axis::Vertical => {
+            let x = positioned.iter().map(|(_, (x, _))| x).sum::<f32>() / count;
+            for (index, (_, y)) in positioned {
+                graph[index].position = Some((x, y));
+            }
+        }
+    }
+}
+
+/// Spreads the nodes out evenly along `axis`, keeping the outermost two where they are and the order they were in.
+pub fn distribute(graph: &mut GraphModel, nodes: &[NodeIndex], axis: Axis) {
+    let mut positioned = positioned(graph, nodes);
+    if positioned.len() < 3 {
+        return;
+    }
+    let coordinate = |position: &(f32, f32)| match axis {
+        Axis::Horizontal => position.0,
+        Axis::Vertical => position.1,
+    };
+    positioned.sort_by(|a, b| coordinate(&a.1).partial_cmp(&coordinate(&b.1)).unwrap_or(std::cmp::Ordering::Equal));
+
+    let first = coordinate(&positioned[0].1);
+    let last = coordinate(&positioned[positioned.len() - 1].1);
+    let step = (last - first) / (positioned.len() - 1) as f32;
+
+    for (order, (index, (x, y))) in positioned.into_iter().enumerate() {
+        let value = first + step * order as f32;
+        graph[index].position = Some(match axis {
+            Axis::Horizontal => (value, y),
+            Axis::Vertical => (x, value),
+        });
+    }
+}
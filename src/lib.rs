@@ -0,0 +1,20 @@
+//! The parts of the visual programming environment that don't need a running bevy app: the graph model and everything that reads or writes it, and the introspection plugin other apps can include. The editor binary (`main.rs`) builds its canvas on top of these.
+
+pub mod call_graph;
+pub mod diff;
+pub mod ecs_view;
+pub mod export;
+pub mod features;
+pub mod geometry;
+pub mod history;
+pub mod import;
+pub mod introspection;
+pub mod layout;
+pub mod metrics;
+pub mod model;
+pub mod pattern;
+pub mod rust_source;
+pub mod spatial;
+pub mod theme;
+pub mod trait_view;
+pub mod type_usage;
@@ -0,0 +1,161 @@
+//! Level of detail, picked from the camera scale. Zoomed in, everything is drawn. Further out, text and bend handles are left off and nodes are plain boxes. Furthest out, nodes shrink to dots and the edges are bundled between the containers they run between. Below full detail, containers with many children are drawn as collapsed summaries without touching the model's own collapsed state.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use petgraph::stable_graph::NodeIndex;
+
+use crate::camera::CanvasView;
+use crate::metrics_overlay::MetricOverlay;
+use crate::model::{self, EdgeKind, GraphModel, NodeData};
+use crate::theme::Theme;
+use crate::{Bounds, Graph, Node};
+
+/// Canvas units per screen pixel above which labels are too small to read, and nodes become plain boxes.
+const BOXES_SCALE: f32 = 1.5;
+
+/// Canvas units per screen pixel above which nodes become dots and edges are bundled.
+const DOTS_SCALE: f32 = 4.0;
+
+/// Containers with at least this many children are summarized below full detail.
+pub const SUMMARY_CHILDREN: usize = 25;
+
+/// How big a node is drawn at the dots tier, as a fraction of the theme's node size.
+const DOT_SIZE: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detail {
+    Full,
+    Boxes,
+    Dots,
+}
+
+impl Detail {
+    pub fn for_scale(scale: f32) -> Self {
+        if scale < BOXES_SCALE {
+            Detail::Full
+        } else if scale < DOTS_SCALE {
+            Detail::Boxes
+        } else {
+            Detail::Dots
+        }
+    }
+}
+
+/// The current tier, and the containers summarized at it. Only written when one of them changes, so that whatever depends on it can check `is_changed`.
+#[derive(Debug)]
+pub struct LevelOfDetail {
+    pub detail: Detail,
+    pub summarized: HashSet<NodeIndex>,
+}
+
+impl Default for LevelOfDetail {
+    fn default() -> Self {
+        LevelOfDetail {
+            detail: Detail::Full,
+            summarized: HashSet::new(),
+        }
+    }
+}
+
+impl LevelOfDetail {
+    /// Whether `container` is drawn collapsed, either because it is collapsed in the model or because it is summarized.
+    pub fn is_collapsed(&self, graph: &GraphModel, container: NodeIndex) -> bool {
+        graph[container].collapsed || self.summarized.contains(&container)
+    }
+
+    /// Whether `index` is out of sight inside of a container that is drawn collapsed.
+    pub fn is_hidden(&self, graph: &GraphModel, index: NodeIndex) -> bool {
+        model::visible_representative_with(graph, index, |container| self.is_collapsed(graph, container)) != index
+    }
+
+    /// The edges standing in for the ones of the kinds `counted` accepts that are hidden inside containers drawn collapsed. At the dots tier every edge is bundled between the outermost containers of its endpoints instead.
+    pub fn aggregated_edges(&self, graph: &GraphModel, counted: impl Fn(EdgeKind) -> bool) -> Vec<model::AggregatedEdge> {
+        match self.detail {
+            Detail::Dots => model::aggregated_edges_with(graph, |container| graph[container].kind.is_container(), counted),
+            _ => model::aggregated_edges_with(graph, |container| self.is_collapsed(graph, container), counted),
+        }
+    }
+}
+
+pub struct LodPlugin;
+
+impl Plugin for LodPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(LevelOfDetail::default())
+            .add_system(update_detail.system())
+            .add_system(shrink_nodes.system());
+    }
+}
+
+/// Picks the tier for the camera scale, and the containers to summarize at it.
+fn update_detail(view: Res<CanvasView>, graph: Res<Graph>, mut lod: ResMut<LevelOfDetail>) {
+    if !view.is_changed() && !graph.is_changed() {
+        return;
+    }
+
+    let detail = Detail::for_scale(view.scale);
+    let summarized = if detail == Detail::Full {
+        HashSet::new()
+    } else {
+        summaries(&graph.0)
+    };
+
+    if lod.detail != detail || lod.summarized != summarized {
+        lod.detail = detail;
+        lod.summarized = summarized;
+    }
+}
+
+/// The expanded containers with at least `SUMMARY_CHILDREN` children.
+fn summaries(graph: &GraphModel) -> HashSet<NodeIndex> {
+    let mut counts: HashMap<NodeIndex, usize> = HashMap::new();
+    for index in graph.node_indices() {
+        if let Some(parent) = graph[index].parent() {
+            *counts.entry(parent).or_insert(0) += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .filter(|(container, count)| *count >= SUMMARY_CHILDREN && !graph[*container].collapsed)
+        .map(|(container, _)| container)
+        .collect()
+}
+
+/// How big a node is drawn: at the dots tier scaled down to a dot in the middle of where it is, above it scaled by the metric overlay, if it is showing a metric by size.
+pub fn node_scale(lod: &LevelOfDetail, theme: &Theme, overlay: &MetricOverlay, node: Option<&NodeData>, bounds: &Bounds) -> Vec3 {
+    match lod.detail {
+        Detail::Dots => {
+            let dot = theme.node_size * DOT_SIZE;
+            let size = bounds.max - bounds.min;
+            Vec3::new(dot / size.x, dot / size.y, 1.0)
+        }
+        _ => {
+            let scale = node.map_or(1.0, |node| overlay.scale(node));
+            Vec3::new(scale, scale, 1.0)
+        }
+    }
+}
+
+/// Scales the nodes as `node_scale` has it. Their bounds stay as they were, which keeps them easy to hit.
+fn shrink_nodes(
+    lod: Res<LevelOfDetail>,
+    theme: Res<Theme>,
+    overlay: Res<MetricOverlay>,
+    graph: Res<Graph>,
+    added: Query<(), Added<Node>>,
+    mut nodes: Query<(&Node, &Bounds, &mut Transform)>,
+) {
+    if !lod.is_changed() && !theme.is_changed() && !overlay.is_changed() && added.iter().next().is_none() {
+        return;
+    }
+
+    for (node, bounds, mut transform) in nodes.iter_mut() {
+        let data = node.identity.and_then(|index| graph.0.node_weight(index));
+        let scale = node_scale(&lod, &theme, &overlay, data, bounds);
+        if transform.scale != scale {
+            transform.scale = scale;
+        }
+    }
+}
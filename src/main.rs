@@ -9,20 +9,44 @@ use strum_macros::EnumIter;
 use uuid::Uuid; // 0.17.1
 use Either::{Left, Right};
 
-use std::collections::HashMap;
 
 use bevy_inspector_egui::WorldInspectorPlugin;
-use bevy_mod_bounding::{debug, sphere::BSphere, *};
 use petgraph::stable_graph::{NodeIndex, EdgeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
 
 use bevy_prototype_lyon::{prelude::*, shapes, geometry::{GeometryBuilder, Geometry}};
 
 #[cfg(target_arch = "wasm32")]
 use bevy_webgl2::*;
 
-struct Cursor {
-    current_tool: Tools,
-}
+mod camera;
+mod culling;
+mod cursor;
+mod diff_menu;
+mod edge_filter;
+mod edges;
+mod export_menu;
+mod feature_menu;
+mod grid;
+mod groups;
+mod history_menu;
+mod hover;
+mod import_menu;
+mod labels;
+mod lod;
+mod metrics_overlay;
+mod minimap;
+mod palette;
+mod selection;
+mod source_menu;
+mod theme_menu;
+
+use visual_programming_environment::{
+    diff, export, features, geometry, import, introspection, layout, metrics, model, pattern, rust_source, theme,
+};
+use model::{EdgeData, EdgeKind, NodeData, NodeKind};
+use theme::Theme;
 
 #[derive(Debug, Clone)]
 struct Position {
@@ -31,14 +55,9 @@ struct Position {
     z: f32,
 }
 
-struct HandleMaterialMap {
-    tools: HashMap<Tools, Handle<StandardMaterial>>,
-    length: f32,
-    height: f32,
-}
-
 #[derive(Clone)]
 struct Node {
+    identity: Option<NodeIndex>,
 }
 enum Interaction {
     AddedNode(Entity),
@@ -46,6 +65,15 @@ enum Interaction {
     AddedEdge(NodeA, NodeB, Entity),
     RemovedNode(Entity),
     RemovedEdge(Entity),
+    AddedGroup(NodeIndex),
+    /// A node was moved from the first container into the second one (`None` being the top level).
+    Regrouped(NodeIndex, Option<NodeIndex>, Option<NodeIndex>),
+    /// A node's label was changed from the first string to the second.
+    Relabeled(NodeIndex, String, String),
+    /// An edge's label was changed from the first string to the second.
+    RelabeledEdge(EdgeIndex, String, String),
+    /// A node was dragged from the first position to the second.
+    Moved(NodeIndex, (f32, f32), (f32, f32)),
 }
 #[derive(Clone)]
 struct NodeA(Entity);
@@ -61,12 +89,22 @@ struct GraphInteractionHistory(Vec<Interaction>);
 
 #[derive(Clone)]
 struct Edge {
+    graph_identity: Option<EdgeIndex>,
 }
 
+/// The path an edge entity was drawn along, as legs between its waypoints (see `geometry::edge_route`). Kept on the entity so that clicking near an edge doesn't have to route it again.
+#[derive(Debug, Clone)]
+struct Route {
+    legs: Vec<Vec<Vec2>>,
+}
+
+/// Two clicks on the same thing within this many seconds are a double-click.
+const DOUBLE_CLICK_SECONDS: f64 = 0.4;
+
 
 use std::sync::Arc;
 
-struct Graph(StableGraph<(), ()>);
+struct Graph(model::GraphModel);
 
 
 
@@ -75,6 +113,7 @@ enum Tools {
     Selector,
     Node,
     Edge,
+    Group,
 }
 
 type EntityType = Tools;
@@ -90,8 +129,27 @@ struct InteractionHistory {
     history: Vec<Either<(Option<(Entity, EntityType)>, Tools), ActionTaken>>,
 }
 /// This fella represents the case in which case an interaction has been parsed and enacted. This will make it so that the next interaction doesn't read past interactions that have already been placed. For instance, in the case that the edge tool is selected and three different nodes A, B and then C are clicked. Without adding the ActionTaken to the interaction history, an edge would be added between A and B and then also an edge between B and C. This is not the desired behavior. When the enact_interaction system is triggered, it will add this struct to the `InteractionHistory`.
+#[derive(Clone)]
 struct ActionTaken;
 
+/// The rectangle an entity takes up on the canvas. Everything with bounds goes into the canvas index, which picking and culling look things up in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    min: Vec2,
+    max: Vec2,
+}
+
+impl Bounds {
+    fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x && point.y >= self.min.y && point.y <= self.max.y
+    }
+
+    fn area(&self) -> f32 {
+        let size = self.max - self.min;
+        size.x * size.y
+    }
+}
+
 /// This is a tag indicating the entities within the environment that have been placed on the grid.
 #[derive(Debug)]
 struct Placed {
@@ -108,8 +166,29 @@ pub fn main() {
     app.add_plugins(bevy::DefaultPlugins)
         .add_plugin(WorldInspectorPlugin::new())
         .add_plugin(EguiPlugin)
-        .add_plugin(BoundingVolumePlugin::<sphere::BSphere>::default())
-        // .add_plugin(BoundingVolumePlugin::<obb::Obb>::default())
+        .add_plugin(camera::CameraPlugin)
+        .add_plugin(lod::LodPlugin)
+        .add_plugin(culling::CullingPlugin)
+        .add_plugin(grid::GridPlugin)
+        .add_plugin(selection::SelectionPlugin)
+        .add_plugin(labels::LabelsPlugin)
+        .add_plugin(edges::EdgesPlugin)
+        .add_plugin(edge_filter::EdgeFilterPlugin)
+        .add_plugin(hover::HoverPlugin)
+        .add_plugin(cursor::CursorPlugin)
+        .add_plugin(groups::GroupsPlugin)
+        .add_plugin(export_menu::ExportMenuPlugin)
+        .add_plugin(import_menu::ImportMenuPlugin)
+        .add_plugin(source_menu::SourceMenuPlugin)
+        .add_plugin(diff_menu::DiffMenuPlugin)
+        .add_plugin(history_menu::HistoryMenuPlugin)
+        .add_plugin(feature_menu::FeatureMenuPlugin)
+        .add_plugin(minimap::MinimapPlugin)
+        .add_plugin(metrics_overlay::MetricsOverlayPlugin)
+        .add_plugin(palette::PalettePlugin)
+        .add_plugin(theme_menu::ThemePlugin)
+        // After `DefaultPlugins`, which sets the runner it hooks into.
+        .add_plugin(introspection::IntrospectionPlugin::default())
         .insert_resource(ToolHistory {
             current_tool: Tools::Selector,
             last_tool: None,
@@ -120,14 +199,12 @@ pub fn main() {
         .insert_resource(GraphInteractionHistory(Vec::new()))
         // .insert_resource(LastClickedEntity(None))
         .add_startup_system(setup.system())
-        // .add_system(visualize_graph.system())
-        .add_system(change_cursor_position.system())
+        .add_system(draw_graph.system())
         .insert_resource(Graph(
             StableGraph::new(),
             
         ))
         .add_system(tool_menu.system())
-        .add_system(change_tool.system())
         .add_system(check_what_is_clicked.system())
         .add_system(enact_interaction.system());
 
@@ -138,76 +215,36 @@ pub fn main() {
     app.run();
 }
 
-fn change_tool(
-    mut query: Query<(&mut Visible, &mut Handle<StandardMaterial>, &mut Mesh), With<Cursor>>,
-    handle_map: ResMut<HandleMaterialMap>,
-    tool_history: ResMut<ToolHistory>,
-) {
-    if tool_history.is_changed() {
-        info!("The tool history has been changed.");
-
-        if let Ok((mut visible, mut handle, mut mesh)) = query.single_mut() {
-            info!("Got the pbr object!");
-            if let Some(current_material) = handle_map.tools.get(&tool_history.current_tool) {
-                info!(
-                    "making the sprite bundle visible and changing the color material (hopefully)"
-                );
-
-                visible.is_transparent = false;
-                visible.is_visible = true;
-                *handle = current_material.to_owned();
-                // Todo: change the other properties of the tools here... For instance, make the Selector tool smaller. Probably I'll want to change the HandleMaterialMap to potentially have more properties to edit. I could also store a custom pbr bundle in each of the values of the map instead of the standard material. This would add complete customizability
-            }
-        }
-    }
-    // info!("The change_tool system has been triggered.");
-}
-
-fn adjust_cursor_position(window: &ResMut<Windows>, optional_position : Option<Position>) -> Option<(f32, f32)> {
+/// Converts a window position (or the cursor's, for `None`) to canvas coordinates, taking the camera's pan and zoom into account.
+fn adjust_cursor_position(window: &Windows, view: &camera::CanvasView, optional_position : Option<Position>) -> Option<(f32, f32)> {
     let window = window.get_primary().unwrap();
-
-    let mut adjust_x: f32 = window.width() / 2.0;
-    let adjust_y = window.height() / 2.0;
+    let size = camera::window_size(window);
 
     if let Some(position) = optional_position {
-        let mut x = position.x;
-        let mut y = position.y;
-        x = x - adjust_x;
-        y = y - adjust_y;
-        return Some((x, y));
+        let adjusted = view.screen_to_canvas(size, Vec2::new(position.x, position.y));
+        return Some((adjusted.x, adjusted.y));
     }
     else if let Some(position) = window.cursor_position() {
-        let mut x = position.x;
-        let mut y = position.y;
-        x = x - adjust_x;
-        y = y - adjust_y;
-        return Some((x, y));
+        let adjusted = view.screen_to_canvas(size, position);
+        return Some((adjusted.x, adjusted.y));
     }
     return None;
 }
 
-//bevy::math::f32::Vec3
-fn change_cursor_position(windows: ResMut<Windows>, mut query: Query<(&Cursor, &mut Transform)>) {
-    for (_potential_node, mut transform) in query.iter_mut() {
-        // If the node is already existing on the screen somewhere, we should transform it to the position of the mouse! Instead of iterating through... There should only be one potential node on the screen at once.
-
-        if let Some((x, y)) = adjust_cursor_position(&windows, None) {
-            // info!("The cursor is at the postion: x: {}, y: {}",x,y);
-            // Update the position of the sprite
-            transform.translation.x = x;
-            transform.translation.y = y;
-        }
-    }
-}
-
 fn enact_interaction(
     mut interaction: ResMut<InteractionHistory>,
-    handle_map: ResMut<HandleMaterialMap>,
-    commands: Commands,
-    window: ResMut<Windows>,
+    theme: Res<Theme>,
+    mut commands: Commands,
+    window: Res<Windows>,
+    view: Res<camera::CanvasView>,
+    grid: Res<grid::GridSettings>,
+    keys: Res<Input<KeyCode>>,
+    edge_defaults: Res<edges::EdgeDefaults>,
+    mut selection: ResMut<selection::Selection>,
     node_query : Query<(Entity,&Node, &Position)>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    // mut graph : ResMut<Graph>,
+    edge_query : Query<&Edge>,
+    frame_query : Query<&groups::GroupFrame>,
+    mut graph : ResMut<Graph>,
     mut graph_interaction_history : ResMut<GraphInteractionHistory>
 ) {
     if interaction.is_changed() {
@@ -216,24 +253,33 @@ fn enact_interaction(
             info!("Did an action.");
         }
 
+        // The entries are copied out of the history so that it can be pushed to (with `ActionTaken`) while they are still in use.
+        let last_interaction = interaction.history.last().cloned();
 
-        // In the case that the last interaction was not actually an action taken. In which case we don't want to take any action.
-        if let Some(Left((Some((entity, entity_type)), interacting_tool))) =
-            interaction.history.last()
-        {
-            let mut entity_tool_interaction = (entity_type, interacting_tool);
-            // The tool interacted with an entity... let's figure out what action to take... The main interaction will be placing an edge between two nodes if the last and second to last interactions were both acting on nodes with the edge
+        let mut second_to_last_interaction_history: Option<
+            Either<(Option<(Entity, EntityType)>, Tools), ActionTaken>,
+        > = None;
 
-            let mut second_to_last_interaction_history: Option<
-                &Either<(Option<(Entity, EntityType)>, Tools), ActionTaken>,
-            > = None;
+        if interaction.history.len() >= 2 {
+            let second_to_last_index = interaction.history.len() - 2;
+            second_to_last_interaction_history = interaction.history.get(second_to_last_index).cloned();
+        }
 
-            if interaction.history.len() >= 2 {
-                let second_to_last_index = interaction.history.len() - 2;
-                second_to_last_interaction_history = interaction.history.get(second_to_last_index);
+        // Both nodes and group frames can be clicked, this looks up which node of the graph model either of them stands for.
+        let graph_index_of = |entity: Entity| -> Option<NodeIndex> {
+            if let Ok((_, node, _)) = node_query.get(entity) {
+                return node.identity;
             }
+            frame_query.get(entity).ok().map(|frame| frame.group)
+        };
 
-            let mut second_to_last_entity_tool_interaction: Option<(&Entity, &EntityType, &Tools)> =
+        // In the case that the last interaction was not actually an action taken. In which case we don't want to take any action.
+        if let Some(Left((Some((entity, entity_type)), interacting_tool))) = last_interaction.clone()
+        {
+            let entity_tool_interaction = (entity_type, interacting_tool);
+            // The tool interacted with an entity... let's figure out what action to take... The main interaction will be placing an edge between two nodes if the last and second to last interactions were both acting on nodes with the edge
+
+            let mut second_to_last_entity_tool_interaction: Option<(Entity, EntityType, Tools)> =
                 None;
 
             if let Some(Left((Some((entity, entity_type)), interacting_tool))) =
@@ -248,188 +294,414 @@ fn enact_interaction(
                     // nothing to be done, there is no entity type associated with the selector tool
                 }
                 (Tools::Node, Tools::Selector) => {
-                    info!("Bring up the node on the info window")
+                    // Shift-clicking adds to (or takes away from) the selection instead of replacing it.
+                    if let Some(index) = graph_index_of(entity) {
+                        let extend = keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift);
+                        selection.select(index, extend);
+                    }
                 }
                 (Tools::Node, Tools::Node) => {
                     info!("The intent is probably to bring up an info panel of the node clicked")
                 }
                 (Tools::Node, Tools::Edge) => {
-                    // This is the case in which an edge is being added to a node. We need to determine if the second to last interaction was the node tool interacting with a *different* node than the last node.
+                    // This is the case in which an edge is being added to a node. We need to determine if the second to last interaction was the edge tool interacting with a node as well. Clicking the same node twice adds a self-loop, and edges can be added as often as wanted between the same two nodes.
                     if let Some((last_entity, EntityType::Node, Tools::Edge)) =
                         second_to_last_entity_tool_interaction
                     {
-                        if *entity != *last_entity {
-                            info!("should add an edge between the two entities here.");
-                            // I don't think that this actually triggers the interaction resource to be noted as changed since it is happening in this system itself (instead of in an external one... I still think it's important to register this though.)
-                            let mut node_a : Option<(NodeA, Node, Position)> = None;
-                            let mut node_b : Option<(NodeB, Node, Position)> = None;
-
-                            for (check_entity, node, position) in node_query.iter() {
-                                info!("checking if {:?} is either {:?} or {:?}", check_entity, *entity, *last_entity);
-                                
-                                if check_entity == *entity {
-                                    node_b = Some((NodeB(entity.clone()),node.clone(), position.clone()));
-                                }
-                                if check_entity == *last_entity {
-                                    node_a = Some((NodeA(last_entity.clone()), node.clone(), position.clone()));
-                                }
+                        info!("should add an edge between the two entities here.");
+                        // I don't think that this actually triggers the interaction resource to be noted as changed since it is happening in this system itself (instead of in an external one... I still think it's important to register this though.)
+                        let mut node_a : Option<(NodeA, Node)> = None;
+                        let mut node_b : Option<(NodeB, Node)> = None;
+
+                        for (check_entity, node, _) in node_query.iter() {
+                            if check_entity == entity {
+                                node_b = Some((NodeB(entity.clone()),node.clone()));
                             }
-
-                            let edge_entity : Option<Entity> = None;
-
-                            if node_b.is_some() && node_a.is_some() {
-                                edge_entity = Some(draw_edge(&commands, node_a.unwrap().2, node_b.unwrap().2));
+                            if check_entity == last_entity {
+                                node_a = Some((NodeA(last_entity.clone()), node.clone()));
                             }
-                            //     let node_index_a = node_a.unwrap().identity.unwrap();
-                            //     let node_index_b = node_b.unwrap().identity.unwrap();
-                                
-                            //     let mut weight = Edge{
-                            //         node_a: node_index_a,
-                            //         node_b : node_index_b,
-                            //         graph_identity: None,
-                            //         bevy_identity: None,
-                            //     };
-                                // let edge = graph.0.add_edge(node_index_a, node_index_b, weight.clone());
-
-
-                                // weight.graph_identity = Some(edge);
-                                if let Some(entity) = edge_entity {
-                                    let a = node_a.unwrap().0.clone();
-                                    let b = node_b.unwrap().0.clone();
-                                    graph_interaction_history.0.push(Interaction::AddedEdge(a,b,entity));
-                                }
-                                
-                                
-                            
+                        }
 
+                        if let (Some((a, node_a)), Some((b, node_b))) = (node_a, node_b) {
+                            if let (Some(index_a), Some(index_b)) = (node_a.identity, node_b.identity) {
+                                let mut weight = EdgeData::new(EdgeKind::Link);
+                                weight.routing = edge_defaults.routing;
+                                let edge_index = graph.0.add_edge(index_a, index_b, weight);
+
+                                // The edges already running between these two nodes are curved apart to make room for the new one.
+                                for sibling in model::edges_between(&graph.0, index_a, index_b) {
+                                    if sibling != edge_index {
+                                        forget_edge_drawing(&mut commands, &mut graph.0, sibling);
+                                    }
+                                }
 
-                            interaction.history.push(Right(ActionTaken));
+                                if let Some(edge_entity) = draw_model_edge(&mut commands, &theme, &mut graph.0, edge_index) {
+                                    graph_interaction_history.0.push(Interaction::AddedEdge(a,b,edge_entity));
+                                }
+                            }
                         }
+
+                        interaction.history.push(Right(ActionTaken));
+                    }
+                }
+                (Tools::Node, Tools::Group) => {
+                    // The group tool first clicks a container and then each of the nodes that should go into it.
+                    if let Some((last_entity, EntityType::Group, Tools::Group)) =
+                        second_to_last_entity_tool_interaction
+                    {
+                        regroup(&mut graph, &mut graph_interaction_history, graph_index_of(entity), graph_index_of(last_entity));
+                        interaction.history.push(Right(ActionTaken));
                     }
                 }
                 (Tools::Edge, Tools::Selector) => {
-                    info!("bring up this edge in the info panel, highlight the connector that was clicked")
+                    // The inspector window shows whatever is selected.
+                    if let Ok(Edge { graph_identity: Some(index) }) = edge_query.get(entity) {
+                        let extend = keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift);
+                        selection.select_edge(*index, extend);
+                    }
                 },
-                (Tools::Edge, Tools::Node) => 
+                (Tools::Edge, Tools::Node) =>
                 {
                     // This interaction doesn't make sense. Why would someone click a placed edge with the Node tool?
                 },
-                (Tools::Edge, Tools::Edge) => 
+                (Tools::Edge, Tools::Edge) =>
                 {
                     info!("bring up the info panel containing the information about the edge")
                 },
+                (Tools::Edge, Tools::Group) => {
+                    // Edges are never nested, the group they belong to follows from their endpoints.
+                }
+                (Tools::Group, Tools::Selector) => {
+                    info!("bring up the group in the info panel")
+                }
+                (Tools::Group, Tools::Node) | (Tools::Group, Tools::Edge) => {
+                    // Container frames aren't connected to anything directly; edges go between the nodes inside of them.
+                }
+                (Tools::Group, Tools::Group) => {
+                    // Clicking one container and then another nests the second one inside of the first.
+                    if let Some((last_entity, EntityType::Group, Tools::Group)) =
+                        second_to_last_entity_tool_interaction
+                    {
+                        if entity != last_entity {
+                            regroup(&mut graph, &mut graph_interaction_history, graph_index_of(entity), graph_index_of(last_entity));
+                            interaction.history.push(Right(ActionTaken));
+                        }
+                    }
+                }
             }
         }
-        if let Some(Left((None, interacting_tool))) = interaction.history.last() {
+        if let Some(Left((None, interacting_tool))) = last_interaction {
             // This is the case in which no component is selected but a tool is being used essentially on empty space
             match interacting_tool {
-                Tools::Selector => {}
+                Tools::Selector => {
+                    // Shift keeps the selection, for adding to it with a rubber band.
+                    if !(keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift)) {
+                        selection.clear();
+                    }
+                }
                 Tools::Node => {
 
-                    if let Some((x,y)) = adjust_cursor_position(&window, None) {
-
-                    
-                        let entity = draw_node(Position{x,y,z: 0.0}, handle_map, &commands, meshes, window);
-                            // let index = graph.0.add_node(node.clone());
-        
-                            // if let Some(node_weight) = graph.0.node_weight_mut(index.clone()) {
-                            //     node_weight.identity = Some(index);
-                            //     info!("updated the node weight... This is ideal.")
-                            // }
-                            
-                            // node.identity = Some(index.clone());
+                    if let Some((x,y)) = adjust_cursor_position(&window, &view, None) {
+                        let placed = grid.place(Vec2::new(x, y));
+                        let (x, y) = (placed.x, placed.y);
+                        let index = graph.0.add_node(NodeData::new("node", NodeKind::Item).at(x, y));
+                        graph.0[index].label = format!("node {}", index.index());
 
-                            let interaction = Interaction::AddedNode(entity);
+                        let label = graph.0[index].label.clone();
+                        let style = theme.node_style(NodeKind::Item);
+                        let entity = draw_node(index, &label, Position{x,y,z: 0.0}, (style.fill, style.stroke), &theme, &mut commands);
+                        graph.0[index].bevy_identity = Some(entity);
 
-                            graph_interaction_history.0.push(interaction);
-                        }
-        
-                            
-                     
+                        let interaction = Interaction::AddedNode(entity);
 
-                   },
+                        graph_interaction_history.0.push(interaction);
+                    }
+                },
                 Tools::Edge => {}
+                Tools::Group => {
+                    // The frame itself is drawn by the groups plugin once it notices the graph changed.
+                    if let Some((x,y)) = adjust_cursor_position(&window, &view, None) {
+                        let placed = grid.place(Vec2::new(x, y));
+                        let (x, y) = (placed.x, placed.y);
+                        let index = graph.0.add_node(NodeData::new("group", NodeKind::Group).at(x, y));
+                        graph.0[index].label = format!("group {}", index.index());
+
+                        graph_interaction_history.0.push(Interaction::AddedGroup(index));
+                    }
+                }
             }
         }
     }
 }
 
+/// Moves `child` into the container `group`, recording the move so that it can be undone.
+fn regroup(
+    graph: &mut Graph,
+    graph_interaction_history: &mut GraphInteractionHistory,
+    child: Option<NodeIndex>,
+    group: Option<NodeIndex>,
+) {
+    if let (Some(child), Some(group)) = (child, group) {
+        let previous = graph.0[child].parent();
+        if model::set_parent(&mut graph.0, child, Some(group)) {
+            graph_interaction_history.0.push(Interaction::Regrouped(child, previous, Some(group)));
+        } else {
+            info!("{:?} can't be nested inside of {:?}", child, group);
+        }
+    }
+}
 
 
+/// Draws whatever is in the graph model but not on the canvas yet, which is how imported diagrams show up. Nodes placed with the tools are drawn straight away by `enact_interaction` and are skipped here.
+fn draw_graph(
+    mut commands: Commands,
+    mut graph: ResMut<Graph>,
+    theme: Res<Theme>,
+    overlay: Res<metrics_overlay::MetricOverlay>,
+) {
+    if !graph.is_changed() {
+        return;
+    }
+
+    let undrawn_nodes: Vec<NodeIndex> = graph
+        .0
+        .node_indices()
+        .filter(|index| {
+            let node = &graph.0[*index];
+            // Containers are drawn as frames by the groups plugin.
+            node.bevy_identity.is_none() && !node.kind.is_container() && node.position.is_some()
+        })
+        .collect();
+    for index in undrawn_nodes {
+        if let Some((x, y)) = graph.0[index].position {
+            let node = &graph.0[index];
+            let (label, colors) = (node.label.clone(), node_colors(&theme, &overlay, node));
+            let entity = draw_node(index, &label, Position { x, y, z: 0.0 }, colors, &theme, &mut commands);
+            graph.0[index].bevy_identity = Some(entity);
+        }
+    }
+
+    let undrawn_edges: Vec<EdgeIndex> = graph
+        .0
+        .edge_indices()
+        .filter(|edge| graph.0[*edge].bevy_identity.is_none())
+        .collect();
+    for edge in undrawn_edges {
+        draw_model_edge(&mut commands, &theme, &mut graph.0, edge);
+    }
+}
 
-fn draw_edge(commands:  &Commands, position_a : Position, position_b : Position) -> Entity{
-    info!("Attempting to draw edge...");
-    let a = Vec2::new(position_a.x, position_a.y);
-    let b = Vec2::new(position_b.x, position_b.y);
-    
-    let line = shapes::Line(a, b);
+/// Despawns the entities drawing the node at `index` and the edges attached to it, so that `draw_graph` draws them again from the model. This is how a node that moved gets redrawn.
+fn forget_drawing(commands: &mut Commands, graph: &mut model::GraphModel, index: NodeIndex) {
+    if let Some(entity) = graph[index].bevy_identity.take() {
+        commands.entity(entity).despawn();
+    }
+
+    let edges: Vec<EdgeIndex> = graph
+        .edges_directed(index, Direction::Outgoing)
+        .chain(graph.edges_directed(index, Direction::Incoming))
+        .map(|edge| edge.id())
+        .collect();
+    for edge in edges {
+        forget_edge_drawing(commands, graph, edge);
+    }
+}
+
+/// Despawns the entity drawing `edge`, so that `draw_graph` routes and draws it again.
+fn forget_edge_drawing(commands: &mut Commands, graph: &mut model::GraphModel, edge: EdgeIndex) {
+    if let Some(entity) = graph[edge].bevy_identity.take() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Routes `edge` and draws it, linking the entity and the model to each other. Edges that changed in a diff are drawn in the color for how they changed.
+fn draw_model_edge(commands: &mut Commands, theme: &Theme, graph: &mut model::GraphModel, edge: EdgeIndex) -> Option<Entity> {
+    let legs = geometry::edge_route(graph, edge, theme.node_size)?;
+    let weight = &graph[edge];
+    let entity = draw_edge(commands, theme, weight.kind, theme.edge_color(weight), &geometry::flatten(&legs));
+    commands
+        .entity(entity)
+        .insert(Edge {
+            graph_identity: Some(edge),
+        })
+        .insert(route_bounds(&legs))
+        .insert(Route { legs });
+    graph[edge].bevy_identity = Some(entity);
+    Some(entity)
+}
+
+/// The rectangle around every point of a route.
+fn route_bounds(legs: &[Vec<Vec2>]) -> Bounds {
+    let points = geometry::flatten(legs);
+    let first = points.first().copied().unwrap_or(Vec2::ZERO);
+    let (min, max) = points
+        .iter()
+        .fold((first, first), |(min, max), point| (min.min(*point), max.max(*point)));
+    Bounds { min, max }
+}
+
+fn draw_edge(commands: &mut Commands, theme: &Theme, kind: EdgeKind, color: Color, points: &[Vec2]) -> Entity{
+    let style = theme.edge_style(kind);
+
+    let mut builder = PathBuilder::new();
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        match style.dash {
+            Some([on, off]) => {
+                for (start, end) in geometry::dashes(a, b, on, off) {
+                    builder.move_to(start);
+                    builder.line_to(end);
+                }
+            }
+            None => {
+                builder.move_to(a);
+                builder.line_to(b);
+            }
+        }
+    }
     let mut entity = commands.spawn_bundle(GeometryBuilder::build_as(
-        &line,
-        ShapeColors::new(Color::ORANGE_RED),
-        DrawMode::Fill(FillOptions::default()),
+        &builder.build(),
+        ShapeColors::new(color),
+        // A line has no area, so it needs to be stroked rather than filled to show up at all.
+        DrawMode::Stroke(StrokeOptions::default().with_line_width(style.width)),
         Transform::from_xyz(0.0, 0.0, -1.0)
     ));
-    entity.insert(Edge{});
+    entity.insert(Edge{
+        graph_identity: None,
+    });
 
     entity.id()
 }
+/// The fill and outline a node from the model is drawn with: its kind's style, with the fill taken from the metric overlay if it is showing one in color or else from how the node changed in a diff, and grayed out if the node is left out of the build.
+fn node_colors(theme: &Theme, overlay: &metrics_overlay::MetricOverlay, node: &NodeData) -> (Color, Color) {
+    let style = theme.node_style(node.kind);
+    let fill = overlay
+        .fill(node)
+        .or_else(|| diff::Change::of(&node.properties).map(|change| theme.change_color(change)))
+        .unwrap_or(style.fill);
+    if node.is_excluded() {
+        (theme::dimmed(fill), theme::dimmed(style.stroke))
+    } else {
+        (fill, style.stroke)
+    }
+}
+
 fn draw_node(
+    identity : NodeIndex,
+    label : &str,
     position : Position,
-    handle_map: ResMut<HandleMaterialMap>,
-    commands: &Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    window : ResMut<Windows>,
+    (fill, stroke): (Color, Color),
+    theme: &Theme,
+    commands: &mut Commands,
 ) -> Entity{
-    
-    let entity : Option<Entity> = None;
-
-    if let Some(material) = handle_map.tools.get(&Tools::Node) {
-        let x = position.x;
-        let y = position.y;
-        let z : f32 = 0.0;
-        info!("Placing the node at {:?}", position);
-            let node_entity = commands
-                .spawn_bundle(PbrBundle {
-                    visible: Visible {
-                        is_visible: true,
-                        is_transparent: false,
-                    },
-                    mesh: meshes.add(Mesh::from(shape::Cube {
-                        size: handle_map.length,
-                    })),
-                    material: material.clone().to_owned(),
-                    transform: Transform::from_xyz(x.clone(), y.clone(), z),
-                    ..Default::default()
-                })
-                .insert(Placed {
-                    position: position.clone(),
-                    entity_type: Tools::Node.clone(),
-                })
-                .insert(Bounded::<sphere::BSphere>::default())
-                .insert(debug::DebugBounds)
-                .insert(Node{
-                    
-                })
-                .insert(Position {
-                    x,y,z
-                }).id();
-                let entity = Some(node_entity.clone());
-                return node_entity;
-
-                
+    let x = position.x;
+    let y = position.y;
+    let z : f32 = 0.0;
+    // The node grows sideways to fit its label, which is drawn on top of it by the labels plugin.
+    let size = geometry::node_dimensions(label, theme.node_size);
+    let square = shapes::Rectangle {
+        width: size.x,
+        height: size.y,
+        origin: shapes::RectangleOrigin::Center,
+    };
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &square,
+            ShapeColors::outlined(fill, stroke),
+            DrawMode::Outlined {
+                fill_options: FillOptions::default(),
+                outline_options: StrokeOptions::default().with_line_width(1.5),
+            },
+            Transform::from_xyz(x, y, z),
+        ))
+        .insert(Placed {
+            position: position.clone(),
+            entity_type: Tools::Node.clone(),
+        })
+        .insert(Bounds {
+            min: Vec2::new(x, y) - size / 2.0,
+            max: Vec2::new(x, y) + size / 2.0,
+        })
+        .insert(Node{
+            identity: Some(identity),
+        })
+        .insert(Position {
+            x,y,z
+        }).id()
+}
+
+/// The visible placed entity under the cursor, if there is one. Container frames enclose the nodes inside of them, so when several entities are under the cursor the smallest one is the one that is meant. Only the entities the canvas index has at the cursor are looked at.
+fn entity_under_cursor(
+    cursor: (f32, f32),
+    index: &culling::CanvasIndex,
+    placed: &Query<(&Placed, &Bounds, &Visible)>,
+) -> Option<(Entity, EntityType)> {
+    let cursor = Vec2::new(cursor.0, cursor.1);
+    let mut hit: Option<(Entity, EntityType, f32)> = None;
+
+    for entity in index.0.at_point(cursor) {
+        let (placed, bounds, visible) = match placed.get(entity) {
+            Ok(found) => found,
+            Err(_) => continue,
+        };
+        if !visible.is_visible || !bounds.contains(cursor) {
+            // Hidden inside of a collapsed container.
+            continue;
+        }
+        let area = bounds.area();
+        if hit.as_ref().map_or(true, |(_, _, smallest)| area < *smallest) {
+            hit = Some((entity, placed.entity_type.clone(), area));
+        }
+    }
+
+    hit.map(|(entity, entity_type, _)| (entity, entity_type))
+}
+
+/// The visible edge closest to the cursor, if one passes within `tolerance` of it, along with the leg (see `Route`) that was hit.
+fn edge_under_cursor(
+    cursor: (f32, f32),
+    tolerance: f32,
+    index: &culling::CanvasIndex,
+    routes: &Query<(&Route, &Visible)>,
+) -> Option<(Entity, usize)> {
+    let cursor = Vec2::new(cursor.0, cursor.1);
+    let reach = Vec2::splat(tolerance);
+    let candidates = index
+        .0
+        .in_rect(cursor - reach, cursor + reach)
+        .into_iter()
+        .filter_map(|entity| match routes.get(entity) {
+            Ok((route, visible)) if visible.is_visible => Some((entity, route.legs.as_slice())),
+            _ => None,
+        });
+    geometry::nearest_leg(cursor, tolerance, candidates)
+}
+
+/// What a click at `cursor` lands on. Nodes are on top of edges, and edges on top of the container frames they run across.
+fn clicked_entity(
+    cursor: (f32, f32),
+    tolerance: f32,
+    index: &culling::CanvasIndex,
+    placed: &Query<(&Placed, &Bounds, &Visible)>,
+    routes: &Query<(&Route, &Visible)>,
+) -> Option<(Entity, EntityType)> {
+    match entity_under_cursor(cursor, index, placed) {
+        Some((entity, Tools::Node)) => Some((entity, Tools::Node)),
+        behind => edge_under_cursor(cursor, tolerance, index, routes)
+            .map(|(entity, _)| (entity, Tools::Edge))
+            .or(behind),
     }
-    entity.unwrap()
 }
 
 fn check_what_is_clicked(
     egui_context: ResMut<EguiContext>,
     buttons: Res<Input<MouseButton>>,
-    query: Query<(Entity, &Placed, &BSphere)>,
-    window: ResMut<Windows>,
-    commands: Commands,
-    handle_map: ResMut<HandleMaterialMap>,
-    tool_history: ResMut<ToolHistory>,
-    meshes: ResMut<Assets<Mesh>>,
+    canvas_index: Res<culling::CanvasIndex>,
+    query: Query<(&Placed, &Bounds, &Visible)>,
+    edges: Query<(&Route, &Visible)>,
+    window: Res<Windows>,
+    view: Res<camera::CanvasView>,
+    tool_history: Res<ToolHistory>,
 
     mut interaction_history: ResMut<InteractionHistory>,
 ) {
@@ -437,34 +709,24 @@ fn check_what_is_clicked(
         // Left button was pressed
         // check what was clicked by having a query that looks up everything with a position
         let primary_window = window.get_primary().unwrap();
-        if let Some(position) = primary_window.cursor_position() {
+        if let Some(_position) = primary_window.cursor_position() {
             if egui_context.ctx().is_pointer_over_area() {
                 info!("Also clicked on a egui window, shouldn't try placing an icon.");
             } else {
                 let current_tool = tool_history.current_tool.clone();
-
-                for (entity, placed, bounded) in query.iter() {
-                    if let Some(cursor) = adjust_cursor_position(&window, None) {
-                        // info!("mouse info: {:?}\nbounded info: {:?}\nplaced info: {:?}", cursor, bounded, placed);
-
-                        let mesh_radius = bounded.mesh_space_radius();
-
-                        let (cursor_x, cursor_y) = cursor;
-                        let mouse_position = Vec3::new(cursor_x, cursor_y, 0.0);
-                        let placed_position = Vec3::new(placed.position.x, placed.position.y, 0.0);
-
-                        // If we are inside the bound of the placed entity
-                        if mouse_position.distance(placed_position) < *mesh_radius {
-                            info!("I should select the icon here. It is represented by the entity {:?}", entity);
-
-                            interaction_history.history.push(Left((
-                                Some((entity.clone(), placed.entity_type.clone())),
-                                current_tool.clone(),
-                            )));
-                            // we do not want to potentially register clicking two entities at the same time. If we accidentally click on two bounding boxes at the same time (where they overlap), then we need to break this loop
-                            return;
-                        }
-                    }
+                let tolerance = edges::EDGE_TOLERANCE * view.scale;
+                let clicked = adjust_cursor_position(&window, &view, None)
+                    .and_then(|cursor| clicked_entity(cursor, tolerance, &canvas_index, &query, &edges));
+
+                if let Some((entity, entity_type)) = clicked {
+                    info!("I should select the icon here. It is represented by the entity {:?}", entity);
+
+                    // we do not want to potentially register clicking two entities at the same time, so only the innermost entity is pushed.
+                    interaction_history.history.push(Left((
+                        Some((entity, entity_type)),
+                        current_tool,
+                    )));
+                    return;
                 }
                 // This means that no entity has been clicked, nor has the egui interface... So... if the tool is a `Tools::Node` we should place a node
                 interaction_history
@@ -475,100 +737,11 @@ fn check_what_is_clicked(
     }
 }
 
-fn setup(
-    mut commands: Commands,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-) {
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
-    // commands.insert_resource(Graph(UnGraph::new().add_node(Node::new())))
-    let mut handle_map: HandleMaterialMap = HandleMaterialMap {
-        tools: HashMap::new(),
-        length: 30.0,
-        height: 17.0,
-    };
 
+fn setup(mut commands: Commands) {
     commands
-        .spawn_bundle(PbrBundle {
-            // sprite: Sprite::new(Vec2::new(handle_map.length, handle_map.height)),
-            mesh: meshes.add(Mesh::from(shape::Cube {
-                size: handle_map.length,
-            })),
-            visible: Visible {
-                is_visible: true,
-                is_transparent: false,
-            },
-            transform: Transform {
-                translation: Vec3::new(0.0, 0.0, 0.0),
-                ..Default::default()
-            },
-            ..Default::default()
-        })
-        .insert(Cursor {
-            current_tool: Tools::Selector,
-        });
-
-    // Oh, right, pbr requires light :shocked_pichachu:
-    commands.spawn_bundle(LightBundle {
-        transform: Transform::from_translation(Vec3::new(4.0, 8.0, 4.0)),
-        light: Light {
-            intensity: 1.0,
-            ..Default::default()
-        },
-        ..Default::default()
-    });
-
-    // for each of the potentially selected tools, let's insert a resource to represent that tool. Using this construction will guarantee that the system will not compile unless there is an allocated resource for each of the tools.
-    for variant in Tools::iter() {
-        match variant {
-            Tools::Edge => {
-                let handle = materials.add(StandardMaterial {
-                    base_color: Color::Rgba {
-                        red: 1.0,
-                        green: 0.0,
-                        blue: 0.0,
-                        alpha: 0.5,
-                    },
-                    roughness: 0.7,
-                    metallic: 0.7,
-                    ..Default::default()
-                });
-                handle_map.tools.insert(Tools::Edge, handle.clone());
-            }
-            Tools::Selector => {
-                let handle = materials.add(StandardMaterial {
-                    base_color: Color::Rgba {
-                        red: 0.0,
-                        green: 1.0,
-                        blue: 0.0,
-                        alpha: 0.0,
-                    },
-                    roughness: 0.7,
-                    metallic: 0.7,
-                    unlit: false,
-                    ..Default::default()
-                });
-                handle_map.tools.insert(Tools::Selector, handle.clone());
-            }
-            Tools::Node => {
-                let handle = materials.add(StandardMaterial {
-                    base_color: Color::Rgba {
-                        red: 0.0,
-                        green: 0.0,
-                        blue: 1.0,
-                        alpha: 0.5,
-                    },
-                    roughness: 0.7,
-                    metallic: 0.7,
-                    unlit: false,
-                    ..Default::default()
-                });
-                handle_map.tools.insert(Tools::Node, handle.clone());
-            }
-        }
-    }
-
-    commands.insert_resource(handle_map);
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(camera::MainCamera);
 }
 
 // Egui stuff will go below here :]
@@ -596,7 +769,15 @@ pub fn update_ui_scale_factor(
 // Note the usage of `ResMut`. Even though `ctx` method doesn't require
 // mutability, accessing the context from different threads will result
 // into panic if you don't enable `egui/multi_threaded` feature.
-fn tool_menu(egui_context: ResMut<EguiContext>, mut tool_history: ResMut<ToolHistory>) {
+fn tool_menu(
+    egui_context: ResMut<EguiContext>,
+    mut tool_history: ResMut<ToolHistory>,
+    mut grid: ResMut<grid::GridSettings>,
+    selection: Res<selection::Selection>,
+    mut arrange: EventWriter<grid::ArrangeSelected>,
+    edge_defaults: Res<edges::EdgeDefaults>,
+    mut set_routing: EventWriter<edges::SetRouting>,
+) {
     egui::Window::new("Toolbox").show(egui_context.ctx(), |ui| {
         for variant in Tools::iter() {
             match variant {
@@ -627,7 +808,63 @@ fn tool_menu(egui_context: ResMut<EguiContext>, mut tool_history: ResMut<ToolHis
                         bevy::log::info!("Selected the edge tool!");
                     };
                 }
+                Tools::Group => {
+                    let group_button = ui.add(egui::Button::new("Group Tool"));
+                    if group_button.clicked() {
+                        let last_tool = tool_history.current_tool.clone();
+                        tool_history.current_tool = Tools::Group;
+                        tool_history.last_tool = Some(last_tool);
+                        bevy::log::info!("Selected the group tool!");
+                    };
+                }
             }
         }
+
+        ui.separator();
+        ui.label("Grid");
+        // Edited on a copy so that the grid is only redrawn when something was actually changed.
+        let (mut visible, mut snap, mut spacing) = (grid.visible, grid.snap, grid.spacing);
+        ui.checkbox(&mut visible, "Show grid");
+        ui.checkbox(&mut snap, "Snap to grid");
+        ui.add(egui::Slider::new(&mut spacing, 5.0..=200.0).text("spacing"));
+        if (visible, snap, spacing) != (grid.visible, grid.snap, grid.spacing) {
+            grid.visible = visible;
+            grid.snap = snap;
+            grid.spacing = spacing;
+        }
+
+        ui.separator();
+        ui.label(format!("Selected: {} nodes", selection.nodes.len()));
+        ui.horizontal(|ui| {
+            if ui.button("Align rows").clicked() {
+                arrange.send(grid::ArrangeSelected::Align(layout::Axis::Horizontal));
+            }
+            if ui.button("Align columns").clicked() {
+                arrange.send(grid::ArrangeSelected::Align(layout::Axis::Vertical));
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Distribute horizontally").clicked() {
+                arrange.send(grid::ArrangeSelected::Distribute(layout::Axis::Horizontal));
+            }
+            if ui.button("Distribute vertically").clicked() {
+                arrange.send(grid::ArrangeSelected::Distribute(layout::Axis::Vertical));
+            }
+        });
+        if ui.button("Snap selected to grid").clicked() {
+            arrange.send(grid::ArrangeSelected::SnapToGrid);
+        }
+
+        ui.separator();
+        ui.label("Edge routing");
+        ui.horizontal(|ui| {
+            for routing in model::Routing::iter() {
+                let name = format!("{:?}", routing);
+                if ui.selectable_label(edge_defaults.routing == routing, name).clicked() {
+                    set_routing.send(edges::SetRouting(routing));
+                }
+            }
+        });
+        ui.label("Double-click an edge to add a bend, double-click a bend to remove it.");
     });
 }
@@ -0,0 +1,299 @@
+//! Code metrics for the nodes of the views built from Rust sources, stored as node properties (see `Metric::property`) so that they travel with the graph: lines of code, number of items, afferent and efferent coupling, instability and the cyclomatic complexity of functions.
+//!
+//! Size and complexity come from the items the nodes were found at (`model::SOURCE_PROPERTY`), and containers add up their children. Coupling goes by the edges of the view: an edge leaving a node or a container it is in counts towards its efferent coupling, one coming in towards its afferent coupling, each other end only counting once.
+
+use std::collections::{HashMap, HashSet};
+
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use strum_macros::EnumIter;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use crate::model::{self, GraphModel, NodeData};
+use crate::rust_source::{for_each_item, ParsedFile};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+pub enum Metric {
+    Lines,
+    Items,
+    /// The number of nodes outside of a node that depend on it.
+    Afferent,
+    /// The number of nodes outside of a node that it depends on.
+    Efferent,
+    /// Efferent coupling over total coupling: 0 for a node that only others depend on, 1 for one that only depends on others.
+    Instability,
+    Complexity,
+}
+
+impl Metric {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Lines => "Lines of code",
+            Metric::Items => "Items",
+            Metric::Afferent => "Afferent coupling",
+            Metric::Efferent => "Efferent coupling",
+            Metric::Instability => "Instability",
+            Metric::Complexity => "Cyclomatic complexity",
+        }
+    }
+
+    /// The node property the metric is stored in.
+    pub fn property(&self) -> &'static str {
+        match self {
+            Metric::Lines => "lines",
+            Metric::Items => "items",
+            Metric::Afferent => "afferent",
+            Metric::Efferent => "efferent",
+            Metric::Instability => "instability",
+            Metric::Complexity => "complexity",
+        }
+    }
+
+    pub fn value(&self, node: &NodeData) -> Option<f32> {
+        node.properties.get(self.property())?.parse().ok()
+    }
+
+    /// The value of a metric that counts something, such as the lines of code.
+    fn count(&self, node: &NodeData) -> Option<usize> {
+        node.properties.get(self.property())?.parse().ok()
+    }
+
+    fn set(&self, node: &mut NodeData, value: impl ToString) {
+        node.properties.insert(self.property().to_string(), value.to_string());
+    }
+}
+
+/// Counts the decision points of a function body. Functions defined inside of it are counted on their own.
+struct Complexity(usize);
+
+impl<'ast> Visit<'ast> for Complexity {
+    fn visit_expr_if(&mut self, expression: &'ast syn::ExprIf) {
+        self.0 += 1;
+        visit::visit_expr_if(self, expression);
+    }
+
+    fn visit_expr_while(&mut self, expression: &'ast syn::ExprWhile) {
+        self.0 += 1;
+        visit::visit_expr_while(self, expression);
+    }
+
+    fn visit_expr_for_loop(&mut self, expression: &'ast syn::ExprForLoop) {
+        self.0 += 1;
+        visit::visit_expr_for_loop(self, expression);
+    }
+
+    fn visit_expr_match(&mut self, expression: &'ast syn::ExprMatch) {
+        self.0 += expression.arms.len().saturating_sub(1);
+        visit::visit_expr_match(self, expression);
+    }
+
+    fn visit_expr_try(&mut self, expression: &'ast syn::ExprTry) {
+        self.0 += 1;
+        visit::visit_expr_try(self, expression);
+    }
+
+    fn visit_bin_op(&mut self, operator: &'ast syn::BinOp) {
+        if matches!(operator, syn::BinOp::And(_) | syn::BinOp::Or(_)) {
+            self.0 += 1;
+        }
+    }
+
+    fn visit_item_fn(&mut self, _: &'ast syn::ItemFn) {}
+}
+
+fn complexity(body: &syn::Block) -> usize {
+    let mut complexity = Complexity(1);
+    complexity.visit_block(body);
+    complexity.0
+}
+
+fn lines(spanned: &impl Spanned) -> usize {
+    let span = spanned.span();
+    span.end().line.saturating_sub(span.start().line) + 1
+}
+
+/// What is known about an item from its source: its lines, the number of items in it, and its complexity if it is a function.
+type ItemMetrics = (usize, Option<usize>, Option<usize>);
+
+/// The metrics of every item that views put a node at, by its location (`path:line` of its name).
+fn item_metrics(files: &[ParsedFile]) -> HashMap<String, ItemMetrics> {
+    let mut found = HashMap::new();
+    for file in files {
+        for_each_item(file, |_, item| match item {
+            syn::Item::Fn(function) => {
+                let metrics = (lines(item), None, Some(complexity(&function.block)));
+                found.insert(file.location(&function.sig.ident), metrics);
+            }
+            syn::Item::Struct(structure) => {
+                let metrics = (lines(item), Some(structure.fields.len()), None);
+                found.insert(file.location(&structure.ident), metrics);
+            }
+            syn::Item::Enum(enumeration) => {
+                let metrics = (lines(item), Some(enumeration.variants.len()), None);
+                found.insert(file.location(&enumeration.ident), metrics);
+            }
+            syn::Item::Trait(definition) => {
+                let metrics = (lines(item), Some(definition.items.len()), None);
+                found.insert(file.location(&definition.ident), metrics);
+                for trait_item in &definition.items {
+                    if let syn::TraitItem::Method(syn::TraitItemMethod {
+                        sig,
+                        default: Some(body),
+                        ..
+                    }) = trait_item
+                    {
+                        found.insert(file.location(&sig.ident), (lines(trait_item), None, Some(complexity(body))));
+                    }
+                }
+            }
+            syn::Item::Impl(implementation) => {
+                for impl_item in &implementation.items {
+                    if let syn::ImplItem::Method(method) = impl_item {
+                        let metrics = (lines(method), None, Some(complexity(&method.block)));
+                        found.insert(file.location(&method.sig.ident), metrics);
+                    }
+                }
+            }
+            _ => {}
+        });
+    }
+    found
+}
+
+/// Stores the metrics of every node of `graph` that they can be worked out for.
+pub fn annotate(graph: &mut GraphModel, files: &[ParsedFile]) {
+    let found = item_metrics(files);
+    for index in graph.node_indices().collect::<Vec<_>>() {
+        let metrics = graph[index]
+            .properties
+            .get(model::SOURCE_PROPERTY)
+            .and_then(|location| found.get(location))
+            .copied();
+        if let Some((lines, items, complexity)) = metrics {
+            let node = &mut graph[index];
+            Metric::Lines.set(node, lines);
+            if let Some(items) = items {
+                Metric::Items.set(node, items);
+            }
+            if let Some(complexity) = complexity {
+                Metric::Complexity.set(node, complexity);
+            }
+        }
+    }
+
+    // Deepest first, so that the containers inside of a container have their size by the time it is added up.
+    let mut containers: Vec<NodeIndex> = graph
+        .node_indices()
+        .filter(|index| graph[*index].kind.is_container())
+        .collect();
+    containers.sort_by_key(|index| std::cmp::Reverse(model::depth(graph, *index)));
+    for container in containers {
+        let children = model::children(graph, container);
+        let lines: usize = children
+            .iter()
+            .filter_map(|child| Metric::Lines.count(&graph[*child]))
+            .sum();
+        let node = &mut graph[container];
+        Metric::Lines.set(node, lines);
+        Metric::Items.set(node, children.len());
+    }
+
+    let mut afferent: HashMap<NodeIndex, HashSet<NodeIndex>> = HashMap::new();
+    let mut efferent: HashMap<NodeIndex, HashSet<NodeIndex>> = HashMap::new();
+    for edge in graph.edge_references() {
+        let (source, target) = (edge.source(), edge.target());
+        let mut around_source = model::ancestors(graph, source);
+        around_source.push(source);
+        let mut around_target = model::ancestors(graph, target);
+        around_target.push(target);
+        for node in around_source.iter().filter(|node| !around_target.contains(node)) {
+            efferent.entry(*node).or_insert_with(HashSet::new).insert(target);
+        }
+        for node in around_target.iter().filter(|node| !around_source.contains(node)) {
+            afferent.entry(*node).or_insert_with(HashSet::new).insert(source);
+        }
+    }
+    for index in graph.node_indices().collect::<Vec<_>>() {
+        let ca = afferent.get(&index).map_or(0, HashSet::len);
+        let ce = efferent.get(&index).map_or(0, HashSet::len);
+        let node = &mut graph[index];
+        Metric::Afferent.set(node, ca);
+        Metric::Efferent.set(node, ce);
+        if ca + ce > 0 {
+            Metric::Instability.set(node, format!("{:.2}", ce as f32 / (ca + ce) as f32));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{EdgeData, EdgeKind, NodeKind};
+
+    fn complexity_of(function: &str) -> usize {
+        let function: syn::ItemFn = syn::parse_str(function).unwrap();
+        complexity(&function.block)
+    }
+
+    #[test]
+    fn decision_points() {
+        assert_eq!(complexity_of("fn f() {}"), 1);
+        assert_eq!(complexity_of("fn f(a: bool) { if a {} else {} }"), 2);
+        assert_eq!(complexity_of("fn f(a: u8) { match a { 0 => {} 1 => {} _ => {} } }"), 3);
+        assert_eq!(complexity_of("fn f(a: Option<u8>) -> Option<u8> { let b = a?; Some(b) }"), 2);
+        assert_eq!(complexity_of("fn f(a: bool, b: bool, c: bool) -> bool { a && b || c }"), 3);
+        assert_eq!(
+            complexity_of("fn f(a: Option<u8>, b: bool) -> Option<u8> { if b && a? > 1 { None } else { a } }"),
+            4
+        );
+    }
+
+    #[test]
+    fn nested_functions_count_on_their_own() {
+        assert_eq!(complexity_of("fn outer(a: bool) { fn inner(b: bool) { if b {} if b {} } if a {} }"), 2);
+    }
+
+    #[test]
+    fn coupling_and_size_of_containers() {
+        let mut graph = GraphModel::default();
+        let krate = graph.add_node(NodeData::new("app", NodeKind::Crate));
+        let a = graph.add_node(NodeData::new("a", NodeKind::Module));
+        let b = graph.add_node(NodeData::new("b", NodeKind::Module));
+        let a1 = graph.add_node(NodeData::new("a1", NodeKind::Function));
+        let a2 = graph.add_node(NodeData::new("a2", NodeKind::Function));
+        let b1 = graph.add_node(NodeData::new("b1", NodeKind::Function));
+        let outside = graph.add_node(NodeData::new("outside", NodeKind::Function));
+        for (module, parent) in &[(a, krate), (b, krate)] {
+            model::set_parent(&mut graph, *module, Some(*parent));
+        }
+        for (item, module, lines) in &[(a1, a, 3), (a2, a, 4), (b1, b, 5)] {
+            model::set_parent(&mut graph, *item, Some(*module));
+            Metric::Lines.set(&mut graph[*item], lines);
+        }
+        for (source, target) in &[(a1, a2), (a1, b1), (a2, b1), (b1, outside)] {
+            graph.add_edge(*source, *target, EdgeData::new(EdgeKind::Calls));
+        }
+
+        annotate(&mut graph, &[]);
+
+        let count = |index: NodeIndex, metric: Metric| metric.count(&graph[index]);
+        // Containers add up their children's lines as whole numbers.
+        assert_eq!(graph[a].properties[Metric::Lines.property()], "7");
+        assert_eq!(count(krate, Metric::Lines), Some(12));
+        assert_eq!(count(krate, Metric::Items), Some(2));
+
+        // Edges within a container don't couple it, and each other end counts once.
+        let coupling = |index: NodeIndex| (count(index, Metric::Afferent), count(index, Metric::Efferent));
+        assert_eq!(coupling(a1), (Some(0), Some(2)));
+        assert_eq!(coupling(a2), (Some(1), Some(1)));
+        assert_eq!(coupling(b1), (Some(2), Some(1)));
+        assert_eq!(coupling(a), (Some(0), Some(1)));
+        assert_eq!(coupling(b), (Some(2), Some(1)));
+        assert_eq!(coupling(krate), (Some(0), Some(1)));
+        assert_eq!(coupling(outside), (Some(1), Some(0)));
+
+        assert_eq!(Metric::Instability.value(&graph[a1]), Some(1.0));
+        assert_eq!(Metric::Instability.value(&graph[b]), Some(0.33));
+    }
+}
@@ -0,0 +1,281 @@
+//! The Metrics window: shows one of the code metrics stored on nodes (see `metrics`) on the canvas, either as the color of the nodes or as their size, with a legend for it and a table of every node's metrics that can be sorted by any of them.
+
+use std::cmp::Ordering;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use petgraph::stable_graph::NodeIndex;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::metrics::Metric;
+use crate::model::NodeData;
+use crate::selection::Selection;
+use crate::{forget_drawing, Graph};
+
+/// The colors of the lowest and the highest value of a metric, with the ones in between mixed from them.
+const LOW_COLOR: (f32, f32, f32) = (0.30, 0.69, 0.31);
+const HIGH_COLOR: (f32, f32, f32) = (0.90, 0.22, 0.21);
+
+/// The scale of the nodes with the lowest and the highest value of a metric.
+const MIN_SCALE: f32 = 0.6;
+const MAX_SCALE: f32 = 1.8;
+
+/// The number of steps the color legend is drawn in.
+const LEGEND_STEPS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum OverlayMode {
+    Color,
+    Size,
+}
+
+impl OverlayMode {
+    fn name(&self) -> &'static str {
+        match self {
+            OverlayMode::Color => "Color",
+            OverlayMode::Size => "Size",
+        }
+    }
+}
+
+/// The metric shown on the canvas, if any, and how.
+pub struct MetricOverlay {
+    pub metric: Option<Metric>,
+    pub mode: OverlayMode,
+    /// The lowest and highest value of the metric in the graph.
+    range: Option<(f32, f32)>,
+}
+
+impl Default for MetricOverlay {
+    fn default() -> Self {
+        MetricOverlay {
+            metric: None,
+            mode: OverlayMode::Color,
+            range: None,
+        }
+    }
+}
+
+impl MetricOverlay {
+    /// Where the metric of `node` lies between the lowest and the highest value, from 0 to 1.
+    fn fraction(&self, node: &NodeData) -> Option<f32> {
+        let value = self.metric?.value(node)?;
+        let (min, max) = self.range?;
+        if max > min {
+            Some((value - min) / (max - min))
+        } else {
+            Some(0.0)
+        }
+    }
+
+    /// The fill `node` is drawn with, when the metric is shown in color and the node has it.
+    pub fn fill(&self, node: &NodeData) -> Option<Color> {
+        match self.mode {
+            OverlayMode::Color => self.fraction(node).map(gradient),
+            OverlayMode::Size => None,
+        }
+    }
+
+    /// How much `node` is scaled, when the metric is shown by size.
+    pub fn scale(&self, node: &NodeData) -> f32 {
+        match (self.mode, self.fraction(node)) {
+            (OverlayMode::Size, Some(fraction)) => MIN_SCALE + fraction * (MAX_SCALE - MIN_SCALE),
+            _ => 1.0,
+        }
+    }
+}
+
+fn gradient(fraction: f32) -> Color {
+    let mix = |low: f32, high: f32| low + (high - low) * fraction;
+    Color::rgb(
+        mix(LOW_COLOR.0, HIGH_COLOR.0),
+        mix(LOW_COLOR.1, HIGH_COLOR.1),
+        mix(LOW_COLOR.2, HIGH_COLOR.2),
+    )
+}
+
+fn color32(color: Color) -> egui::Color32 {
+    egui::Color32::from_rgb(
+        (color.r() * 255.0).round() as u8,
+        (color.g() * 255.0).round() as u8,
+        (color.b() * 255.0).round() as u8,
+    )
+}
+
+/// How the metrics table is sorted: by a metric, or by name when there is none, and in which direction.
+#[derive(Default)]
+struct TableOrder {
+    column: Option<Metric>,
+    descending: bool,
+}
+
+pub struct MetricsOverlayPlugin;
+
+impl Plugin for MetricsOverlayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(MetricOverlay::default())
+            .insert_resource(TableOrder::default())
+            .add_system(update_range.system())
+            .add_system(redraw_nodes.system())
+            .add_system(metrics_menu.system());
+    }
+}
+
+/// Keeps the range of the metric shown in step with the graph.
+fn update_range(graph: Res<Graph>, mut overlay: ResMut<MetricOverlay>) {
+    if !graph.is_changed() && !overlay.is_changed() {
+        return;
+    }
+    let range = overlay.metric.and_then(|metric| {
+        graph
+            .0
+            .node_weights()
+            .filter_map(|node| metric.value(node))
+            .fold(None, |range: Option<(f32, f32)>, value| match range {
+                Some((min, max)) => Some((min.min(value), max.max(value))),
+                None => Some((value, value)),
+            })
+    });
+    // Only written when it changes, so that the nodes aren't drawn again for nothing.
+    if overlay.range != range {
+        overlay.range = range;
+    }
+}
+
+/// Draws every node again when what the overlay shows changes, since their colors are set when they are drawn. Scaling is left to the level of detail, and containers keep their frames.
+fn redraw_nodes(mut commands: Commands, overlay: Res<MetricOverlay>, mut graph: ResMut<Graph>) {
+    if !overlay.is_changed() {
+        return;
+    }
+    let drawn: Vec<NodeIndex> = graph
+        .0
+        .node_indices()
+        .filter(|index| {
+            let node = &graph.0[*index];
+            node.bevy_identity.is_some() && !node.kind.is_container()
+        })
+        .collect();
+    for index in drawn {
+        forget_drawing(&mut commands, &mut graph.0, index);
+    }
+}
+
+fn metrics_menu(
+    egui_context: ResMut<EguiContext>,
+    graph: Res<Graph>,
+    mut overlay: ResMut<MetricOverlay>,
+    mut order: ResMut<TableOrder>,
+    mut selection: ResMut<Selection>,
+) {
+    let (mut metric, mut mode) = (overlay.metric, overlay.mode);
+    let mut sort_by: Option<Option<Metric>> = None;
+    let mut clicked: Option<NodeIndex> = None;
+
+    egui::Window::new("Metrics").show(egui_context.ctx(), |ui| {
+        ui.horizontal_wrapped(|ui| {
+            if ui.selectable_label(metric.is_none(), "None").clicked() {
+                metric = None;
+            }
+            for candidate in Metric::iter() {
+                if ui.selectable_label(metric == Some(candidate), candidate.name()).clicked() {
+                    metric = Some(candidate);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            for candidate in OverlayMode::iter() {
+                if ui.selectable_label(mode == candidate, candidate.name()).clicked() {
+                    mode = candidate;
+                }
+            }
+        });
+
+        if let (Some(shown), Some((min, max))) = (overlay.metric, overlay.range) {
+            ui.separator();
+            match overlay.mode {
+                OverlayMode::Color => {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}", min));
+                        let (rect, _) = ui.allocate_exact_size(egui::vec2(160.0, 12.0), egui::Sense::hover());
+                        let step = rect.width() / LEGEND_STEPS as f32;
+                        for position in 0..LEGEND_STEPS {
+                            let left = rect.left() + step * position as f32;
+                            let slice = egui::Rect::from_min_max(
+                                egui::pos2(left, rect.top()),
+                                egui::pos2(left + step, rect.bottom()),
+                            );
+                            let fraction = position as f32 / (LEGEND_STEPS - 1) as f32;
+                            ui.painter().rect_filled(slice, 0.0, color32(gradient(fraction)));
+                        }
+                        ui.label(format!("{}", max));
+                    });
+                }
+                OverlayMode::Size => {
+                    ui.label(format!("Smallest: {}, largest: {}", min, max));
+                }
+            }
+            ui.label(format!("{} of the nodes that have it.", shown.name()));
+        }
+
+        ui.separator();
+        let mut rows: Vec<(NodeIndex, &NodeData)> = graph
+            .0
+            .node_indices()
+            .map(|index| (index, &graph.0[index]))
+            .filter(|(_, node)| Metric::iter().any(|metric| metric.value(node).is_some()))
+            .collect();
+        rows.sort_by(|(_, a), (_, b)| {
+            let ordering = match order.column {
+                Some(column) => {
+                    let (a, b) = (column.value(a), column.value(b));
+                    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+                }
+                None => a.label.cmp(&b.label),
+            };
+            if order.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        egui::ScrollArea::from_max_height(240.0).show(ui, |ui| {
+            egui::Grid::new("metrics table").striped(true).show(ui, |ui| {
+                if ui.button("Name").clicked() {
+                    sort_by = Some(None);
+                }
+                for column in Metric::iter() {
+                    if ui.button(column.property()).on_hover_text(column.name()).clicked() {
+                        sort_by = Some(Some(column));
+                    }
+                }
+                ui.end_row();
+
+                for (index, node) in rows {
+                    if ui.selectable_label(selection.nodes.contains(&index), &node.label).clicked() {
+                        clicked = Some(index);
+                    }
+                    for column in Metric::iter() {
+                        ui.label(column.value(node).map_or(String::new(), |value| value.to_string()));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    });
+
+    // Only written when something changed, so that the nodes aren't drawn again every frame.
+    if metric != overlay.metric || mode != overlay.mode {
+        overlay.metric = metric;
+        overlay.mode = mode;
+    }
+    if let Some(column) = sort_by {
+        // Sorting by the same column again turns the order around. Metrics start out with the highest values on top.
+        order.descending = if order.column == column { !order.descending } else { column.is_some() };
+        order.column = column;
+    }
+    if let Some(index) = clicked {
+        selection.select(index, false);
+    }
+}
@@ -0,0 +1,212 @@
+//! The minimap: an overview of the whole canvas in a corner window, with a dot per node and a rectangle for what the view currently shows. Clicking or dragging in it moves the view there.
+//!
+//! The dots are kept up to date from the nodes that are drawn and undrawn rather than by going through the whole graph each frame. Nodes that are close enough to land on the same pixel share a dot, which keeps the painting cheap on large graphs.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::camera::{self, CanvasView};
+use crate::theme::Theme;
+use crate::{Bounds, Node};
+
+/// Size of the map inside the window, in egui points.
+const MINIMAP_SIZE: egui::Vec2 = egui::Vec2 { x: 200.0, y: 150.0 };
+
+/// Room left around the nodes, as a fraction of the canvas size.
+const MINIMAP_MARGIN: f32 = 0.05;
+
+/// The dots are snapped to a grid this many cells across, so that nearby nodes share one.
+const DOT_RESOLUTION: f32 = 100.0;
+
+#[derive(Default)]
+struct Minimap {
+    /// Where each drawn node is on the canvas.
+    nodes: HashMap<Entity, Vec2>,
+    /// The smallest rectangle around every node, as (min, max) corners.
+    extent: Option<(Vec2, Vec2)>,
+    /// The part of the canvas the map covers: the extent with some room around it.
+    area: Option<(Vec2, Vec2)>,
+    /// The dots to paint, in cells of `DOT_RESOLUTION` across the area, with how many nodes share each one.
+    dots: HashMap<(u16, u16), usize>,
+}
+
+impl Minimap {
+    fn cell(&self, point: Vec2) -> Option<(u16, u16)> {
+        let (min, max) = self.area?;
+        let cell = (point - min) / (max - min) * DOT_RESOLUTION;
+        Some((cell.x as u16, cell.y as u16))
+    }
+
+    fn add_dot(&mut self, point: Vec2) {
+        if let Some(cell) = self.cell(point) {
+            *self.dots.entry(cell).or_insert(0) += 1;
+        }
+    }
+
+    fn remove_dot(&mut self, point: Vec2) {
+        if let Some(cell) = self.cell(point) {
+            if let Some(count) = self.dots.get_mut(&cell) {
+                *count -= 1;
+                if *count == 0 {
+                    self.dots.remove(&cell);
+                }
+            }
+        }
+    }
+
+    fn covers(&self, point: Vec2) -> bool {
+        self.extent
+            .map_or(false, |(min, max)| point.cmpge(min).all() && point.cmple(max).all())
+    }
+
+    /// Whether `point` is on the edge of the extent, so that the extent may shrink without it.
+    fn on_boundary(&self, point: Vec2) -> bool {
+        self.extent.map_or(false, |(min, max)| {
+            point.x == min.x || point.y == min.y || point.x == max.x || point.y == max.y
+        })
+    }
+
+    /// The extent of every node, worked out from scratch.
+    fn fit(&self) -> Option<(Vec2, Vec2)> {
+        let mut points = self.nodes.values();
+        points
+            .next()
+            .map(|first| points.fold((*first, *first), |(min, max), point| (min.min(*point), max.max(*point))))
+    }
+
+    /// Works out the area from the extent, and every dot in it again.
+    fn redraw(&mut self) {
+        self.area = self.extent.map(|(min, max)| {
+            // A single node (or a row of them) still needs a map with some height and width.
+            let margin = ((max - min) * MINIMAP_MARGIN).max(Vec2::splat(50.0));
+            (min - margin, max + margin)
+        });
+        self.dots.clear();
+        let points: Vec<Vec2> = self.nodes.values().copied().collect();
+        for point in points {
+            self.add_dot(point);
+        }
+    }
+}
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // Nodes despawned during the update are only reported as removed until the end of the frame, same as for the canvas index.
+        app.insert_resource(Minimap::default())
+            .add_system_to_stage(CoreStage::PostUpdate, track_nodes.system())
+            .add_system(minimap.system());
+    }
+}
+
+/// Adds the nodes that were drawn since the last frame and drops the ones that were despawned. A node that moved is both: it is despawned and drawn again in its new place.
+///
+/// Only the dots of those nodes are touched, unless the area changes: when a node lands outside of it, or when a node on its edge goes away and the rest turn out to take up less room.
+fn track_nodes(
+    mut map: ResMut<Minimap>,
+    added: Query<(Entity, &Bounds), Added<Node>>,
+    removed: RemovedComponents<Node>,
+) {
+    let (mut refit, mut redraw) = (false, false);
+    for entity in removed.iter() {
+        if let Some(point) = map.nodes.remove(&entity) {
+            map.remove_dot(point);
+            refit |= map.on_boundary(point);
+        }
+    }
+    for (entity, bounds) in added.iter() {
+        let point = (bounds.min + bounds.max) / 2.0;
+        map.nodes.insert(entity, point);
+        if map.covers(point) {
+            map.add_dot(point);
+        } else {
+            map.extent = Some(match map.extent {
+                Some((min, max)) => (min.min(point), max.max(point)),
+                None => (point, point),
+            });
+            redraw = true;
+        }
+    }
+    if refit {
+        let extent = map.fit();
+        if extent != map.extent {
+            map.extent = extent;
+            redraw = true;
+        }
+    }
+    if redraw {
+        map.redraw();
+    }
+}
+
+fn color32(color: Color) -> egui::Color32 {
+    egui::Color32::from_rgba_unmultiplied(
+        (color.r() * 255.0).round() as u8,
+        (color.g() * 255.0).round() as u8,
+        (color.b() * 255.0).round() as u8,
+        (color.a() * 255.0).round() as u8,
+    )
+}
+
+fn minimap(
+    egui_context: ResMut<EguiContext>,
+    map: Res<Minimap>,
+    theme: Res<Theme>,
+    windows: Res<Windows>,
+    mut view: ResMut<CanvasView>,
+) {
+    let window_size = match windows.get_primary() {
+        Some(window) => camera::window_size(window),
+        None => return,
+    };
+
+    egui::Window::new("Minimap")
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .collapsible(true)
+        .resizable(false)
+        .show(egui_context.ctx(), |ui| {
+            let (response, painter) = ui.allocate_painter(MINIMAP_SIZE, egui::Sense::click_and_drag());
+            let rect = response.rect;
+            painter.rect_filled(rect, 0.0, color32(theme.background));
+
+            let (min, max) = match map.area {
+                Some(area) => area,
+                None => return,
+            };
+            // One scale for both axes, so that the map isn't stretched, with the canvas centered in the map.
+            let size = max - min;
+            let scale = (rect.width() / size.x).min(rect.height() / size.y);
+            let offset = egui::vec2(rect.width() - size.x * scale, rect.height() - size.y * scale) / 2.0;
+            // The canvas has y pointing up, egui has it pointing down.
+            let to_map = |point: Vec2| {
+                rect.left_top() + offset + egui::vec2((point.x - min.x) * scale, (max.y - point.y) * scale)
+            };
+            let to_canvas = |point: egui::Pos2| {
+                let local = point - rect.left_top() - offset;
+                Vec2::new(min.x + local.x / scale, max.y - local.y / scale)
+            };
+
+            let dot = color32(theme.node.stroke);
+            let cell = size / DOT_RESOLUTION;
+            for (x, y) in map.dots.keys() {
+                let center = to_map(min + Vec2::new(*x as f32 + 0.5, *y as f32 + 0.5) * cell);
+                painter.rect_filled(egui::Rect::from_center_size(center, egui::vec2(2.0, 2.0)), 0.0, dot);
+            }
+
+            let (view_min, view_max) = view.visible_rect(window_size);
+            let viewport = egui::Rect::from_two_pos(to_map(view_min), to_map(view_max));
+            painter.rect_stroke(viewport.intersect(rect), 0.0, (1.5, color32(theme.selection)));
+
+            if response.clicked() || response.dragged() {
+                if let Some(pointer) = response.interact_pointer_pos() {
+                    let target = to_canvas(pointer.clamp(rect.min, rect.max));
+                    if view.translation != target {
+                        view.translation = target;
+                    }
+                }
+            }
+        });
+}
@@ -0,0 +1,448 @@
+//! The typed graph model stored in the `Graph` resource. Every node and edge drawn on the canvas has a weight in here, and this is the structure that persistence and code generation read -- the bevy entities are only the visualization of it.
+
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::prelude::Entity;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex, StableGraph};
+use petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
+use strum_macros::{EnumIter, EnumString};
+use uuid::Uuid;
+
+pub type GraphModel = StableGraph<NodeData, EdgeData>;
+
+/// The property recording where an imported node came from, such as the file (and line) it was read from.
+pub const SOURCE_PROPERTY: &str = "source";
+
+/// What a node built from sources stands for, such as `fn app::main` or `type Selection`. Unlike its `id`, it comes out the same every time the view is built, which is what lets two versions of a graph be compared.
+pub const KEY_PROPERTY: &str = "key";
+
+/// Set on nodes that are left out of the build with the features and target picked in the Features window, which are drawn grayed out.
+pub const EXCLUDED_PROPERTY: &str = "excluded";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, EnumString, Serialize, Deserialize)]
+#[strum(ascii_case_insensitive)]
+pub enum NodeKind {
+    /// A plain node placed with `Tools::Node`.
+    Item,
+    /// A free-form container placed with `Tools::Group`.
+    Group,
+    Crate,
+    Module,
+    Struct,
+    Enum,
+    Trait,
+    Function,
+    /// A type that isn't defined in the sources a view was built from, such as `String`, or the generic type of a blanket impl.
+    Type,
+    /// A Bevy resource, as used by systems.
+    Resource,
+    /// A Bevy component, as queried by systems.
+    Component,
+    /// A Cargo feature.
+    Feature,
+    /// A crate depended on. Unlike `Crate` it isn't a container, since its contents aren't shown.
+    Dependency,
+    /// A `cfg` condition that isn't a feature, such as `target_arch = "wasm32"`.
+    Cfg,
+}
+
+impl NodeKind {
+    /// Container kinds visually enclose their children and can be collapsed to a single node.
+    pub fn is_container(&self) -> bool {
+        matches!(self, NodeKind::Group | NodeKind::Crate | NodeKind::Module)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, EnumString, Serialize, Deserialize)]
+#[strum(ascii_case_insensitive)]
+pub enum EdgeKind {
+    /// An edge drawn by hand with `Tools::Edge`.
+    Link,
+    /// From a type to a trait it implements.
+    Implements,
+    /// From a trait to one of its supertraits.
+    Supertrait,
+    /// From the generic type of a blanket impl (`impl<T: Bound> Trait for T`) to the trait.
+    BlanketImpl,
+    /// From a function to a function it calls.
+    Calls,
+    /// From a function to something it calls that couldn't be pinned down to a function in the sources.
+    UnresolvedCall,
+    /// From a type or function to a type it mentions in its fields, variants or signature.
+    UsesType,
+    /// From a Bevy system to a resource or component it reads.
+    Reads,
+    /// From a Bevy system to a resource or component it writes.
+    Writes,
+    /// From a system to the one run after it, in a snapshot of a running app.
+    RunsBefore,
+    /// From a Cargo feature to a feature or dependency it turns on, labelled with the feature of the dependency if there is one.
+    Enables,
+    /// From a feature or `cfg` condition to an item behind a `#[cfg(...)]` that depends on it.
+    Gates,
+}
+
+impl Default for EdgeKind {
+    fn default() -> Self {
+        EdgeKind::Link
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeData {
+    pub id: Uuid,
+    pub label: String,
+    pub kind: NodeKind,
+    pub position: Option<(f32, f32)>,
+    /// The container this node is nested in. Nesting is stored here rather than as edges so that a node can only ever have a single parent. Only `set_parent` changes it, so that `children` stays in step.
+    parent: Option<NodeIndex>,
+    /// The nodes whose `parent` this is, kept in step by `set_parent` and `remove_node` so that finding them doesn't take a pass over the whole graph. Not saved, `rebuild_children` puts it back after loading.
+    #[serde(skip)]
+    children: Vec<NodeIndex>,
+    /// Only meaningful for container kinds. A collapsed container hides all of its descendants and stands in for them on the canvas.
+    pub collapsed: bool,
+    /// Anything known about the node that doesn't have a field of its own, such as attributes of an imported diagram.
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+    /// The entity currently drawing this node, if any.
+    #[serde(skip)]
+    pub bevy_identity: Option<Entity>,
+}
+
+impl NodeData {
+    pub fn new(label: impl Into<String>, kind: NodeKind) -> Self {
+        NodeData {
+            id: Uuid::new_v4(),
+            label: label.into(),
+            kind,
+            position: None,
+            parent: None,
+            children: Vec::new(),
+            collapsed: false,
+            properties: BTreeMap::new(),
+            bevy_identity: None,
+        }
+    }
+
+    pub fn at(mut self, x: f32, y: f32) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+
+    /// The container this node is nested in, if any.
+    pub fn parent(&self) -> Option<NodeIndex> {
+        self.parent
+    }
+
+    /// A copy of this node to add to another graph, without its nesting or its drawing in this one.
+    pub fn detached(&self) -> NodeData {
+        NodeData {
+            parent: None,
+            children: Vec::new(),
+            bevy_identity: None,
+            ..self.clone()
+        }
+    }
+
+    pub fn is_excluded(&self) -> bool {
+        self.properties.contains_key(EXCLUDED_PROPERTY)
+    }
+
+    pub fn set_excluded(&mut self, excluded: bool) {
+        if excluded {
+            self.properties.insert(EXCLUDED_PROPERTY.to_string(), String::from("true"));
+        } else {
+            self.properties.remove(EXCLUDED_PROPERTY);
+        }
+    }
+}
+
+/// How an edge gets from one waypoint (its endpoints and bend points) to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Serialize, Deserialize)]
+pub enum Routing {
+    Straight,
+    /// Horizontal and vertical segments only, going around the nodes in the way.
+    Orthogonal,
+}
+
+impl Default for Routing {
+    fn default() -> Self {
+        Routing::Straight
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeData {
+    pub id: Uuid,
+    pub label: String,
+    pub kind: EdgeKind,
+    /// Points the user has added for the edge to pass through, in order from source to target.
+    #[serde(default)]
+    pub bends: Vec<(f32, f32)>,
+    #[serde(default)]
+    pub routing: Routing,
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+    /// The entity currently drawing this edge, if any.
+    #[serde(skip)]
+    pub bevy_identity: Option<Entity>,
+}
+
+impl EdgeData {
+    pub fn new(kind: EdgeKind) -> Self {
+        EdgeData {
+            id: Uuid::new_v4(),
+            label: String::new(),
+            kind,
+            bends: Vec::new(),
+            routing: Routing::Straight,
+            properties: BTreeMap::new(),
+            bevy_identity: None,
+        }
+    }
+}
+
+/// A stand-in for one or more edges whose endpoints are hidden inside collapsed containers. `count` is the number of model edges it represents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregatedEdge {
+    pub source: NodeIndex,
+    pub target: NodeIndex,
+    pub count: usize,
+}
+
+/// The nodes nested directly inside `index`, in index order.
+pub fn children(graph: &GraphModel, index: NodeIndex) -> Vec<NodeIndex> {
+    let node = match graph.node_weight(index) {
+        Some(node) => node,
+        None => return Vec::new(),
+    };
+    // Only the ones that still say so, in case a node was taken out of the graph without going through `remove_node`.
+    let mut children: Vec<NodeIndex> = node
+        .children
+        .iter()
+        .copied()
+        .filter(|child| graph.node_weight(*child).map_or(false, |child| child.parent() == Some(index)))
+        .collect();
+    children.sort();
+    children
+}
+
+/// The chain of containers above `index`, innermost first.
+pub fn ancestors(graph: &GraphModel, index: NodeIndex) -> Vec<NodeIndex> {
+    let mut ancestors = Vec::new();
+    let mut current = graph.node_weight(index).and_then(|node| node.parent());
+    while let Some(parent) = current {
+        if ancestors.contains(&parent) {
+            // A cycle can only come from a hand-edited file, but we'd rather not spin forever on one.
+            break;
+        }
+        ancestors.push(parent);
+        current = graph.node_weight(parent).and_then(|node| node.parent());
+    }
+    ancestors
+}
+
+pub fn depth(graph: &GraphModel, index: NodeIndex) -> usize {
+    ancestors(graph, index).len()
+}
+
+/// The node that is actually drawn in place of `index`: the outermost collapsed container above it, or the node itself if none of its ancestors are collapsed.
+pub fn visible_representative(graph: &GraphModel, index: NodeIndex) -> NodeIndex {
+    visible_representative_with(graph, index, |ancestor| graph[ancestor].collapsed)
+}
+
+/// Like `visible_representative`, but with `collapsed` deciding which containers count as collapsed, for views that collapse more than the model does (such as zooming out).
+pub fn visible_representative_with(graph: &GraphModel, index: NodeIndex, collapsed: impl Fn(NodeIndex) -> bool) -> NodeIndex {
+    ancestors(graph, index)
+        .into_iter()
+        .rev()
+        .find(|ancestor| collapsed(*ancestor))
+        .unwrap_or(index)
+}
+
+pub fn is_hidden(graph: &GraphModel, index: NodeIndex) -> bool {
+    visible_representative(graph, index) != index
+}
+
+/// Every edge between `a` and `b`, in either direction, in index order.
+pub fn edges_between(graph: &GraphModel, a: NodeIndex, b: NodeIndex) -> Vec<EdgeIndex> {
+    let mut edges: Vec<EdgeIndex> = graph.edges_connecting(a, b).map(|edge| edge.id()).collect();
+    // A self-loop would otherwise be found twice.
+    if a != b {
+        edges.extend(graph.edges_connecting(b, a).map(|edge| edge.id()));
+    }
+    edges.sort();
+    edges
+}
+
+/// Nests `child` inside `parent` (or moves it back to the top level for `None`). Returns false, leaving the graph untouched, when the parent is not a container or when the move would make a container its own ancestor.
+pub fn set_parent(graph: &mut GraphModel, child: NodeIndex, parent: Option<NodeIndex>) -> bool {
+    if let Some(parent) = parent {
+        if parent == child
+            || !graph[parent].kind.is_container()
+            || ancestors(graph, parent).contains(&child)
+        {
+            return false;
+        }
+    }
+    attach(graph, child, parent);
+    true
+}
+
+/// Moves `child` into `parent` without any checks, keeping the children of both containers in step.
+fn attach(graph: &mut GraphModel, child: NodeIndex, parent: Option<NodeIndex>) {
+    if let Some(previous) = graph[child].parent.and_then(|previous| graph.node_weight_mut(previous)) {
+        previous.children.retain(|other| *other != child);
+    }
+    graph[child].parent = parent;
+    if let Some(parent) = parent {
+        graph[parent].children.push(child);
+    }
+}
+
+/// Fills in the children of every container from the nodes' `parent`, for a graph that was just deserialized (the children aren't saved along with it).
+pub fn rebuild_children(graph: &mut GraphModel) {
+    let indices: Vec<NodeIndex> = graph.node_indices().collect();
+    for index in &indices {
+        graph[*index].children.clear();
+    }
+    for child in indices {
+        if let Some(parent) = graph[child].parent.and_then(|parent| graph.node_weight_mut(parent)) {
+            parent.children.push(child);
+        }
+    }
+}
+
+/// Removes a node, handing its children up to its own parent so that removing a container never silently drops what was inside it.
+pub fn remove_node(graph: &mut GraphModel, index: NodeIndex) -> Option<NodeData> {
+    let parent = graph.node_weight(index)?.parent();
+    let orphans = children(graph, index);
+    graph[index].children.clear();
+    for child in orphans {
+        attach(graph, child, parent);
+    }
+    attach(graph, index, None);
+    graph.remove_node(index)
+}
+
+/// Every edge that touches a hidden node is rerouted to the visible representatives of its endpoints. Edges that end up inside a single collapsed container disappear, and edges that end up between the same pair of representatives are merged.
+pub fn aggregated_edges(graph: &GraphModel) -> Vec<AggregatedEdge> {
+    aggregated_edges_with(graph, |container| graph[container].collapsed, |_| true)
+}
+
+/// Like `aggregated_edges`, with `collapsed` deciding which containers count as collapsed and only the edges of the kinds `counted` accepts taken into account.
+pub fn aggregated_edges_with(
+    graph: &GraphModel,
+    collapsed: impl Fn(NodeIndex) -> bool,
+    counted: impl Fn(EdgeKind) -> bool,
+) -> Vec<AggregatedEdge> {
+    let mut counts: BTreeMap<(NodeIndex, NodeIndex), usize> = BTreeMap::new();
+
+    for edge in graph.edge_references().filter(|edge| counted(edge.weight().kind)) {
+        let source = visible_representative_with(graph, edge.source(), &collapsed);
+        let target = visible_representative_with(graph, edge.target(), &collapsed);
+
+        if source == target || (source == edge.source() && target == edge.target()) {
+            continue;
+        }
+        *counts.entry((source, target)).or_insert(0) += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|((source, target), count)| AggregatedEdge {
+            source,
+            target,
+            count,
+        })
+        .collect()
+}
+
+/// Adds everything in `from` to `into`, keeping the nesting and edges of `from` intact. Returns where each node of `from` ended up.
+pub fn merge(into: &mut GraphModel, from: &GraphModel) -> HashMap<NodeIndex, NodeIndex> {
+    let mut mapping = HashMap::new();
+
+    for index in from.node_indices() {
+        mapping.insert(index, into.add_node(from[index].detached()));
+    }
+
+    for index in from.node_indices() {
+        if let Some(parent) = from[index].parent().and_then(|parent| mapping.get(&parent)) {
+            attach(into, mapping[&index], Some(*parent));
+        }
+    }
+
+    for edge in from.edge_references() {
+        let mut weight = edge.weight().clone();
+        weight.bevy_identity = None;
+        into.add_edge(mapping[&edge.source()], mapping[&edge.target()], weight);
+    }
+
+    mapping
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn children_follow_nesting() {
+        let mut graph = GraphModel::default();
+        let outer = graph.add_node(NodeData::new("outer", NodeKind::Group));
+        let inner = graph.add_node(NodeData::new("inner", NodeKind::Group));
+        let item = graph.add_node(NodeData::new("item", NodeKind::Item));
+        assert!(set_parent(&mut graph, inner, Some(outer)));
+        assert!(set_parent(&mut graph, item, Some(inner)));
+        assert!(!set_parent(&mut graph, outer, Some(inner)));
+        assert_eq!(children(&graph, outer), vec![inner]);
+        assert_eq!(children(&graph, inner), vec![item]);
+
+        assert!(set_parent(&mut graph, item, Some(outer)));
+        assert_eq!(children(&graph, outer), vec![inner, item]);
+        assert!(children(&graph, inner).is_empty());
+
+        remove_node(&mut graph, outer);
+        assert_eq!(graph[inner].parent(), None);
+        assert_eq!(graph[item].parent(), None);
+
+        // A node added where a removed one was doesn't inherit its children.
+        let reused = graph.add_node(NodeData::new("reused", NodeKind::Group));
+        assert!(children(&graph, reused).is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_nesting() {
+        let mut from = GraphModel::default();
+        let group = from.add_node(NodeData::new("group", NodeKind::Group));
+        let item = from.add_node(NodeData::new("item", NodeKind::Item));
+        set_parent(&mut from, item, Some(group));
+
+        let mut into = GraphModel::default();
+        into.add_node(NodeData::new("already there", NodeKind::Item));
+        let mapping = merge(&mut into, &from);
+        assert_eq!(children(&into, mapping[&group]), vec![mapping[&item]]);
+    }
+    #[test]
+    fn children_are_rebuilt_after_loading() {
+        let mut graph = GraphModel::default();
+        let group = graph.add_node(NodeData::new("group", NodeKind::Group));
+        let item = graph.add_node(NodeData::new("item", NodeKind::Item));
+        let other = graph.add_node(NodeData::new("other", NodeKind::Item));
+        set_parent(&mut graph, item, Some(group));
+        set_parent(&mut graph, other, Some(group));
+
+        let saved = ron::to_string(&graph).unwrap();
+        let mut loaded: GraphModel = ron::from_str(&saved).unwrap();
+        assert_eq!(loaded[item].parent(), Some(group));
+        assert!(children(&loaded, group).is_empty());
+
+        rebuild_children(&mut loaded);
+        assert_eq!(children(&loaded, group), vec![item, other]);
+        assert!(children(&loaded, item).is_empty());
+
+        // Rebuilding again doesn't count anyone twice.
+        rebuild_children(&mut loaded);
+        assert_eq!(children(&loaded, group), vec![item, other]);
+    }
+}
@@ -0,0 +1,181 @@
+//! The Patterns window: a palette of architecture patterns that can be dragged onto the canvas, and saving the current selection as a pattern of its own. The patterns themselves live in the library (`pattern`).
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::camera::CanvasView;
+use crate::grid::GridSettings;
+use crate::pattern::{self, Pattern};
+use crate::selection::Selection;
+use crate::theme::Theme;
+use crate::{adjust_cursor_position, Graph};
+
+/// Patterns saved from selections are kept here, in the working directory, next to `themes.ron`.
+#[cfg(not(target_arch = "wasm32"))]
+const USER_PATTERNS: &str = "patterns.ron";
+
+/// Every pattern in the palette, the built-in ones first.
+struct Patterns {
+    patterns: Vec<Pattern>,
+    built_in: usize,
+}
+
+#[derive(Default)]
+struct PaletteState {
+    /// The pattern whose parameters are shown.
+    chosen: Option<usize>,
+    /// The pattern being dragged out of the palette.
+    dragging: Option<usize>,
+    /// Values for the parameters, by name. Patterns with a parameter of the same name share its value.
+    arguments: HashMap<String, String>,
+    new_name: String,
+    status: String,
+}
+
+pub struct PalettePlugin;
+
+impl Plugin for PalettePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(read_patterns())
+            .insert_resource(PaletteState::default())
+            .add_system(palette.system())
+            .add_system(drop_pattern.system());
+    }
+}
+
+fn read_patterns() -> Patterns {
+    let mut patterns = match pattern::load_patterns(pattern::BUILT_IN_PATTERNS) {
+        Ok(patterns) => patterns,
+        Err(error) => {
+            error!("The built-in patterns are broken: {}", error);
+            Vec::new()
+        }
+    };
+    let built_in = patterns.len();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(source) = std::fs::read_to_string(USER_PATTERNS) {
+        match pattern::load_patterns(&source) {
+            Ok(saved) => patterns.extend(saved),
+            Err(error) => warn!("Couldn't read {}: {}", USER_PATTERNS, error),
+        }
+    }
+
+    Patterns { patterns, built_in }
+}
+
+fn palette(
+    egui_context: ResMut<EguiContext>,
+    graph: Res<Graph>,
+    selection: Res<Selection>,
+    mut patterns: ResMut<Patterns>,
+    mut state: ResMut<PaletteState>,
+) {
+    let mut saved: Option<Pattern> = None;
+
+    egui::Window::new("Patterns").show(egui_context.ctx(), |ui| {
+        ui.label("Drag a pattern onto the canvas to place it.");
+        for (position, pattern) in patterns.patterns.iter().enumerate() {
+            let response = ui
+                .selectable_label(state.chosen == Some(position), &pattern.name)
+                .interact(egui::Sense::drag())
+                .on_hover_text(&pattern.description);
+            if response.clicked() {
+                state.chosen = if state.chosen == Some(position) { None } else { Some(position) };
+            }
+            if response.drag_started() {
+                state.dragging = Some(position);
+            }
+
+            if state.chosen == Some(position) {
+                ui.indent(position, |ui| {
+                    for parameter in &pattern.parameters {
+                        ui.horizontal(|ui| {
+                            ui.label(parameter);
+                            let value = state.arguments.entry(parameter.clone()).or_insert_with(String::new);
+                            ui.text_edit_singleline(value);
+                        });
+                    }
+                    if !pattern.ports.is_empty() {
+                        let ports: Vec<&str> = pattern.ports.iter().map(|port| port.name.as_str()).collect();
+                        ui.label(format!("Ports: {}", ports.join(", ")));
+                    }
+                });
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut state.new_name);
+            let enabled = !selection.nodes.is_empty() && !state.new_name.trim().is_empty();
+            if ui.add(egui::Button::new("Save selection").enabled(enabled)).clicked() {
+                let nodes: Vec<_> = selection.nodes.iter().copied().collect();
+                saved = Some(Pattern::from_selection(&graph.0, &nodes, state.new_name.trim()));
+            }
+        });
+        if !state.status.is_empty() {
+            ui.label(state.status.clone());
+        }
+    });
+
+    if let Some(dragging) = state.dragging {
+        egui::show_tooltip(egui_context.ctx(), egui::Id::new("pattern drag"), |ui| {
+            ui.label(&patterns.patterns[dragging].name);
+        });
+    }
+
+    if let Some(pattern) = saved {
+        state.status = format!("Saved {} with {} nodes.", pattern.name, pattern.nodes.len());
+        state.new_name.clear();
+        patterns.patterns.push(pattern);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let built_in = patterns.built_in;
+            match pattern::save_patterns(&patterns.patterns[built_in..]) {
+                Ok(text) => {
+                    if let Err(error) = std::fs::write(USER_PATTERNS, text) {
+                        state.status = format!("Couldn't write {}: {}", USER_PATTERNS, error);
+                    }
+                }
+                Err(error) => state.status = format!("Couldn't save the patterns: {}", error),
+            }
+        }
+    }
+}
+
+/// Places the pattern being dragged where it is let go of, unless that is over a window, and selects what it placed.
+fn drop_pattern(
+    buttons: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    view: Res<CanvasView>,
+    grid: Res<GridSettings>,
+    theme: Res<Theme>,
+    egui_context: ResMut<EguiContext>,
+    patterns: Res<Patterns>,
+    mut state: ResMut<PaletteState>,
+    mut selection: ResMut<Selection>,
+    mut graph: ResMut<Graph>,
+) {
+    let dragging = match state.dragging {
+        Some(dragging) if buttons.just_released(MouseButton::Left) => dragging,
+        _ => return,
+    };
+    state.dragging = None;
+    if egui_context.ctx().is_pointer_over_area() {
+        return;
+    }
+    let cursor = match adjust_cursor_position(&windows, &view, None) {
+        Some((x, y)) => Vec2::new(x, y),
+        None => return,
+    };
+
+    let pattern = &patterns.patterns[dragging];
+    let placed = pattern.place(&mut graph.0, &state.arguments, grid.place(cursor), theme.node_size);
+
+    selection.clear();
+    selection.nodes.extend(placed);
+    state.status = format!("Placed {}.", pattern.name);
+}
@@ -0,0 +1,345 @@
+//! Architecture patterns (Repository, Builder, Observer, ...) as small parameterized subgraphs. Placing a pattern expands it into ordinary nodes and edges of the graph model, so nothing downstream has to know that they came from one.
+
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::math::Vec2;
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
+
+use crate::layout;
+use crate::model::{self, EdgeData, EdgeKind, GraphModel, NodeData, NodeKind};
+
+pub const BUILT_IN_PATTERNS: &str = include_str!("../assets/patterns.ron");
+
+/// The property naming the pattern a node was placed from.
+pub const PATTERN_PROPERTY: &str = "pattern";
+
+/// The property naming the port a node is, for the nodes of a pattern that are meant to be connected to the rest of the diagram.
+pub const PORT_PROPERTY: &str = "port";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pattern {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Names that are filled in when the pattern is placed: `{Entity}` in a label is replaced by the value given for `Entity`.
+    #[serde(default)]
+    pub parameters: Vec<String>,
+    pub nodes: Vec<PatternNode>,
+    #[serde(default)]
+    pub edges: Vec<PatternEdge>,
+    #[serde(default)]
+    pub ports: Vec<Port>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternNode {
+    pub label: String,
+    pub kind: NodeKind,
+    /// Where the node goes relative to where the pattern is placed. Containers take their place from their children.
+    #[serde(default)]
+    pub at: Option<(f32, f32)>,
+    /// The container node (by its position in `nodes`) this node is nested in.
+    #[serde(default)]
+    pub parent: Option<usize>,
+}
+
+/// An edge between two nodes of the pattern, by their position in `nodes`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternEdge {
+    pub from: usize,
+    pub to: usize,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub kind: EdgeKind,
+}
+
+/// A node of the pattern meant to be connected to the rest of the diagram.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Port {
+    pub name: String,
+    pub node: usize,
+}
+
+impl Pattern {
+    /// `label` with every `{Parameter}` replaced by its argument. Parameters without an argument keep their own name.
+    pub fn fill(&self, label: &str, arguments: &HashMap<String, String>) -> String {
+        self.parameters.iter().fold(label.to_string(), |label, parameter| {
+            let value = arguments
+                .get(parameter)
+                .filter(|value| !value.is_empty())
+                .unwrap_or(parameter);
+            label.replace(&format!("{{{}}}", parameter), value)
+        })
+    }
+
+    /// The pattern as a graph of its own, placed around the origin.
+    pub fn build(&self, arguments: &HashMap<String, String>) -> GraphModel {
+        let mut graph = GraphModel::default();
+        let ports: HashMap<usize, &str> = self.ports.iter().map(|port| (port.node, port.name.as_str())).collect();
+
+        let indices: Vec<NodeIndex> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(position, node)| {
+                let mut data = NodeData::new(self.fill(&node.label, arguments), node.kind);
+                data.position = node.at;
+                data.properties.insert(PATTERN_PROPERTY.to_string(), self.name.clone());
+                if let Some(port) = ports.get(&position) {
+                    data.properties.insert(PORT_PROPERTY.to_string(), port.to_string());
+                }
+                graph.add_node(data)
+            })
+            .collect();
+
+        for (position, node) in self.nodes.iter().enumerate() {
+            if let Some(parent) = node.parent.and_then(|parent| indices.get(parent)) {
+                model::set_parent(&mut graph, indices[position], Some(*parent));
+            }
+        }
+
+        for edge in &self.edges {
+            if let (Some(from), Some(to)) = (indices.get(edge.from), indices.get(edge.to)) {
+                let mut data = EdgeData::new(edge.kind);
+                data.label = self.fill(&edge.label, arguments);
+                graph.add_edge(*from, *to, data);
+            }
+        }
+
+        graph
+    }
+
+    /// Expands the pattern into `graph` around `at`, returning the nodes it added. Nodes the pattern has no place for (such as an empty container saved with it) are laid out next to the others first.
+    pub fn place(&self, graph: &mut GraphModel, arguments: &HashMap<String, String>, at: Vec2, node_size: f32) -> Vec<NodeIndex> {
+        let mut pattern = self.build(arguments);
+        layout::layout_unpositioned(&mut pattern, node_size);
+        for node in pattern.node_weights_mut() {
+            if let Some((x, y)) = node.position {
+                node.position = Some((x + at.x, y + at.y));
+            }
+        }
+        model::merge(graph, &pattern).values().copied().collect()
+    }
+
+    /// A new pattern from part of a graph, with the nodes placed relative to their middle. Whatever is inside of a selected container comes along with it, and the nodes with edges to anything outside of the pattern become its ports.
+    pub fn from_selection(graph: &GraphModel, nodes: &[NodeIndex], name: &str) -> Pattern {
+        let selected: Vec<NodeIndex> = graph
+            .node_indices()
+            .filter(|index| {
+                nodes.contains(index) || model::ancestors(graph, *index).iter().any(|ancestor| nodes.contains(ancestor))
+            })
+            .collect();
+        let order: BTreeMap<NodeIndex, usize> = selected
+            .into_iter()
+            .enumerate()
+            .map(|(position, index)| (index, position))
+            .collect();
+
+        let positions: Vec<Vec2> = order
+            .keys()
+            .filter_map(|index| graph[*index].position)
+            .map(|(x, y)| Vec2::new(x, y))
+            .collect();
+        let middle = if positions.is_empty() {
+            Vec2::ZERO
+        } else {
+            positions.iter().fold(Vec2::ZERO, |sum, position| sum + *position) / positions.len() as f32
+        };
+
+        let pattern_nodes = order
+            .keys()
+            .map(|index| {
+                let node = &graph[*index];
+                PatternNode {
+                    label: node.label.clone(),
+                    kind: node.kind,
+                    at: node.position.map(|(x, y)| (x - middle.x, y - middle.y)),
+                    parent: node.parent().and_then(|parent| order.get(&parent).copied()),
+                }
+            })
+            .collect();
+
+        let mut edges = Vec::new();
+        let mut ports = Vec::new();
+        for (index, position) in &order {
+            let mut outside = false;
+            for edge in graph.edges(*index) {
+                match order.get(&edge.target()) {
+                    Some(target) => edges.push(PatternEdge {
+                        from: *position,
+                        to: *target,
+                        label: edge.weight().label.clone(),
+                        kind: edge.weight().kind,
+                    }),
+                    None => outside = true,
+                }
+            }
+            outside |= graph
+                .edges_directed(*index, petgraph::Direction::Incoming)
+                .any(|edge| !order.contains_key(&edge.source()));
+            if outside {
+                ports.push(Port {
+                    name: graph[*index].label.clone(),
+                    node: *position,
+                });
+            }
+        }
+
+        Pattern {
+            name: name.to_string(),
+            description: format!("Saved from a selection of {} nodes.", order.len()),
+            parameters: Vec::new(),
+            nodes: pattern_nodes,
+            edges,
+            ports,
+        }
+    }
+}
+
+/// Parses a list of patterns in the format of `assets/patterns.ron`.
+pub fn load_patterns(source: &str) -> Result<Vec<Pattern>, ron::Error> {
+    ron::from_str(source)
+}
+
+/// Writes patterns out in the format `load_patterns` reads.
+pub fn save_patterns(patterns: &[Pattern]) -> Result<String, ron::Error> {
+    ron::ser::to_string_pretty(&patterns, ron::ser::PrettyConfig::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn built_in(name: &str) -> Pattern {
+        load_patterns(BUILT_IN_PATTERNS)
+            .unwrap()
+            .into_iter()
+            .find(|pattern| pattern.name == name)
+            .unwrap()
+    }
+
+    fn arguments(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn find(graph: &GraphModel, label: &str) -> NodeIndex {
+        graph.node_indices().find(|index| graph[*index].label == label).unwrap()
+    }
+
+    #[test]
+    fn built_in_patterns_are_well_formed() {
+        let patterns = load_patterns(BUILT_IN_PATTERNS).unwrap();
+        let names: Vec<&str> = patterns.iter().map(|pattern| pattern.name.as_str()).collect();
+        for name in &["Repository", "Builder", "Observer", "Newtype", "Actor", "Bevy Plugin"] {
+            assert!(names.contains(name), "{} is built in", name);
+        }
+
+        for pattern in &patterns {
+            let count = pattern.nodes.len();
+            for node in &pattern.nodes {
+                if let Some(parent) = node.parent {
+                    assert!(pattern.nodes[parent].kind.is_container(), "{}: {} is in a container", pattern.name, node.label);
+                }
+            }
+            assert!(pattern.edges.iter().all(|edge| edge.from < count && edge.to < count), "{}: edges", pattern.name);
+            assert!(pattern.ports.iter().all(|port| port.node < count), "{}: ports", pattern.name);
+
+            // Every placeholder in a label is one of the parameters.
+            let filled = arguments(&pattern.parameters.iter().map(|parameter| (parameter.as_str(), "X")).collect::<Vec<_>>());
+            let graph = pattern.build(&filled);
+            assert!(graph.node_weights().all(|node| !node.label.contains('{')), "{}: labels", pattern.name);
+            assert!(graph.edge_weights().all(|edge| !edge.label.contains('{')), "{}: edge labels", pattern.name);
+        }
+    }
+
+    #[test]
+    fn fill_parameters() {
+        let pattern = built_in("Repository");
+        assert_eq!(pattern.fill("{Entity}Repository", &arguments(&[("Entity", "User")])), "UserRepository");
+        // Parameters without a value keep their name, and anything that isn't a parameter is left alone.
+        assert_eq!(pattern.fill("{Entity}Repository", &arguments(&[("Entity", "")])), "EntityRepository");
+        assert_eq!(pattern.fill("{Entity} {Other}", &HashMap::new()), "Entity {Other}");
+    }
+
+    #[test]
+    fn build_nests_and_marks_ports() {
+        let graph = built_in("Bevy Plugin").build(&arguments(&[("Name", "Physics")]));
+        assert_eq!(graph.node_count(), 5);
+        let module = find(&graph, "Physics");
+        let plugin = find(&graph, "PhysicsPlugin");
+        assert_eq!(graph[module].kind, NodeKind::Module);
+        assert_eq!(graph[module].position, None);
+        assert_eq!(graph[plugin].position, Some((0.0, 80.0)));
+        assert_eq!(
+            model::children(&graph, module),
+            vec![plugin, find(&graph, "setup"), find(&graph, "update")]
+        );
+        assert_eq!(graph[find(&graph, "Plugin")].parent(), None);
+
+        assert!(graph
+            .node_weights()
+            .all(|node| node.properties.get(PATTERN_PROPERTY).map(String::as_str) == Some("Bevy Plugin")));
+        let ports: Vec<&str> = graph
+            .node_weights()
+            .filter_map(|node| node.properties.get(PORT_PROPERTY).map(String::as_str))
+            .collect();
+        assert_eq!(ports, vec!["plugin"]);
+        assert_eq!(graph.edges_connecting(plugin, find(&graph, "Plugin")).count(), 1);
+    }
+
+    #[test]
+    fn place_around_the_drop_point() {
+        let mut graph = GraphModel::default();
+        let existing = graph.add_node(NodeData::new("existing", NodeKind::Item).at(-500.0, -500.0));
+
+        let placed = built_in("Repository").place(&mut graph, &arguments(&[("Entity", "User")]), Vec2::new(100.0, 50.0), 40.0);
+        assert_eq!(placed.len(), 3);
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph[existing].position, Some((-500.0, -500.0)));
+        assert_eq!(graph[find(&graph, "UserRepository")].position, Some((100.0, 130.0)));
+        assert_eq!(graph[find(&graph, "User")].position, Some((-100.0, 130.0)));
+        assert_eq!(graph[find(&graph, "InMemoryUserRepository")].position, Some((100.0, 10.0)));
+    }
+
+    #[test]
+    fn saved_selection_round_trip() {
+        let mut graph = GraphModel::default();
+        let module = graph.add_node(NodeData::new("storage", NodeKind::Module));
+        let store = graph.add_node(NodeData::new("Store", NodeKind::Trait).at(0.0, 0.0));
+        let file = graph.add_node(NodeData::new("FileStore", NodeKind::Struct).at(100.0, 0.0));
+        let outside = graph.add_node(NodeData::new("App", NodeKind::Struct).at(0.0, 300.0));
+        model::set_parent(&mut graph, store, Some(module));
+        model::set_parent(&mut graph, file, Some(module));
+        let mut implements = EdgeData::new(EdgeKind::Implements);
+        implements.label = String::from("implements");
+        graph.add_edge(file, store, implements);
+        graph.add_edge(outside, store, EdgeData::new(EdgeKind::UsesType));
+
+        // Selecting the module brings along what is in it.
+        let pattern = Pattern::from_selection(&graph, &[module], "Storage");
+        assert_eq!(pattern.nodes.len(), 3);
+        assert_eq!(pattern.edges.len(), 1);
+        assert_eq!(pattern.ports.len(), 1);
+        assert_eq!(pattern.ports[0].name, "Store");
+
+        let saved = save_patterns(&[pattern]).unwrap();
+        let loaded = load_patterns(&saved).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(save_patterns(&loaded).unwrap(), saved);
+
+        let mut placed = GraphModel::default();
+        loaded[0].place(&mut placed, &HashMap::new(), Vec2::new(1000.0, 1000.0), 40.0);
+        assert_eq!(placed.node_count(), 3);
+        let (module, store, file) = (find(&placed, "storage"), find(&placed, "Store"), find(&placed, "FileStore"));
+        assert_eq!(model::children(&placed, module), vec![store, file]);
+        assert_eq!(placed[store].position, Some((950.0, 1000.0)));
+        assert_eq!(placed[file].position, Some((1050.0, 1000.0)));
+        let edge = placed.edges_connecting(file, store).next().unwrap();
+        assert_eq!((edge.weight().kind, edge.weight().label.as_str()), (EdgeKind::Implements, "implements"));
+        assert_eq!(placed[store].properties.get(PORT_PROPERTY).map(String::as_str), Some("Store"));
+        assert_eq!(placed[file].properties.get(PATTERN_PROPERTY).map(String::as_str), Some("Storage"));
+    }
+}
@@ -0,0 +1,396 @@
+//! Views of a Rust code base, built from its source files. The sources are parsed with `syn`, and each view (`SourceView`) turns what it is interested in into an ordinary graph model that can be merged into the canvas like an imported diagram. Every node that comes from an item in the sources records where it was found in `model::SOURCE_PROPERTY`, along with its metrics (see `metrics`).
+
+use std::collections::HashMap;
+use std::fmt;
+
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use quote::ToTokens;
+use strum_macros::EnumIter;
+use syn::spanned::Spanned;
+
+use crate::model::{self, EdgeData, EdgeKind, GraphModel, NodeData, NodeKind};
+use crate::{call_graph, ecs_view, metrics, trait_view, type_usage};
+
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceError {
+    pub path: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.path, self.line, self.message)
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+/// A source file parsed into its syntax tree, along with the crate and the module path the file stands for.
+pub struct ParsedFile {
+    pub path: String,
+    pub krate: String,
+    pub module: Vec<String>,
+    pub syntax: syn::File,
+}
+
+impl ParsedFile {
+    /// `path:line` for something in this file, as recorded in `model::SOURCE_PROPERTY`.
+    pub fn location(&self, spanned: &impl Spanned) -> String {
+        format!("{}:{}", self.path, spanned.span().start().line)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum SourceView {
+    /// Traits, the types implementing them, supertraits and blanket impls.
+    Traits,
+    /// Functions and methods, and the calls between them.
+    Calls,
+    /// Structs, enums and functions, and the types they mention.
+    Types,
+    /// Bevy systems, and the resources and components they read and write.
+    Ecs,
+}
+
+/// How a view is built, beyond which view it is.
+#[derive(Debug, Clone)]
+pub struct ViewOptions {
+    /// Narrows the view down to a single item (what that means depends on the view) when it isn't empty.
+    pub focus: String,
+    /// Whether the type usage view looks through wrappers such as `Option` and `Vec`, and into generic arguments.
+    pub unwrap: bool,
+    /// Whether the call graph shows calls to methods that the crate doesn't define, which are mostly into the standard library.
+    pub external_methods: bool,
+}
+
+impl Default for ViewOptions {
+    fn default() -> Self {
+        ViewOptions {
+            focus: String::new(),
+            unwrap: true,
+            external_methods: true,
+        }
+    }
+}
+
+impl SourceView {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SourceView::Traits => "Trait implementations",
+            SourceView::Calls => "Call graph",
+            SourceView::Types => "Type usage",
+            SourceView::Ecs => "Bevy ECS",
+        }
+    }
+
+    pub fn build(&self, files: &[SourceFile], options: &ViewOptions) -> Result<GraphModel, SourceError> {
+        let parsed = parse(files)?;
+        let focus = Some(options.focus.trim()).filter(|focus| !focus.is_empty());
+        let mut graph = match self {
+            SourceView::Traits => trait_view::build(&parsed, focus),
+            SourceView::Calls => call_graph::build(&parsed, focus, options.external_methods),
+            SourceView::Types => type_usage::build(&parsed, focus, options.unwrap),
+            SourceView::Ecs => ecs_view::build(&parsed, focus),
+        };
+        metrics::annotate(&mut graph, &parsed);
+        Ok(graph)
+    }
+}
+
+pub fn parse(files: &[SourceFile]) -> Result<Vec<ParsedFile>, SourceError> {
+    files
+        .iter()
+        .map(|file| {
+            let syntax = syn::parse_file(&file.text).map_err(|error| SourceError {
+                path: file.path.clone(),
+                line: error.span().start().line,
+                message: error.to_string(),
+            })?;
+            Ok(ParsedFile {
+                path: file.path.clone(),
+                krate: crate_name(&file.path),
+                module: module_path(&file.path),
+                syntax,
+            })
+        })
+        .collect()
+}
+
+/// Parses files given as `(path, text)`, for the tests of the views.
+#[cfg(test)]
+pub fn parse_sources(files: &[(&str, &str)]) -> Vec<ParsedFile> {
+    let files: Vec<SourceFile> = files
+        .iter()
+        .map(|(path, text)| SourceFile {
+            path: path.to_string(),
+            text: text.to_string(),
+        })
+        .collect();
+    parse(&files).unwrap()
+}
+
+/// The crate a file belongs to: the directory its `src` directory is in, or `crate` when that can't be told.
+pub fn crate_name(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.rfind("src/")
+        .and_then(|start| path[..start].trim_end_matches('/').rsplit('/').next())
+        .filter(|name| !name.is_empty() && *name != ".")
+        .unwrap_or("crate")
+        .to_string()
+}
+
+/// The module a file stands for, going by the usual layout of a crate: `src/lib.rs` and `src/main.rs` are the crate root, `src/a/b.rs` and `src/a/b/mod.rs` are `a::b`.
+pub fn module_path(path: &str) -> Vec<String> {
+    let path = path.replace('\\', "/");
+    let relative = match path.rfind("src/") {
+        Some(start) => &path[start + "src/".len()..],
+        None => path.rsplit('/').next().unwrap_or(&path),
+    };
+    let mut module: Vec<String> = relative
+        .trim_end_matches(".rs")
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .map(str::to_string)
+        .collect();
+    if matches!(module.last().map(String::as_str), Some("mod") | Some("lib") | Some("main")) {
+        module.pop();
+    }
+    module
+}
+
+/// Calls `visit` with every item in `file`, including the ones inside of inline modules, along with the module path it is in.
+pub fn for_each_item<'a>(file: &'a ParsedFile, mut visit: impl FnMut(&[String], &'a syn::Item)) {
+    fn walk<'a>(module: &mut Vec<String>, items: &'a [syn::Item], visit: &mut impl FnMut(&[String], &'a syn::Item)) {
+        for item in items {
+            visit(module, item);
+            if let syn::Item::Mod(syn::ItemMod {
+                ident,
+                content: Some((_, items)),
+                ..
+            }) = item
+            {
+                module.push(ident.to_string());
+                walk(module, items, visit);
+                module.pop();
+            }
+        }
+    }
+    walk(&mut file.module.clone(), &file.syntax.items, &mut visit);
+}
+
+/// The name a path is known by in the views: its last segment, without generic arguments.
+pub fn path_name(path: &syn::Path) -> Option<String> {
+    path.segments.last().map(|segment| segment.ident.to_string())
+}
+
+/// The name of a type as written, with references and generic arguments left off where that leaves a plain path.
+pub fn type_name(ty: &syn::Type) -> String {
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => {
+            path_name(&path.path).unwrap_or_else(|| ty.to_token_stream().to_string())
+        }
+        syn::Type::Reference(reference) => type_name(&reference.elem),
+        syn::Type::Paren(paren) => type_name(&paren.elem),
+        syn::Type::Group(group) => type_name(&group.elem),
+        _ => ty.to_token_stream().to_string(),
+    }
+}
+
+/// Where a type or trait is defined in the sources, and what it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDefinition {
+    pub krate: String,
+    pub module: Vec<String>,
+    pub kind: NodeKind,
+}
+
+impl TypeDefinition {
+    /// `krate::module::name`.
+    fn path(&self, name: &str) -> String {
+        let path: Vec<&str> = std::iter::once(self.krate.as_str())
+            .chain(self.module.iter().map(String::as_str))
+            .chain(std::iter::once(name))
+            .collect();
+        path.join("::")
+    }
+}
+
+/// The structs, unions, enums, type aliases and traits defined in a set of files, by name. Their nodes are keyed by where they are defined (`type app::shapes::Circle`, `trait app::Shape`), as functions are, so that two modules can each have one of the same name.
+#[derive(Default)]
+pub struct DefinedTypes {
+    by_name: HashMap<String, Vec<TypeDefinition>>,
+}
+
+impl DefinedTypes {
+    pub fn new(files: &[ParsedFile]) -> Self {
+        let mut types = DefinedTypes::default();
+        for file in files {
+            for_each_item(file, |module, item| {
+                let (ident, kind) = match item {
+                    syn::Item::Struct(item) => (&item.ident, NodeKind::Struct),
+                    syn::Item::Union(item) => (&item.ident, NodeKind::Struct),
+                    syn::Item::Enum(item) => (&item.ident, NodeKind::Enum),
+                    syn::Item::Type(item) => (&item.ident, NodeKind::Type),
+                    syn::Item::Trait(item) => (&item.ident, NodeKind::Trait),
+                    _ => return,
+                };
+                types.by_name.entry(ident.to_string()).or_insert_with(Vec::new).push(TypeDefinition {
+                    krate: file.krate.clone(),
+                    module: module.to_vec(),
+                    kind,
+                });
+            });
+        }
+        types
+    }
+
+    /// The definition `name` mentioned in `module` of `krate` stands for, among the traits or else among the types: the one in that module, or else the only one in that crate, or else the only one there is.
+    fn find(&self, krate: &str, module: &[String], name: &str, traits: bool) -> Option<&TypeDefinition> {
+        fn only<'a>(mut candidates: impl Iterator<Item = &'a TypeDefinition>) -> Option<&'a TypeDefinition> {
+            let first = candidates.next();
+            if candidates.next().is_none() {
+                first
+            } else {
+                None
+            }
+        }
+
+        let definitions: Vec<&TypeDefinition> = self
+            .by_name
+            .get(name)?
+            .iter()
+            .filter(|definition| (definition.kind == NodeKind::Trait) == traits)
+            .collect();
+        definitions
+            .iter()
+            .copied()
+            .find(|definition| definition.krate == krate && definition.module == module)
+            .or_else(|| only(definitions.iter().copied().filter(|definition| definition.krate == krate)))
+            .or_else(|| only(definitions.iter().copied()))
+    }
+
+    /// The definition the type `name` mentioned in `module` of `krate` stands for.
+    pub fn resolve(&self, krate: &str, module: &[String], name: &str) -> Option<&TypeDefinition> {
+        self.find(krate, module, name, false)
+    }
+
+    /// The key of the node for the type `name` mentioned in `module` of `krate`. Types that aren't defined in the sources (or can't be told apart) are keyed by their name alone.
+    pub fn key(&self, krate: &str, module: &[String], name: &str) -> String {
+        let path = self.resolve(krate, module, name).map(|definition| definition.path(name));
+        format!("type {}", path.as_deref().unwrap_or(name))
+    }
+
+    /// The same as `key`, for the trait `name`.
+    pub fn trait_key(&self, krate: &str, module: &[String], name: &str) -> String {
+        let path = self.find(krate, module, name, true).map(|definition| definition.path(name));
+        format!("trait {}", path.as_deref().unwrap_or(name))
+    }
+}
+
+/// Builds a view's graph, making sure that every item only gets a single node however often it is come across.
+#[derive(Default)]
+pub struct ViewBuilder {
+    pub graph: GraphModel,
+    nodes: HashMap<String, NodeIndex>,
+}
+
+impl ViewBuilder {
+    /// The node for `key`, added with `label` and `kind` the first time it is asked for. The key is kept in `model::KEY_PROPERTY`.
+    pub fn node(&mut self, key: &str, label: &str, kind: NodeKind) -> NodeIndex {
+        if let Some(index) = self.nodes.get(key) {
+            return *index;
+        }
+        let mut node = NodeData::new(label, kind);
+        node.properties.insert(model::KEY_PROPERTY.to_string(), key.to_string());
+        let index = self.graph.add_node(node);
+        self.nodes.insert(key.to_string(), index);
+        index
+    }
+
+    pub fn get(&self, key: &str) -> Option<NodeIndex> {
+        self.nodes.get(key).copied()
+    }
+
+    /// The container for `module` in `krate`, nested in the containers for the modules it is in. The crate root is a `Crate` node, everything inside of it `Module` nodes.
+    pub fn module(&mut self, krate: &str, module: &[String]) -> NodeIndex {
+        let mut parent = self.node(&format!("crate {}", krate), krate, NodeKind::Crate);
+        for depth in 1..=module.len() {
+            let key = format!("module {}::{}", krate, module[..depth].join("::"));
+            let index = self.node(&key, &module[depth - 1], NodeKind::Module);
+            model::set_parent(&mut self.graph, index, Some(parent));
+            parent = index;
+        }
+        parent
+    }
+
+    /// Records where the node at `index` was found, unless it already knows.
+    pub fn locate(&mut self, index: NodeIndex, location: String) {
+        self.graph[index]
+            .properties
+            .entry(model::SOURCE_PROPERTY.to_string())
+            .or_insert(location);
+    }
+
+    /// An edge from `from` to `to`, unless there already is one of the same kind and label.
+    pub fn edge(&mut self, from: NodeIndex, to: NodeIndex, kind: EdgeKind, label: &str) {
+        let exists = self
+            .graph
+            .edges(from)
+            .any(|edge| edge.target() == to && edge.weight().kind == kind && edge.weight().label == label);
+        if !exists {
+            let mut data = EdgeData::new(kind);
+            data.label = label.to_string();
+            self.graph.add_edge(from, to, data);
+        }
+    }
+}
+
+/// Every `.rs` file under `root` (or `root` itself if it is a file), in path order, leaving out build output and hidden directories.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_sources(root: &std::path::Path) -> std::io::Result<Vec<SourceFile>> {
+    fn visit(path: &std::path::Path, files: &mut Vec<SourceFile>) -> std::io::Result<()> {
+        if path.is_dir() {
+            let mut entries: Vec<std::path::PathBuf> = std::fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .collect();
+            entries.sort();
+            for entry in entries {
+                let name = entry.file_name().and_then(|name| name.to_str()).unwrap_or("");
+                if name == "target" || name.starts_with('.') {
+                    continue;
+                }
+                visit(&entry, files)?;
+            }
+        } else if path.extension().map_or(false, |extension| extension == "rs") {
+            files.push(SourceFile {
+                path: path.to_string_lossy().into_owned(),
+                text: std::fs::read_to_string(path)?,
+            });
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    visit(root, &mut files)?;
+    Ok(files)
+}
+
+/// Like `read_sources`, with the paths relative to `root`, so that two checkouts of a project (at different revisions, say) read into the same crates and modules wherever they are.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_checkout(root: &std::path::Path) -> std::io::Result<Vec<SourceFile>> {
+    let mut files = read_sources(root)?;
+    for file in &mut files {
+        if let Ok(relative) = std::path::Path::new(&file.path).strip_prefix(root) {
+            file.path = relative.to_string_lossy().into_owned();
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,612 @@
+//! What is selected, how that is shown on the canvas, the inspector window for it, deleting it, dragging nodes around with the selector tool, and selecting everything inside a rubber band dragged out over empty space.
+
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_prototype_lyon::prelude::*;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+
+use crate::camera::CanvasView;
+use crate::culling::CanvasIndex;
+use crate::edges::EDGE_TOLERANCE;
+use crate::grid::GridSettings;
+use crate::groups::GroupFrame;
+use crate::labels::{LabelEditor, NodeLabel};
+use crate::metrics_overlay::MetricOverlay;
+use crate::model::{self, GraphModel};
+use crate::theme::Theme;
+use crate::{
+    adjust_cursor_position, clicked_entity, entity_under_cursor, forget_drawing, forget_edge_drawing, node_colors,
+    Bounds, Edge, Graph, GraphInteractionHistory, Interaction, Node, Placed, Route, ToolHistory, Tools,
+};
+
+#[derive(Debug, Default)]
+pub struct Selection {
+    pub nodes: BTreeSet<NodeIndex>,
+    pub edges: BTreeSet<EdgeIndex>,
+}
+
+impl Selection {
+    /// Selects `index`, either on its own or (with `extend`, i.e. shift-clicking) in addition to what is already selected. Shift-clicking a selected node deselects it.
+    pub fn select(&mut self, index: NodeIndex, extend: bool) {
+        if !extend {
+            self.clear();
+        } else if self.nodes.remove(&index) {
+            return;
+        }
+        self.nodes.insert(index);
+    }
+
+    /// The same as `select`, for an edge.
+    pub fn select_edge(&mut self, index: EdgeIndex, extend: bool) {
+        if !extend {
+            self.clear();
+        } else if self.edges.remove(&index) {
+            return;
+        }
+        self.edges.insert(index);
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.edges.clear();
+    }
+
+    pub fn contains(&self, index: NodeIndex) -> bool {
+        self.nodes.contains(&index)
+    }
+
+    pub fn contains_edge(&self, index: EdgeIndex) -> bool {
+        self.edges.contains(&index)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.edges.is_empty()
+    }
+}
+
+/// The node being dragged with the selector, where the cursor grabbed it relative to its center, and where the cursor and the node were when it was grabbed. The node only starts moving once the cursor has gone further than a click would.
+#[derive(Default)]
+struct Drag {
+    node: Option<NodeIndex>,
+    offset: Vec2,
+    start: Vec2,
+    from: (f32, f32),
+    moving: bool,
+}
+
+/// The rubber band being dragged out with the selector: where it started, and the rectangle drawn for it.
+#[derive(Default)]
+struct RubberBand {
+    start: Option<Vec2>,
+    shape: Option<Entity>,
+}
+
+/// The rubber band is drawn over everything but the cursor.
+const RUBBER_BAND_Z: f32 = 5.0;
+
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Selection::default())
+            .add_system(highlight_selection.system())
+            .add_system(drag_nodes.system())
+            .add_system(rubber_band.system())
+            .add_system(delete_selected.system())
+            .add_system(inspector.system());
+    }
+}
+
+/// Outlines selected nodes, and colors selected edges, in the theme's selection color. Everything else gets back the colors it is drawn with.
+fn highlight_selection(
+    selection: Res<Selection>,
+    theme: Res<Theme>,
+    overlay: Res<MetricOverlay>,
+    graph: Res<Graph>,
+    added: Query<(), Or<(Added<Node>, Added<Edge>)>>,
+    mut shapes: Query<(Option<&Node>, Option<&Edge>, &mut ShapeColors), Or<(With<Node>, With<Edge>)>>,
+) {
+    // Nodes and edges get redrawn (and lose their highlight) when they move or the theme changes, so new ones need styling too.
+    if !selection.is_changed() && !theme.is_changed() && added.iter().next().is_none() {
+        return;
+    }
+
+    for (node, edge, mut colors) in shapes.iter_mut() {
+        if let Some(index) = node.and_then(|node| node.identity) {
+            if let Some(weight) = graph.0.node_weight(index) {
+                let outline = if selection.contains(index) {
+                    theme.selection
+                } else {
+                    node_colors(&theme, &overlay, weight).1
+                };
+                if colors.outline != outline {
+                    colors.outline = outline;
+                }
+            }
+        }
+        if let Some(index) = edge.and_then(|edge| edge.graph_identity) {
+            if let Some(weight) = graph.0.edge_weight(index) {
+                let color = if selection.contains_edge(index) {
+                    theme.selection
+                } else {
+                    theme.edge_color(weight)
+                };
+                if colors.main != color {
+                    colors.main = color;
+                }
+            }
+        }
+    }
+}
+
+fn drag_nodes(
+    mut commands: Commands,
+    buttons: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    view: Res<CanvasView>,
+    egui_context: ResMut<EguiContext>,
+    tool_history: Res<ToolHistory>,
+    grid: Res<GridSettings>,
+    canvas_index: Res<CanvasIndex>,
+    placed: Query<(&Placed, &Bounds, &Visible)>,
+    nodes: Query<&Node>,
+    mut transforms: QuerySet<(Query<&mut Transform, With<Node>>, Query<(&NodeLabel, &mut Transform)>)>,
+    mut graph: ResMut<Graph>,
+    mut graph_interaction_history: ResMut<GraphInteractionHistory>,
+    mut drag: Local<Drag>,
+) {
+    if tool_history.current_tool != Tools::Selector {
+        let_go(&mut transforms, &graph.0, &mut drag);
+        return;
+    }
+    let cursor = match adjust_cursor_position(&windows, &view, None) {
+        Some((x, y)) => Vec2::new(x, y),
+        None => return,
+    };
+
+    if buttons.just_pressed(MouseButton::Left) && !egui_context.ctx().is_pointer_over_area() {
+        if let Some((entity, Tools::Node)) = entity_under_cursor((cursor.x, cursor.y), &canvas_index, &placed) {
+            let index = nodes.get(entity).ok().and_then(|node| node.identity);
+            if let Some(index) = index {
+                if let Some((x, y)) = graph.0[index].position {
+                    *drag = Drag {
+                        node: Some(index),
+                        offset: Vec2::new(x, y) - cursor,
+                        start: cursor,
+                        from: (x, y),
+                        moving: false,
+                    };
+                }
+            }
+        }
+    }
+
+    let index = match drag.node {
+        Some(index) => index,
+        None => return,
+    };
+    if !buttons.pressed(MouseButton::Left) && !buttons.just_released(MouseButton::Left) {
+        let_go(&mut transforms, &graph.0, &mut drag);
+        return;
+    }
+    let released = buttons.just_released(MouseButton::Left);
+
+    // A click, even with snapping on, leaves the node where it is.
+    if !drag.moving && (cursor - drag.start).length() < EDGE_TOLERANCE * view.scale {
+        if released {
+            drag.node = None;
+        }
+        return;
+    }
+    drag.moving = true;
+    let target = grid.place(cursor + drag.offset);
+
+    if !released {
+        // Until it is let go, only the node and its label follow the cursor, so that nothing else is drawn again on the way.
+        follow(&mut transforms, &graph.0, index, target);
+        return;
+    }
+
+    drag.node = None;
+    let to = (target.x, target.y);
+    // Let go where it started, the node is already back in its place.
+    if drag.from == to || graph.0.node_weight(index).map_or(true, |node| node.position.is_none()) {
+        return;
+    }
+    graph.0[index].position = Some(to);
+    forget_drawing(&mut commands, &mut graph.0, index);
+    graph_interaction_history.0.push(Interaction::Moved(index, drag.from, to));
+}
+
+/// Moves the entities drawing `index` and its label to `to`, leaving the graph as it is.
+fn follow(
+    transforms: &mut QuerySet<(Query<&mut Transform, With<Node>>, Query<(&NodeLabel, &mut Transform)>)>,
+    graph: &GraphModel,
+    index: NodeIndex,
+    to: Vec2,
+) {
+    if let Some(entity) = graph.node_weight(index).and_then(|node| node.bevy_identity) {
+        if let Ok(mut transform) = transforms.q0_mut().get_mut(entity) {
+            transform.translation.x = to.x;
+            transform.translation.y = to.y;
+        }
+    }
+    for (label, mut transform) in transforms.q1_mut().iter_mut() {
+        if label.node == index {
+            transform.translation.x = to.x;
+            transform.translation.y = to.y;
+        }
+    }
+}
+
+/// Stops dragging without moving the node, putting it back where it was if it was already following the cursor.
+fn let_go(
+    transforms: &mut QuerySet<(Query<&mut Transform, With<Node>>, Query<(&NodeLabel, &mut Transform)>)>,
+    graph: &GraphModel,
+    drag: &mut Drag,
+) {
+    if let (Some(index), true) = (drag.node, drag.moving) {
+        follow(transforms, graph, index, Vec2::new(drag.from.0, drag.from.1));
+    }
+    drag.node = None;
+}
+
+/// Pressing the selector on empty space and dragging draws a rubber band, and letting go selects every node and container that lies entirely inside of it. Shift keeps what was already selected.
+fn rubber_band(
+    mut commands: Commands,
+    buttons: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    view: Res<CanvasView>,
+    egui_context: ResMut<EguiContext>,
+    tool_history: Res<ToolHistory>,
+    theme: Res<Theme>,
+    canvas_index: Res<CanvasIndex>,
+    placed: Query<(&Placed, &Bounds, &Visible)>,
+    routes: Query<(&Route, &Visible)>,
+    nodes: Query<&Node>,
+    frames: Query<&GroupFrame>,
+    mut selection: ResMut<Selection>,
+    mut band: Local<RubberBand>,
+) {
+    if let Some(shape) = band.shape.take() {
+        commands.entity(shape).despawn();
+    }
+    if tool_history.current_tool != Tools::Selector {
+        band.start = None;
+        return;
+    }
+    let cursor = match adjust_cursor_position(&windows, &view, None) {
+        Some((x, y)) => Vec2::new(x, y),
+        None => return,
+    };
+
+    if buttons.just_pressed(MouseButton::Left) && !egui_context.ctx().is_pointer_over_area() {
+        let tolerance = EDGE_TOLERANCE * view.scale;
+        if clicked_entity((cursor.x, cursor.y), tolerance, &canvas_index, &placed, &routes).is_none() {
+            band.start = Some(cursor);
+        }
+    }
+
+    let start = match band.start {
+        Some(start) => start,
+        None => return,
+    };
+    let min = start.min(cursor);
+    let max = start.max(cursor);
+
+    if buttons.pressed(MouseButton::Left) {
+        let size = max - min;
+        let rectangle = shapes::Rectangle {
+            width: size.x,
+            height: size.y,
+            origin: shapes::RectangleOrigin::BottomLeft,
+        };
+        let mut fill = theme.selection;
+        fill.set_a(0.1);
+        band.shape = Some(
+            commands
+                .spawn_bundle(GeometryBuilder::build_as(
+                    &rectangle,
+                    ShapeColors::outlined(fill, theme.selection),
+                    DrawMode::Outlined {
+                        fill_options: FillOptions::default(),
+                        outline_options: StrokeOptions::default().with_line_width(view.scale),
+                    },
+                    Transform::from_xyz(min.x, min.y, RUBBER_BAND_Z),
+                ))
+                .id(),
+        );
+        return;
+    }
+
+    band.start = None;
+    // A click without a drag only clears the selection, which the click itself already took care of.
+    if (max - min).length() < EDGE_TOLERANCE * view.scale {
+        return;
+    }
+    for entity in canvas_index.0.in_rect(min, max) {
+        let inside = match placed.get(entity) {
+            Ok((_, bounds, visible)) => {
+                visible.is_visible && bounds.min.cmpge(min).all() && bounds.max.cmple(max).all()
+            }
+            Err(_) => false,
+        };
+        if !inside {
+            continue;
+        }
+        let index = nodes
+            .get(entity)
+            .ok()
+            .and_then(|node| node.identity)
+            .or_else(|| frames.get(entity).ok().map(|frame| frame.group));
+        if let Some(index) = index {
+            selection.nodes.insert(index);
+        }
+    }
+}
+
+/// The Delete key removes everything that is selected, unless a label is being edited or egui has the keyboard.
+fn delete_selected(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    egui_context: ResMut<EguiContext>,
+    editor: Res<LabelEditor>,
+    mut selection: ResMut<Selection>,
+    mut graph: ResMut<Graph>,
+    mut graph_interaction_history: ResMut<GraphInteractionHistory>,
+) {
+    if !keys.just_pressed(KeyCode::Delete)
+        || selection.is_empty()
+        || editor.editing.is_some()
+        || egui_context.ctx().wants_keyboard_input()
+    {
+        return;
+    }
+    remove_selected(&mut commands, &mut selection, &mut graph, &mut graph_interaction_history);
+}
+
+/// Removes the selected edges and nodes from the graph along with their drawings, recording each removal.
+fn remove_selected(
+    commands: &mut Commands,
+    selection: &mut Selection,
+    graph: &mut Graph,
+    graph_interaction_history: &mut GraphInteractionHistory,
+) {
+    for edge in std::mem::take(&mut selection.edges) {
+        let endpoints = graph.0.edge_endpoints(edge);
+        let entity = graph.0.edge_weight_mut(edge).and_then(|weight| weight.bevy_identity.take());
+        if let Some(entity) = entity {
+            commands.entity(entity).despawn();
+            graph_interaction_history.0.push(Interaction::RemovedEdge(entity));
+        }
+        graph.0.remove_edge(edge);
+
+        // Parallel edges that were curved apart to make room for this one can straighten out again.
+        if let Some((a, b)) = endpoints {
+            for sibling in model::edges_between(&graph.0, a, b) {
+                forget_edge_drawing(commands, &mut graph.0, sibling);
+            }
+        }
+    }
+
+    for index in std::mem::take(&mut selection.nodes) {
+        if !graph.0.contains_node(index) {
+            continue;
+        }
+        let entity = graph.0[index].bevy_identity;
+        forget_drawing(commands, &mut graph.0, index);
+        model::remove_node(&mut graph.0, index);
+        if let Some(entity) = entity {
+            graph_interaction_history.0.push(Interaction::RemovedNode(entity));
+        }
+    }
+}
+
+/// Shows what is selected: a single node or edge in detail (with its label editable), or a count when several things are selected.
+fn inspector(
+    mut commands: Commands,
+    egui_context: ResMut<EguiContext>,
+    mut selection: ResMut<Selection>,
+    mut graph: ResMut<Graph>,
+    mut graph_interaction_history: ResMut<GraphInteractionHistory>,
+    mut label: Local<Option<(Option<NodeIndex>, Option<EdgeIndex>, String)>>,
+) {
+    if selection.is_empty() {
+        *label = None;
+        return;
+    }
+
+    let single_node = match (selection.nodes.len(), selection.edges.len()) {
+        (1, 0) => selection.nodes.iter().next().copied().filter(|index| graph.0.contains_node(*index)),
+        _ => None,
+    };
+    let single_edge = match (selection.nodes.len(), selection.edges.len()) {
+        (0, 1) => selection.edges.iter().next().copied().filter(|index| graph.0.contains_edge(*index)),
+        _ => None,
+    };
+
+    // The label being typed in is kept across frames, and thrown away when the selection moves on.
+    let current_label = single_node
+        .map(|index| graph.0[index].label.clone())
+        .or_else(|| single_edge.map(|index| graph.0[index].label.clone()))
+        .unwrap_or_default();
+    if label.as_ref().map(|(node, edge, _)| (*node, *edge)) != Some((single_node, single_edge)) {
+        *label = Some((single_node, single_edge, current_label.clone()));
+    }
+
+    let mut rename = false;
+    let mut delete = false;
+    {
+        let model = &graph.0;
+        let buffer = &mut label.as_mut().unwrap().2;
+        egui::Window::new("Inspector").show(egui_context.ctx(), |ui| {
+            if let Some(index) = single_node {
+                let node = &model[index];
+                ui.label(format!("Node {} ({:?})", index.index(), node.kind));
+                ui.label(format!("id: {}", node.id));
+                if let Some((x, y)) = node.position {
+                    ui.label(format!("position: {:.0}, {:.0}", x, y));
+                }
+                if let Some(parent) = node.parent() {
+                    ui.label(format!("inside: {}", model[parent].label));
+                }
+                let degree = model.edges_directed(index, petgraph::Direction::Outgoing).count()
+                    + model.edges_directed(index, petgraph::Direction::Incoming).count();
+                ui.label(format!("edges: {}", degree));
+                for (key, value) in &node.properties {
+                    ui.label(format!("{}: {}", key, value));
+                }
+            } else if let Some(index) = single_edge {
+                let edge = &model[index];
+                ui.label(format!("Edge {} ({:?}, {:?} routing)", index.index(), edge.kind, edge.routing));
+                ui.label(format!("id: {}", edge.id));
+                if let Some((source, target)) = model.edge_endpoints(index) {
+                    ui.label(format!("{} → {}", model[source].label, model[target].label));
+                }
+                ui.label(format!("bend points: {}", edge.bends.len()));
+                for (key, value) in &edge.properties {
+                    ui.label(format!("{}: {}", key, value));
+                }
+            } else {
+                ui.label(format!(
+                    "{} nodes and {} edges selected",
+                    selection.nodes.len(),
+                    selection.edges.len()
+                ));
+            }
+
+            if single_node.is_some() || single_edge.is_some() {
+                ui.horizontal(|ui| {
+                    ui.label("label");
+                    ui.text_edit_singleline(buffer);
+                    if ui.button("Rename").clicked() {
+                        rename = true;
+                    }
+                });
+            }
+            ui.separator();
+            if ui.button("Delete (Del)").clicked() {
+                delete = true;
+            }
+        });
+    }
+
+    let typed = label.as_ref().map(|(_, _, buffer)| buffer.clone()).unwrap_or_default();
+    if rename && typed != current_label {
+        if let Some(index) = single_node {
+            let previous = std::mem::replace(&mut graph.0[index].label, typed.clone());
+            graph_interaction_history.0.push(Interaction::Relabeled(index, previous, typed));
+            // The node is as wide as its label.
+            forget_drawing(&mut commands, &mut graph.0, index);
+        } else if let Some(index) = single_edge {
+            let previous = std::mem::replace(&mut graph.0[index].label, typed.clone());
+            graph_interaction_history.0.push(Interaction::RelabeledEdge(index, previous, typed));
+        }
+    }
+    if delete {
+        remove_selected(&mut commands, &mut selection, &mut graph, &mut graph_interaction_history);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use visual_programming_environment::diff::{Change, CHANGE_PROPERTY};
+
+    use super::*;
+    use crate::model::{EdgeData, EdgeKind, NodeData, NodeKind};
+
+    #[test]
+    fn diffed_edges_keep_their_change_color() {
+        let theme = Theme::default();
+        let mut graph = model::GraphModel::default();
+        let a = graph.add_node(NodeData::new("a", NodeKind::Item));
+        let b = graph.add_node(NodeData::new("b", NodeKind::Item));
+        let mut added = EdgeData::new(EdgeKind::Calls);
+        added.properties.insert(CHANGE_PROPERTY.to_string(), Change::Added.name().to_string());
+        let added = graph.add_edge(a, b, added);
+        let plain = graph.add_edge(b, a, EdgeData::new(EdgeKind::Calls));
+        let added_color = theme.change_color(Change::Added);
+        let plain_color = theme.edge_style(EdgeKind::Calls).color;
+        assert_ne!(added_color, plain_color);
+
+        let mut world = World::default();
+        let drawn = |world: &mut World, edge: EdgeIndex, color: Color| {
+            world
+                .spawn()
+                .insert_bundle((
+                    Edge {
+                        graph_identity: Some(edge),
+                    },
+                    ShapeColors::new(color),
+                ))
+                .id()
+        };
+        let added_entity = drawn(&mut world, added, added_color);
+        let plain_entity = drawn(&mut world, plain, plain_color);
+        world.insert_resource(Selection::default());
+        world.insert_resource(MetricOverlay::default());
+        world.insert_resource(Graph(graph));
+        world.insert_resource(theme.clone());
+        let mut stage = SystemStage::single(highlight_selection.system());
+        let color = |world: &World, entity: Entity| world.get::<ShapeColors>(entity).unwrap().main;
+
+        stage.run(&mut world);
+        assert_eq!(color(&world, added_entity), added_color);
+        assert_eq!(color(&world, plain_entity), plain_color);
+
+        world.get_resource_mut::<Selection>().unwrap().select_edge(plain, false);
+        stage.run(&mut world);
+        assert_eq!(color(&world, added_entity), added_color);
+        assert_eq!(color(&world, plain_entity), theme.selection);
+
+        world.get_resource_mut::<Selection>().unwrap().select_edge(added, false);
+        stage.run(&mut world);
+        assert_eq!(color(&world, added_entity), theme.selection);
+        assert_eq!(color(&world, plain_entity), plain_color);
+
+        world.get_resource_mut::<Selection>().unwrap().clear();
+        stage.run(&mut world);
+        assert_eq!(color(&world, added_entity), added_color);
+    }
+
+    #[test]
+    fn excluded_nodes_stay_dimmed() {
+        let theme = Theme::default();
+        let mut graph = model::GraphModel::default();
+        let mut excluded = NodeData::new("excluded", NodeKind::Item);
+        excluded.set_excluded(true);
+        let excluded = graph.add_node(excluded);
+        let included = graph.add_node(NodeData::new("included", NodeKind::Item));
+        let stroke = theme.node_style(NodeKind::Item).stroke;
+        let dimmed = crate::theme::dimmed(stroke);
+        assert_ne!(dimmed, stroke);
+
+        let mut world = World::default();
+        let drawn = |world: &mut World, node: NodeIndex, outline: Color| {
+            world
+                .spawn()
+                .insert_bundle((Node { identity: Some(node) }, ShapeColors::outlined(Color::WHITE, outline)))
+                .id()
+        };
+        let excluded_entity = drawn(&mut world, excluded, dimmed);
+        let included_entity = drawn(&mut world, included, stroke);
+        world.insert_resource(Selection::default());
+        world.insert_resource(MetricOverlay::default());
+        world.insert_resource(Graph(graph));
+        world.insert_resource(theme.clone());
+        let mut stage = SystemStage::single(highlight_selection.system());
+        let outline = |world: &World, entity: Entity| world.get::<ShapeColors>(entity).unwrap().outline;
+
+        world.get_resource_mut::<Selection>().unwrap().select(excluded, false);
+        stage.run(&mut world);
+        assert_eq!(outline(&world, excluded_entity), theme.selection);
+        assert_eq!(outline(&world, included_entity), stroke);
+
+        world.get_resource_mut::<Selection>().unwrap().select(included, false);
+        stage.run(&mut world);
+        assert_eq!(outline(&world, excluded_entity), dimmed);
+        assert_eq!(outline(&world, included_entity), theme.selection);
+    }
+}
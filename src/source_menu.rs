@@ -0,0 +1,112 @@
+//! The egui Rust source window. Views of a code base (see `rust_source::SourceView`) are built from a crate's sources, loaded natively from a directory or pasted in as a single file, and added to the current graph like an imported diagram.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use strum::IntoEnumIterator;
+
+use crate::layout;
+use crate::model;
+use crate::rust_source::{SourceFile, SourceView, ViewOptions};
+use crate::theme::Theme;
+use crate::Graph;
+
+struct SourceState {
+    view: SourceView,
+    options: ViewOptions,
+    path: String,
+    pasted: String,
+    /// The outcome of the last build, shown underneath the buttons.
+    status: String,
+}
+
+impl Default for SourceState {
+    fn default() -> Self {
+        SourceState {
+            view: SourceView::Traits,
+            options: ViewOptions::default(),
+            path: String::from("src"),
+            pasted: String::new(),
+            status: String::new(),
+        }
+    }
+}
+
+pub struct SourceMenuPlugin;
+
+impl Plugin for SourceMenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(SourceState::default())
+            .add_system(source_menu.system());
+    }
+}
+
+fn source_menu(
+    egui_context: ResMut<EguiContext>,
+    mut graph: ResMut<Graph>,
+    theme: Res<Theme>,
+    mut state: ResMut<SourceState>,
+) {
+    let mut files: Option<Vec<SourceFile>> = None;
+
+    egui::Window::new("Rust source").show(egui_context.ctx(), |ui| {
+        ui.horizontal(|ui| {
+            for view in SourceView::iter() {
+                if ui.selectable_label(state.view == view, view.name()).clicked() {
+                    state.view = view;
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Only");
+            ui.text_edit_singleline(&mut state.options.focus);
+        });
+        if state.view == SourceView::Types {
+            ui.checkbox(&mut state.options.unwrap, "Look through wrappers and generic arguments");
+        }
+        if state.view == SourceView::Calls {
+            ui.checkbox(&mut state.options.external_methods, "Show calls to methods defined elsewhere");
+        }
+        ui.separator();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut state.path);
+            if ui.button("Read sources").clicked() {
+                match crate::rust_source::read_sources(std::path::Path::new(&state.path)) {
+                    Ok(read) => files = Some(read),
+                    Err(error) => state.status = format!("Couldn't read {}: {}", state.path, error),
+                }
+            }
+        });
+
+        ui.label("Or paste a source file:");
+        ui.add(egui::TextEdit::multiline(&mut state.pasted).desired_rows(6));
+        if ui.button("Build from pasted source").clicked() {
+            files = Some(vec![SourceFile {
+                path: String::from("src/lib.rs"),
+                text: state.pasted.clone(),
+            }]);
+        }
+
+        if !state.status.is_empty() {
+            ui.label(state.status.clone());
+        }
+    });
+
+    if let Some(files) = files {
+        match state.view.build(&files, &state.options) {
+            Ok(view) => {
+                // Laying out after merging keeps the new nodes clear of whatever is already on the canvas.
+                model::merge(&mut graph.0, &view);
+                layout::layout_unpositioned(&mut graph.0, theme.node_size);
+                state.status = format!(
+                    "Read {} files into {} nodes and {} edges.",
+                    files.len(),
+                    view.node_count(),
+                    view.edge_count()
+                );
+            }
+            Err(error) => state.status = format!("Couldn't build the view: {}", error),
+        }
+    }
+}
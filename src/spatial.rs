@@ -0,0 +1,136 @@
+//! A uniform grid over the canvas, for finding what is at a point or inside a rectangle without going through everything that is on the canvas.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use bevy::math::Vec2;
+
+/// Width and height of a grid cell, in canvas units. A few nodes wide, so that most nodes only cover one or two cells.
+pub const DEFAULT_CELL_SIZE: f32 = 100.0;
+
+#[derive(Debug, Clone)]
+pub struct SpatialIndex<T> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<T>>,
+    bounds: HashMap<T, (Vec2, Vec2)>,
+}
+
+impl<T: Copy + Eq + Hash> Default for SpatialIndex<T> {
+    fn default() -> Self {
+        SpatialIndex::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+fn overlaps(a: (Vec2, Vec2), b: (Vec2, Vec2)) -> bool {
+    a.0.x <= b.1.x && a.1.x >= b.0.x && a.0.y <= b.1.y && a.1.y >= b.0.y
+}
+
+impl<T: Copy + Eq + Hash> SpatialIndex<T> {
+    pub fn new(cell_size: f32) -> Self {
+        SpatialIndex {
+            cell_size,
+            cells: HashMap::new(),
+            bounds: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, point: Vec2) -> (i32, i32) {
+        (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    fn cells_covering(&self, min: Vec2, max: Vec2) -> Vec<(i32, i32)> {
+        let (x0, y0) = self.cell(min);
+        let (x1, y1) = self.cell(max);
+        (x0..=x1).flat_map(|x| (y0..=y1).map(move |y| (x, y))).collect()
+    }
+
+    fn cell_count(&self, min: Vec2, max: Vec2) -> usize {
+        let (x0, y0) = self.cell(min);
+        let (x1, y1) = self.cell(max);
+        ((x1 - x0 + 1).max(0) as usize) * ((y1 - y0 + 1).max(0) as usize)
+    }
+
+    /// Adds `item` with the rectangle `min`..`max`, replacing wherever it was before.
+    pub fn insert(&mut self, item: T, min: Vec2, max: Vec2) {
+        self.remove(item);
+        for cell in self.cells_covering(min, max) {
+            self.cells.entry(cell).or_insert_with(Vec::new).push(item);
+        }
+        self.bounds.insert(item, (min, max));
+    }
+
+    /// Takes `item` out of the index. Returns false when it wasn't in there.
+    pub fn remove(&mut self, item: T) -> bool {
+        let (min, max) = match self.bounds.remove(&item) {
+            Some(bounds) => bounds,
+            None => return false,
+        };
+        for cell in self.cells_covering(min, max) {
+            if let Some(items) = self.cells.get_mut(&cell) {
+                items.retain(|other| *other != item);
+                if items.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.bounds.clear();
+    }
+
+    pub fn bounds(&self, item: T) -> Option<(Vec2, Vec2)> {
+        self.bounds.get(&item).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    /// Everything whose rectangle contains `point`.
+    pub fn at_point(&self, point: Vec2) -> Vec<T> {
+        match self.cells.get(&self.cell(point)) {
+            Some(items) => items
+                .iter()
+                .copied()
+                .filter(|item| overlaps(self.bounds[item], (point, point)))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Everything whose rectangle overlaps `min`..`max`.
+    pub fn in_rect(&self, min: Vec2, max: Vec2) -> Vec<T> {
+        // Zoomed far out, the rectangle covers more cells than there are items, and going through the items is quicker.
+        if self.cell_count(min, max) > self.bounds.len() {
+            return self
+                .bounds
+                .iter()
+                .filter(|(_, bounds)| overlaps(**bounds, (min, max)))
+                .map(|(item, _)| *item)
+                .collect();
+        }
+
+        let mut seen = HashSet::new();
+        let mut found = Vec::new();
+        for cell in self.cells_covering(min, max) {
+            if let Some(items) = self.cells.get(&cell) {
+                for item in items {
+                    if seen.insert(*item) && overlaps(self.bounds[item], (min, max)) {
+                        found.push(*item);
+                    }
+                }
+            }
+        }
+        found
+    }
+}
@@ -0,0 +1,195 @@
+//! Colors and sizes used to draw the graph. The canvas and the SVG export both read them from here so that an exported picture looks like the screen.
+//!
+//! Themes are loaded from a RON file (`assets/themes.ron` is built in, and a `themes.ron` in the working directory overrides it). Colors are written as `"#rrggbb"` or `"#rrggbbaa"` strings.
+
+use std::collections::HashMap;
+
+use bevy::render::color::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::diff::Change;
+use crate::model::{EdgeData, EdgeKind, NodeKind};
+
+/// The themes that ship with the editor.
+pub const BUILT_IN_THEMES: &str = include_str!("../assets/themes.ron");
+
+/// How much of its opacity a node left out of the build keeps.
+const DIMMED_ALPHA: f32 = 0.25;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeStyle {
+    #[serde(with = "hex")]
+    pub fill: Color,
+    #[serde(with = "hex")]
+    pub stroke: Color,
+    #[serde(with = "hex")]
+    pub text: Color,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeStyle {
+    #[serde(with = "hex")]
+    pub color: Color,
+    pub width: f32,
+    /// Lengths of the drawn and the skipped part of each dash. Solid when `None`.
+    #[serde(default)]
+    pub dash: Option<[f32; 2]>,
+}
+
+/// The colors of what was added, removed or changed in a diff of two graphs (see `diff`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffColors {
+    #[serde(with = "hex")]
+    pub added: Color,
+    #[serde(with = "hex")]
+    pub removed: Color,
+    #[serde(with = "hex")]
+    pub changed: Color,
+}
+
+impl Default for DiffColors {
+    fn default() -> Self {
+        DiffColors {
+            added: Color::rgb(0.4, 0.73, 0.42),
+            removed: Color::rgb(0.94, 0.33, 0.31),
+            changed: Color::rgb(1.0, 0.93, 0.35),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    /// Whether egui should use its dark visuals.
+    pub dark: bool,
+    /// Width and height of a plain node.
+    pub node_size: f32,
+    #[serde(with = "hex")]
+    pub background: Color,
+    #[serde(with = "hex")]
+    pub grid: Color,
+    /// Highlight for selected and hovered entities.
+    #[serde(with = "hex")]
+    pub selection: Color,
+    #[serde(with = "hex")]
+    pub container_fill: Color,
+    /// A collapsed container stands in for a node, so it is filled in more solidly than an expanded one.
+    #[serde(with = "hex")]
+    pub collapsed_fill: Color,
+    #[serde(with = "hex")]
+    pub container_stroke: Color,
+    #[serde(with = "hex")]
+    pub cursor: Color,
+    /// The cursor while it is over something the current tool can act on.
+    #[serde(with = "hex")]
+    pub cursor_on_target: Color,
+    /// The style of any node kind that doesn't have one in `node_kinds`.
+    pub node: NodeStyle,
+    /// The style of any edge kind that doesn't have one in `edge_kinds`.
+    pub edge: EdgeStyle,
+    #[serde(default)]
+    pub node_kinds: HashMap<NodeKind, NodeStyle>,
+    #[serde(default)]
+    pub edge_kinds: HashMap<EdgeKind, EdgeStyle>,
+    #[serde(default)]
+    pub diff: DiffColors,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            name: "Classic".to_string(),
+            dark: true,
+            node_size: 30.0,
+            background: Color::rgb(0.4, 0.4, 0.4),
+            grid: Color::rgba(1.0, 1.0, 1.0, 0.08),
+            selection: Color::GOLD,
+            container_fill: Color::rgba(0.3, 0.3, 0.8, 0.15),
+            collapsed_fill: Color::rgba(0.3, 0.3, 0.8, 0.9),
+            container_stroke: Color::MIDNIGHT_BLUE,
+            cursor: Color::rgba(0.9, 0.9, 0.9, 0.8),
+            cursor_on_target: Color::GOLD,
+            node: NodeStyle {
+                fill: Color::rgba(0.0, 0.0, 1.0, 0.5),
+                stroke: Color::NAVY,
+                text: Color::WHITE,
+            },
+            edge: EdgeStyle {
+                color: Color::ORANGE_RED,
+                width: 2.0,
+                dash: None,
+            },
+            node_kinds: HashMap::new(),
+            edge_kinds: HashMap::new(),
+            diff: DiffColors::default(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn node_style(&self, kind: NodeKind) -> &NodeStyle {
+        self.node_kinds.get(&kind).unwrap_or(&self.node)
+    }
+
+    pub fn edge_style(&self, kind: EdgeKind) -> &EdgeStyle {
+        self.edge_kinds.get(&kind).unwrap_or(&self.edge)
+    }
+
+    pub fn change_color(&self, change: Change) -> Color {
+        match change {
+            Change::Added => self.diff.added,
+            Change::Removed => self.diff.removed,
+            Change::Changed => self.diff.changed,
+        }
+    }
+
+    /// The color `edge` is drawn in: its kind's, or the one for how it changed if it changed in a diff.
+    pub fn edge_color(&self, edge: &EdgeData) -> Color {
+        Change::of(&edge.properties).map_or(self.edge_style(edge.kind).color, |change| self.change_color(change))
+    }
+
+    /// The body color of a node as it is drawn on the canvas. An expanded container only tints the area behind its children, whatever its kind.
+    pub fn node_body(&self, kind: NodeKind, collapsed: bool) -> Color {
+        match (kind.is_container(), collapsed) {
+            (true, true) => self.node_kinds.get(&kind).map_or(self.collapsed_fill, |style| style.fill),
+            (true, false) => self.container_fill,
+            (false, _) => self.node_style(kind).fill,
+        }
+    }
+}
+
+/// `color` as it is drawn on nodes that are left out of the build (see `model::EXCLUDED_PROPERTY`).
+pub fn dimmed(color: Color) -> Color {
+    let mut dimmed = color;
+    dimmed.set_a(color.a() * DIMMED_ALPHA);
+    dimmed
+}
+
+/// Parses a list of themes in the format of `assets/themes.ron`.
+pub fn load_themes(source: &str) -> Result<Vec<Theme>, ron::Error> {
+    ron::from_str(source)
+}
+
+/// (De)serializes a color as a `"#rrggbbaa"` string.
+mod hex {
+    use bevy::render::color::Color;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        let channel = |value: f32| (value.max(0.0).min(1.0) * 255.0).round() as u8;
+        serializer.serialize_str(&format!(
+            "#{:02x}{:02x}{:02x}{:02x}",
+            channel(color.r()),
+            channel(color.g()),
+            channel(color.b()),
+            channel(color.a())
+        ))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Color::hex(text.trim_start_matches('#'))
+            .map_err(|error| D::Error::custom(format!("invalid color {}: {:?}", text, error)))
+    }
+}
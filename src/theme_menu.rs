@@ -0,0 +1,101 @@
+//! Loads the themes, lets the user switch between them in the Theme window, and restyles the canvas when they do.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::theme::{self, Theme};
+use crate::Graph;
+
+/// Every theme that can be picked. The current one is the `Theme` resource.
+struct Themes(Vec<Theme>);
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let themes = read_themes();
+        let current = themes.first().cloned().unwrap_or_default();
+
+        app.insert_resource(ClearColor(current.background))
+            .insert_resource(current)
+            .insert_resource(Themes(themes))
+            .add_system(theme_menu.system())
+            .add_system(apply_theme.system());
+    }
+}
+
+/// A `themes.ron` in the working directory takes precedence over the built-in themes, so that teams can share their own.
+fn read_themes() -> Vec<Theme> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(source) = std::fs::read_to_string("themes.ron") {
+        match theme::load_themes(&source) {
+            Ok(themes) if !themes.is_empty() => return themes,
+            Ok(_) => warn!("themes.ron doesn't contain any themes, using the built-in ones"),
+            Err(error) => warn!("Couldn't read themes.ron, using the built-in themes: {}", error),
+        }
+    }
+
+    match theme::load_themes(theme::BUILT_IN_THEMES) {
+        Ok(themes) => themes,
+        Err(error) => {
+            error!("The built-in themes are broken: {}", error);
+            vec![Theme::default()]
+        }
+    }
+}
+
+fn theme_menu(egui_context: ResMut<EguiContext>, themes: Res<Themes>, mut theme: ResMut<Theme>) {
+    let mut picked: Option<&Theme> = None;
+
+    egui::Window::new("Theme").show(egui_context.ctx(), |ui| {
+        for candidate in themes.0.iter() {
+            if ui.selectable_label(candidate.name == theme.name, &candidate.name).clicked() {
+                picked = Some(candidate);
+            }
+        }
+    });
+
+    if let Some(picked) = picked {
+        if picked.name != theme.name {
+            *theme = picked.clone();
+        }
+    }
+}
+
+/// Restyles everything when the theme changes. Nodes and edges are simply despawned: with their `bevy_identity` cleared, `draw_graph` draws them again in the new style (and at the new size), and the groups plugin redraws the frames since the graph changed.
+fn apply_theme(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    mut clear_color: ResMut<ClearColor>,
+    egui_context: ResMut<EguiContext>,
+    mut graph: ResMut<Graph>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+
+    clear_color.0 = theme.background;
+    egui_context.ctx().set_visuals(if theme.dark {
+        egui::Visuals::dark()
+    } else {
+        egui::Visuals::light()
+    });
+
+    if theme.is_added() {
+        // Nothing has been drawn yet.
+        return;
+    }
+
+    let nodes: Vec<_> = graph.0.node_indices().collect();
+    for index in nodes {
+        if let Some(entity) = graph.0[index].bevy_identity.take() {
+            commands.entity(entity).despawn();
+        }
+    }
+    let edges: Vec<_> = graph.0.edge_indices().collect();
+    for edge in edges {
+        if let Some(entity) = graph.0[edge].bevy_identity.take() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! The trait implementation view: a node per trait and per implementing type, an `Implements` edge for each `impl Trait for Type`, `Supertrait` edges from traits to their supertraits, and blanket impls (`impl<T: Bound> Trait for T`) as a `Type` node of their own with a `BlanketImpl` edge. Focused on a single trait, it shows that trait with its supertraits, the traits building on it and everything implementing any of those.
+
+use std::collections::HashSet;
+
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use quote::ToTokens;
+
+use crate::model::{EdgeKind, GraphModel, NodeKind};
+use crate::rust_source::{for_each_item, path_name, type_name, DefinedTypes, ParsedFile, ViewBuilder};
+
+pub fn build(files: &[ParsedFile], focus: Option<&str>) -> GraphModel {
+    let mut view = ViewBuilder::default();
+
+    // Types are only shown once something implements a trait for them, but they should show up as what they are. Both they and the traits are told apart by where they are defined.
+    let types = DefinedTypes::new(files);
+
+    for file in files {
+        for_each_item(file, |module, item| match item {
+            syn::Item::Trait(item) => {
+                let name = item.ident.to_string();
+                let index = trait_node(&mut view, &types, file, module, &name);
+                view.locate(index, file.location(&item.ident));
+                for bound in &item.supertraits {
+                    if let syn::TypeParamBound::Trait(bound) = bound {
+                        if let Some(supertrait) = path_name(&bound.path) {
+                            let supertrait = trait_node(&mut view, &types, file, module, &supertrait);
+                            view.edge(index, supertrait, EdgeKind::Supertrait, "");
+                        }
+                    }
+                }
+            }
+            syn::Item::Impl(item) => {
+                let trait_name = match &item.trait_ {
+                    // Negative impls (`impl !Send for T`) say what a type is not, which this view has no way to show.
+                    Some((None, path, _)) => match path_name(path) {
+                        Some(name) => name,
+                        None => return,
+                    },
+                    _ => return,
+                };
+                let trait_index = trait_node(&mut view, &types, file, module, &trait_name);
+
+                match blanket_parameter(item) {
+                    Some(parameter) => {
+                        let label = format!("impl<{}> {} for {}", parameter, trait_name, item.self_ty.to_token_stream());
+                        let key = format!("blanket {}", label);
+                        let index = view.node(&key, &label, NodeKind::Type);
+                        view.locate(index, file.location(&item.self_ty));
+                        view.edge(index, trait_index, EdgeKind::BlanketImpl, "");
+                    }
+                    None => {
+                        let name = type_name(&item.self_ty);
+                        let kind = types.resolve(&file.krate, module, &name).map_or(NodeKind::Type, |definition| definition.kind);
+                        let index = view.node(&types.key(&file.krate, module, &name), &name, kind);
+                        view.locate(index, file.location(&item.self_ty));
+                        view.edge(index, trait_index, EdgeKind::Implements, "");
+                    }
+                }
+            }
+            _ => {}
+        });
+    }
+
+    let mut graph = view.graph;
+    if let Some(focus) = focus {
+        let trait_index = graph
+            .node_indices()
+            .find(|index| graph[*index].kind == NodeKind::Trait && graph[*index].label == focus);
+        let keep = match trait_index {
+            Some(trait_index) => focused(&graph, trait_index),
+            None => HashSet::new(),
+        };
+        graph.retain_nodes(|_, index| keep.contains(&index));
+    }
+    graph
+}
+
+/// The node for the trait `name` as mentioned in `module` of `file`.
+fn trait_node(view: &mut ViewBuilder, types: &DefinedTypes, file: &ParsedFile, module: &[String], name: &str) -> NodeIndex {
+    view.node(&types.trait_key(&file.krate, module, name), name, NodeKind::Trait)
+}
+
+/// The generic parameter with its bounds (`T: Display`) when `item` is a blanket impl, i.e. one for a type that is just one of its own generic parameters (or a reference to one).
+fn blanket_parameter(item: &syn::ItemImpl) -> Option<String> {
+    let mut ty = &*item.self_ty;
+    while let syn::Type::Reference(reference) = ty {
+        ty = &reference.elem;
+    }
+    let ident = match ty {
+        syn::Type::Path(path) if path.qself.is_none() => path.path.get_ident()?,
+        _ => return None,
+    };
+
+    item.generics.type_params().find(|parameter| parameter.ident == *ident).map(|parameter| {
+        let mut bounds: Vec<String> = parameter
+            .bounds
+            .iter()
+            .map(|bound| bound.to_token_stream().to_string())
+            .collect();
+        // Bounds given in the where clause count as much as the ones on the parameter itself.
+        if let Some(clause) = &item.generics.where_clause {
+            for predicate in &clause.predicates {
+                if let syn::WherePredicate::Type(predicate) = predicate {
+                    if *ident == type_name(&predicate.bounded_ty) {
+                        bounds.extend(predicate.bounds.iter().map(|bound| bound.to_token_stream().to_string()));
+                    }
+                }
+            }
+        }
+        if bounds.is_empty() {
+            ident.to_string()
+        } else {
+            format!("{}: {}", ident, bounds.join(" + "))
+        }
+    })
+}
+
+/// `trait_index`, its supertraits, the traits that (through any number of steps) have it as a supertrait, and whatever implements one of the latter.
+fn focused(graph: &GraphModel, trait_index: NodeIndex) -> HashSet<NodeIndex> {
+    let follow = |start: NodeIndex, direction: Direction| {
+        let mut found = HashSet::new();
+        let mut pending = vec![start];
+        while let Some(index) = pending.pop() {
+            if !found.insert(index) {
+                continue;
+            }
+            for edge in graph.edges_directed(index, direction) {
+                if edge.weight().kind == EdgeKind::Supertrait {
+                    pending.push(match direction {
+                        Direction::Outgoing => edge.target(),
+                        Direction::Incoming => edge.source(),
+                    });
+                }
+            }
+        }
+        found
+    };
+
+    let subtraits = follow(trait_index, Direction::Incoming);
+    let mut keep = follow(trait_index, Direction::Outgoing);
+    for subtrait in &subtraits {
+        keep.extend(
+            graph
+                .edges_directed(*subtrait, Direction::Incoming)
+                .filter(|edge| matches!(edge.weight().kind, EdgeKind::Implements | EdgeKind::BlanketImpl))
+                .map(|edge| edge.source()),
+        );
+    }
+    keep.extend(subtraits);
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::visit::IntoEdgeReferences;
+
+    use super::*;
+    use crate::model;
+    use crate::rust_source::parse_sources;
+
+    fn key(graph: &GraphModel, index: NodeIndex) -> &str {
+        &graph[index].properties[model::KEY_PROPERTY]
+    }
+
+    #[test]
+    fn same_named_traits_and_types_keep_their_own_impls() {
+        let files = parse_sources(&[
+            ("app/src/lib.rs", "pub struct Thing; impl Clone for Thing {}"),
+            ("app/src/a.rs", "pub trait Load {} pub struct Config; impl Load for Config {}"),
+            ("app/src/b.rs", "pub trait Load: std::fmt::Debug {} pub enum Config {} impl Load for Config {}"),
+        ]);
+        let graph = build(&files, None);
+
+        let mut keys: Vec<&str> = graph.node_indices().map(|index| key(&graph, index)).collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                "trait Clone",
+                "trait Debug",
+                "trait app::a::Load",
+                "trait app::b::Load",
+                "type app::Thing",
+                "type app::a::Config",
+                "type app::b::Config",
+            ]
+        );
+
+        let mut edges: Vec<(&str, &str, EdgeKind)> = graph
+            .edge_references()
+            .map(|edge| (key(&graph, edge.source()), key(&graph, edge.target()), edge.weight().kind))
+            .collect();
+        edges.sort_by_key(|(source, target, _)| (*source, *target));
+        assert_eq!(
+            edges,
+            vec![
+                ("trait app::b::Load", "trait Debug", EdgeKind::Supertrait),
+                ("type app::Thing", "trait Clone", EdgeKind::Implements),
+                ("type app::a::Config", "trait app::a::Load", EdgeKind::Implements),
+                ("type app::b::Config", "trait app::b::Load", EdgeKind::Implements),
+            ]
+        );
+
+        let kinds: Vec<NodeKind> = ["type app::a::Config", "type app::b::Config"]
+            .iter()
+            .map(|wanted| graph.node_weights().find(|node| node.properties[model::KEY_PROPERTY] == *wanted).unwrap().kind)
+            .collect();
+        assert_eq!(kinds, vec![NodeKind::Struct, NodeKind::Enum]);
+    }
+}
@@ -0,0 +1,303 @@
+//! The type usage view: a node per struct, enum, type alias and function, nested in the containers of the module it is defined in, and a `UsesType` edge to every type it mentions in its fields, variants or signature, labelled with the field, variant or parameter that mentions it. Focused on a single type, it shows that type, the types it uses and whatever uses it.
+//!
+//! With wrappers unwrapped, containers and smart pointers (`Option<Config>`, `ResMut<Graph>`) stand aside for the types they hold, and the generic arguments of every other type are followed as well. Generic parameters of the item itself and the primitive types are always left out.
+
+use std::collections::HashSet;
+
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+
+use crate::model::{self, EdgeKind, GraphModel, NodeKind};
+use crate::rust_source::{for_each_item, type_name, DefinedTypes, ParsedFile, ViewBuilder};
+
+/// Types that mostly say how something else is held, rather than being what is held.
+const WRAPPERS: &[&str] = &[
+    "Option", "Result", "Either", "Vec", "VecDeque", "HashMap", "HashSet", "BTreeMap", "BTreeSet", "Box", "Rc", "Arc",
+    "Weak", "Cell", "RefCell", "Mutex", "RwLock", "Cow", "PhantomData", "Res", "ResMut", "Query", "Local", "Handle",
+    "Mut",
+];
+
+const PRIMITIVES: &[&str] = &[
+    "bool", "char", "str", "String", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128",
+    "isize", "f32", "f64",
+];
+
+/// What a type is mentioned in, for looking up the types it uses.
+struct Scope<'a> {
+    unwrap: bool,
+    /// The generic parameters in scope, which stand for whatever type they are given.
+    generics: HashSet<String>,
+    /// What `Self` stands for, inside of an impl.
+    self_type: Option<&'a str>,
+}
+
+impl Scope<'_> {
+    /// The names of the types `ty` uses, in the order they are written.
+    fn used_types(&self, ty: &syn::Type, found: &mut Vec<String>) {
+        match ty {
+            syn::Type::Path(path) if path.qself.is_none() => {
+                let segment = match path.path.segments.last() {
+                    Some(segment) => segment,
+                    None => return,
+                };
+                let name = segment.ident.to_string();
+                if path.path.segments.len() == 1 && self.generics.contains(&name) {
+                    return;
+                }
+                let name = match (name.as_str(), self.self_type) {
+                    ("Self", Some(self_type)) => self_type.to_string(),
+                    ("Self", None) => return,
+                    _ => name,
+                };
+
+                let wrapper = self.unwrap && WRAPPERS.contains(&name.as_str());
+                if !wrapper && !PRIMITIVES.contains(&name.as_str()) {
+                    found.push(name);
+                }
+                if self.unwrap {
+                    if let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments {
+                        for argument in &arguments.args {
+                            if let syn::GenericArgument::Type(argument) = argument {
+                                self.used_types(argument, found);
+                            }
+                        }
+                    }
+                }
+            }
+            syn::Type::Reference(reference) => self.used_types(&reference.elem, found),
+            syn::Type::Ptr(pointer) => self.used_types(&pointer.elem, found),
+            syn::Type::Slice(slice) => self.used_types(&slice.elem, found),
+            syn::Type::Array(array) => self.used_types(&array.elem, found),
+            syn::Type::Paren(paren) => self.used_types(&paren.elem, found),
+            syn::Type::Group(group) => self.used_types(&group.elem, found),
+            syn::Type::Tuple(tuple) => {
+                for element in &tuple.elems {
+                    self.used_types(element, found);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn generic_names<'a>(generics: impl IntoIterator<Item = &'a syn::Generics>) -> HashSet<String> {
+    generics
+        .into_iter()
+        .flat_map(|generics| generics.type_params())
+        .map(|parameter| parameter.ident.to_string())
+        .collect()
+}
+
+/// The types used by a struct's or a variant's fields, labelled with the field names (or positions, for tuple fields).
+fn field_types(scope: &Scope, fields: &syn::Fields, prefix: &str) -> Vec<(String, String)> {
+    let mut used = Vec::new();
+    for (position, field) in fields.iter().enumerate() {
+        let label = match &field.ident {
+            Some(ident) => format!("{}{}", prefix, ident),
+            None => format!("{}{}", prefix, position),
+        };
+        let mut found = Vec::new();
+        scope.used_types(&field.ty, &mut found);
+        used.extend(found.into_iter().map(|name| (label.clone(), name)));
+    }
+    used
+}
+
+/// The types used by a function's parameters (labelled with their names) and its return type (labelled `->`).
+fn signature_types(scope: &Scope, signature: &syn::Signature) -> Vec<(String, String)> {
+    let mut used = Vec::new();
+    for input in &signature.inputs {
+        if let syn::FnArg::Typed(input) = input {
+            let label = match &*input.pat {
+                syn::Pat::Ident(pattern) => pattern.ident.to_string(),
+                _ => String::from("_"),
+            };
+            let mut found = Vec::new();
+            scope.used_types(&input.ty, &mut found);
+            used.extend(found.into_iter().map(|name| (label.clone(), name)));
+        }
+    }
+    if let syn::ReturnType::Type(_, output) = &signature.output {
+        let mut found = Vec::new();
+        scope.used_types(output, &mut found);
+        used.extend(found.into_iter().map(|name| (String::from("->"), name)));
+    }
+    used
+}
+
+/// The node for the type `name` as mentioned in `module` of `krate`.
+fn type_node(view: &mut ViewBuilder, types: &DefinedTypes, krate: &str, module: &[String], name: &str) -> NodeIndex {
+    view.node(&types.key(krate, module, name), name, NodeKind::Type)
+}
+
+pub fn build(files: &[ParsedFile], focus: Option<&str>, unwrap: bool) -> GraphModel {
+    let mut view = ViewBuilder::default();
+    let types = DefinedTypes::new(files);
+
+    // Types defined in the sources get their node (in their module, and as what they are) before anything refers to them.
+    for file in files {
+        for_each_item(file, |module, item| {
+            let (ident, kind) = match item {
+                syn::Item::Struct(item) => (&item.ident, NodeKind::Struct),
+                syn::Item::Union(item) => (&item.ident, NodeKind::Struct),
+                syn::Item::Enum(item) => (&item.ident, NodeKind::Enum),
+                syn::Item::Type(item) => (&item.ident, NodeKind::Type),
+                _ => return,
+            };
+            let name = ident.to_string();
+            let container = view.module(&file.krate, module);
+            let index = view.node(&types.key(&file.krate, module, &name), &name, kind);
+            model::set_parent(&mut view.graph, index, Some(container));
+            view.locate(index, file.location(ident));
+        });
+    }
+
+    for file in files {
+        for_each_item(file, |module, item| {
+            let uses = |view: &mut ViewBuilder, user: NodeIndex, used: Vec<(String, String)>| {
+                for (label, name) in used {
+                    let index = type_node(view, &types, &file.krate, module, &name);
+                    if index != user {
+                        view.edge(user, index, EdgeKind::UsesType, &label);
+                    }
+                }
+            };
+            let function = |view: &mut ViewBuilder, owner: Option<&str>, ident: &syn::Ident| {
+                let name = ident.to_string();
+                let mut path = module.to_vec();
+                path.extend(owner.map(str::to_string));
+                path.push(name.clone());
+                let label = match owner {
+                    Some(owner) => format!("{}::{}", owner, name),
+                    None => name,
+                };
+                let container = view.module(&file.krate, module);
+                let index = view.node(&format!("fn {}::{}", file.krate, path.join("::")), &label, NodeKind::Function);
+                model::set_parent(&mut view.graph, index, Some(container));
+                view.locate(index, file.location(ident));
+                index
+            };
+
+            match item {
+                syn::Item::Struct(item) => {
+                    let scope = Scope {
+                        unwrap,
+                        generics: generic_names(Some(&item.generics)),
+                        self_type: None,
+                    };
+                    let user = type_node(&mut view, &types, &file.krate, module, &item.ident.to_string());
+                    uses(&mut view, user, field_types(&scope, &item.fields, ""));
+                }
+                syn::Item::Enum(item) => {
+                    let scope = Scope {
+                        unwrap,
+                        generics: generic_names(Some(&item.generics)),
+                        self_type: None,
+                    };
+                    let user = type_node(&mut view, &types, &file.krate, module, &item.ident.to_string());
+                    for variant in &item.variants {
+                        let prefix = format!("{}.", variant.ident);
+                        uses(&mut view, user, field_types(&scope, &variant.fields, &prefix));
+                    }
+                }
+                syn::Item::Type(item) => {
+                    let scope = Scope {
+                        unwrap,
+                        generics: generic_names(Some(&item.generics)),
+                        self_type: None,
+                    };
+                    let user = type_node(&mut view, &types, &file.krate, module, &item.ident.to_string());
+                    let mut found = Vec::new();
+                    scope.used_types(&item.ty, &mut found);
+                    uses(&mut view, user, found.into_iter().map(|name| (String::from("="), name)).collect());
+                }
+                syn::Item::Fn(item) => {
+                    let scope = Scope {
+                        unwrap,
+                        generics: generic_names(Some(&item.sig.generics)),
+                        self_type: None,
+                    };
+                    let user = function(&mut view, None, &item.sig.ident);
+                    uses(&mut view, user, signature_types(&scope, &item.sig));
+                }
+                syn::Item::Impl(item) => {
+                    let owner = type_name(&item.self_ty);
+                    for impl_item in &item.items {
+                        if let syn::ImplItem::Method(method) = impl_item {
+                            let scope = Scope {
+                                unwrap,
+                                generics: generic_names(vec![&item.generics, &method.sig.generics]),
+                                self_type: Some(&owner),
+                            };
+                            let user = function(&mut view, Some(&owner), &method.sig.ident);
+                            uses(&mut view, user, signature_types(&scope, &method.sig));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        });
+    }
+
+    let mut graph = view.graph;
+    if let Some(focus) = focus {
+        let keep = match graph
+            .node_indices()
+            .find(|index| !graph[*index].kind.is_container() && graph[*index].label == focus)
+        {
+            Some(start) => focused(&graph, start),
+            None => HashSet::new(),
+        };
+        graph.retain_nodes(|_, index| keep.contains(&index));
+    }
+    graph
+}
+
+/// `start`, the types it uses, whatever uses it, and the modules all of those are in.
+fn focused(graph: &GraphModel, start: NodeIndex) -> HashSet<NodeIndex> {
+    let mut keep: HashSet<NodeIndex> = graph
+        .edges_directed(start, Direction::Outgoing)
+        .map(|edge| edge.target())
+        .chain(graph.edges_directed(start, Direction::Incoming).map(|edge| edge.source()))
+        .collect();
+    keep.insert(start);
+
+    let containers: Vec<NodeIndex> = keep.iter().flat_map(|index| model::ancestors(graph, *index)).collect();
+    keep.extend(containers);
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::visit::IntoEdgeReferences;
+
+    use super::*;
+    use crate::rust_source::parse_sources;
+
+    fn key(graph: &GraphModel, index: NodeIndex) -> &str {
+        &graph[index].properties[model::KEY_PROPERTY]
+    }
+
+    #[test]
+    fn uses_are_of_the_type_defined_in_scope() {
+        let files = parse_sources(&[
+            ("app/src/a.rs", "pub struct Config; pub struct Window { config: Config, title: Title }"),
+            ("app/src/b.rs", "pub struct Config; pub fn load() -> Config { Config }"),
+        ]);
+        let graph = build(&files, None, true);
+        let mut used: Vec<(&str, &str)> = graph
+            .edge_references()
+            .map(|edge| (key(&graph, edge.source()), key(&graph, edge.target())))
+            .collect();
+        used.sort();
+        assert_eq!(
+            used,
+            vec![
+                ("fn app::b::load", "type app::b::Config"),
+                ("type app::a::Window", "type Title"),
+                ("type app::a::Window", "type app::a::Config"),
+            ]
+        );
+    }
+}
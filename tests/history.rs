@@ -0,0 +1,164 @@
+//! The history of a project is read from a real git repository, made up for each test in the temporary directory.
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use visual_programming_environment::history;
+use visual_programming_environment::model::GraphModel;
+use visual_programming_environment::rust_source::{SourceView, ViewOptions};
+
+/// A git repository in the temporary directory, removed again when dropped.
+struct Repo(PathBuf);
+
+impl Repo {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("vpe-history-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        let repo = Repo(path);
+        repo.git(&["init", "-q"]);
+        repo
+    }
+
+    fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.0)
+            .args(&["-c", "user.name=Test", "-c", "user.email=test@example.com", "-c", "commit.gpgsign=false"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn write(&self, path: &str, text: &str) {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    fn remove(&self, path: &str) {
+        fs::remove_file(self.0.join(path)).unwrap();
+    }
+
+    fn commit(&self, summary: &str) -> String {
+        self.git(&["add", "-A"]);
+        self.git(&["commit", "-q", "-m", summary]);
+        self.git(&["rev-parse", "HEAD"])
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Repo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Three commits: a trait with one implementation, a second implementation, and the first one deleted along with a submodule added whose commit isn't in the repository. Hidden directories, build output and other files come along from the start.
+fn project(name: &str) -> (Repo, Vec<String>) {
+    let repo = Repo::new(name);
+    repo.write("src/lib.rs", "pub mod shapes;\npub trait Shape {}\n");
+    repo.write("src/shapes.rs", "pub struct Circle;\nimpl crate::Shape for Circle {}\n");
+    repo.write(".hidden/hidden.rs", "pub struct Hidden;\n");
+    repo.write("target/debug/build.rs", "pub struct Built;\n");
+    repo.write("notes.txt", "not rust\n");
+    let first = repo.commit("Add circles");
+
+    repo.write("src/lib.rs", "pub mod color;\npub mod shapes;\npub trait Shape {}\n");
+    repo.write("src/color.rs", "pub enum Red {}\nimpl crate::Shape for Red {}\n");
+    let second = repo.commit("Add red");
+
+    repo.write("src/lib.rs", "pub mod color;\npub trait Shape {}\n");
+    repo.remove("src/shapes.rs");
+    repo.git(&["add", "-A"]);
+    // A submodule only records the id of its commit, which needn't be anywhere in the repository.
+    let submodule = "160000,1234567890123456789012345678901234567890,vendored.rs";
+    repo.git(&["update-index", "--add", "--cacheinfo", submodule]);
+    repo.git(&["commit", "-q", "-m", "Drop circles"]);
+    let third = repo.git(&["rev-parse", "HEAD"]);
+
+    (repo, vec![first, second, third])
+}
+
+fn paths(repo: &Repo, commit: &str) -> Vec<String> {
+    let mut paths: Vec<String> = history::sources_at(repo.path(), commit)
+        .unwrap()
+        .into_iter()
+        .map(|file| file.path)
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn position(graph: &GraphModel, label: &str) -> Option<(f32, f32)> {
+    graph.node_weights().find(|node| node.label == label)?.position
+}
+
+#[test]
+fn log_is_oldest_first() {
+    let (repo, ids) = project("log");
+
+    let commits = history::log(repo.path(), 10).unwrap();
+    let summaries: Vec<&str> = commits.iter().map(|commit| commit.summary.as_str()).collect();
+    assert_eq!(summaries, vec!["Add circles", "Add red", "Drop circles"]);
+    let found: Vec<&String> = commits.iter().map(|commit| &commit.id).collect();
+    assert_eq!(found, ids.iter().collect::<Vec<_>>());
+    assert!(commits.iter().all(|commit| commit.date.len() == "yyyy-mm-dd".len()));
+
+    // A limit keeps the latest commits.
+    let latest = history::log(repo.path(), 2).unwrap();
+    assert_eq!(latest, commits[1..].to_vec());
+}
+
+#[test]
+fn sources_leave_out_hidden_and_build_directories() {
+    let (repo, ids) = project("sources");
+
+    assert_eq!(paths(&repo, &ids[0]), vec!["src/lib.rs", "src/shapes.rs"]);
+    assert_eq!(paths(&repo, &ids[1]), vec!["src/color.rs", "src/lib.rs", "src/shapes.rs"]);
+
+    let files = history::sources_at(repo.path(), &ids[1]).unwrap();
+    let lib = files.iter().find(|file| file.path == "src/lib.rs").unwrap();
+    assert_eq!(lib.text, "pub mod color;\npub mod shapes;\npub trait Shape {}\n");
+}
+
+#[test]
+fn sources_skip_what_isnt_in_the_repository() {
+    let (repo, ids) = project("missing");
+
+    // The deleted file is gone, and the submodule's commit isn't there to be read.
+    assert_eq!(paths(&repo, &ids[2]), vec!["src/color.rs", "src/lib.rs"]);
+    assert!(history::sources_at(repo.path(), "no-such-commit").is_err());
+}
+
+#[test]
+fn timeline_keeps_nodes_in_place() {
+    let (repo, _) = project("timeline");
+    let commits = history::log(repo.path(), 10).unwrap();
+
+    let timeline =
+        history::timeline(repo.path(), commits.clone(), SourceView::Traits, &ViewOptions::default(), 40.0).unwrap();
+    assert_eq!(timeline.commits, commits);
+    assert_eq!(timeline.graphs.len(), 3);
+
+    let shape = position(&timeline.graphs[0], "Shape");
+    assert!(shape.is_some());
+    assert!(timeline.graphs.iter().all(|graph| position(graph, "Shape") == shape));
+
+    let circle = position(&timeline.graphs[0], "Circle");
+    assert!(circle.is_some());
+    assert_eq!(position(&timeline.graphs[1], "Circle"), circle);
+    assert!(timeline.graphs[2].node_weights().all(|node| node.label != "Circle"));
+
+    let red = position(&timeline.graphs[1], "Red");
+    assert!(red.is_some());
+    assert_eq!(position(&timeline.graphs[2], "Red"), red);
+    assert_ne!(red, circle);
+}
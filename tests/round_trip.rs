@@ -0,0 +1,160 @@
+//! Exporting a graph and importing it back should give the same graph, as far as the format can carry it.
+
+use std::collections::HashMap;
+
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+
+use visual_programming_environment::export;
+use visual_programming_environment::import;
+use visual_programming_environment::model::{self, EdgeData, EdgeKind, GraphModel, NodeData, NodeKind};
+
+/// A crate holding a module holding a struct, a trait next to the module, and a free-standing node, with two parallel edges and a self-loop.
+fn sample() -> GraphModel {
+    let mut graph = GraphModel::default();
+    let krate = graph.add_node(NodeData::new("app", NodeKind::Crate).at(0.0, 0.0));
+    let module = graph.add_node(NodeData::new("app::shapes", NodeKind::Module).at(10.0, -20.5));
+    let mut circle = NodeData::new("Circle", NodeKind::Struct).at(12.25, -30.0);
+    circle.properties.insert(String::from("source"), String::from("src/shapes.rs:3"));
+    let circle = graph.add_node(circle);
+    let shape = graph.add_node(NodeData::new("Shape \"trait\"", NodeKind::Trait).at(-40.0, 8.0));
+    let loose = graph.add_node(NodeData::new("loose", NodeKind::Item).at(100.0, 100.0));
+    assert!(model::set_parent(&mut graph, module, Some(krate)));
+    assert!(model::set_parent(&mut graph, circle, Some(module)));
+    assert!(model::set_parent(&mut graph, shape, Some(krate)));
+
+    let mut implements = EdgeData::new(EdgeKind::Implements);
+    implements.label = String::from("impl");
+    implements.properties.insert(String::from("weight"), String::from("2"));
+    graph.add_edge(circle, shape, implements);
+    graph.add_edge(circle, shape, EdgeData::new(EdgeKind::UsesType));
+    let mut to_itself = EdgeData::new(EdgeKind::Calls);
+    to_itself.label = String::from("recurses");
+    graph.add_edge(loose, loose, to_itself);
+    graph.add_edge(loose, krate, EdgeData::new(EdgeKind::Link));
+    graph
+}
+
+fn by_label(graph: &GraphModel) -> HashMap<String, NodeIndex> {
+    graph.node_indices().map(|index| (graph[index].label.clone(), index)).collect()
+}
+
+fn parent_label(graph: &GraphModel, index: NodeIndex) -> Option<String> {
+    graph[index].parent().map(|parent| graph[parent].label.clone())
+}
+
+/// Every edge as its endpoints' labels and its own label, sorted, so that parallel edges count separately.
+fn edges(graph: &GraphModel) -> Vec<(String, String, String)> {
+    let mut edges: Vec<(String, String, String)> = graph
+        .edge_references()
+        .map(|edge| {
+            (
+                graph[edge.source()].label.clone(),
+                graph[edge.target()].label.clone(),
+                edge.weight().label.clone(),
+            )
+        })
+        .collect();
+    edges.sort();
+    edges
+}
+
+/// The labels, kinds, nesting and edges of `imported` are those of `original`.
+fn assert_same_structure(original: &GraphModel, imported: &GraphModel) {
+    let nodes = by_label(imported);
+    assert_eq!(nodes.len(), original.node_count());
+    for index in original.node_indices() {
+        let node = &original[index];
+        let imported_index = nodes[&node.label];
+        assert_eq!(imported[imported_index].kind, node.kind, "kind of {}", node.label);
+        assert_eq!(
+            parent_label(imported, imported_index),
+            parent_label(original, index),
+            "parent of {}",
+            node.label
+        );
+    }
+    assert_eq!(edges(imported), edges(original));
+}
+
+#[test]
+fn mermaid_round_trip() {
+    let original = sample();
+    let imported = import::import_mermaid(&export::to_mermaid(&original)).unwrap();
+    assert_same_structure(&original, &imported);
+}
+
+#[test]
+fn dot_round_trip() {
+    let original = sample();
+    let imported = import::import_dot(&export::to_dot(&original)).unwrap();
+    assert_same_structure(&original, &imported);
+
+    // DOT carries everything else about the nodes and edges as well.
+    let nodes = by_label(&imported);
+    for node in original.node_weights() {
+        let imported_node = &imported[nodes[&node.label]];
+        assert_eq!(imported_node.id, node.id, "id of {}", node.label);
+        assert_eq!(imported_node.position, node.position, "position of {}", node.label);
+        assert_eq!(imported_node.properties, node.properties, "properties of {}", node.label);
+    }
+    let mut original_edges: Vec<_> = original.edge_weights().map(|edge| (edge.id, edge.kind, edge.properties.clone())).collect();
+    let mut imported_edges: Vec<_> = imported.edge_weights().map(|edge| (edge.id, edge.kind, edge.properties.clone())).collect();
+    original_edges.sort_by_key(|(id, ..)| *id);
+    imported_edges.sort_by_key(|(id, ..)| *id);
+    assert_eq!(imported_edges, original_edges);
+}
+
+#[test]
+fn dot_plain_subgraph_doesnt_describe_its_cluster() {
+    let source = r#"digraph {
+        subgraph cluster_a {
+            label="Outer";
+            b;
+            subgraph {
+                label="Inner";
+                node [color=red];
+                c;
+            }
+        }
+    }"#;
+    let graph = import::import_dot(source).unwrap();
+    let nodes = by_label(&graph);
+    let container = nodes["Outer"];
+    assert!(!nodes.contains_key("Inner"));
+    assert!(graph[container].kind.is_container());
+    assert_eq!(graph[nodes["b"]].parent(), Some(container));
+    assert_eq!(graph[nodes["c"]].parent(), Some(container));
+    assert_eq!(graph[nodes["c"]].properties.get("color").map(String::as_str), Some("red"));
+}
+
+#[test]
+fn dot_shape_round_trip() {
+    let source = r#"digraph {
+        box [shape=box];
+        folder [shape=folder];
+        subgraph cluster_group {
+            label="Group";
+            group [shape=folder];
+            inside;
+        }
+    }"#;
+    let graph = import::import_dot(source).unwrap();
+    let nodes = by_label(&graph);
+    assert_eq!(graph[nodes["box"]].properties.get("shape").map(String::as_str), Some("box"));
+    // Folders are how containers are drawn, and are read as one rather than kept.
+    for container in &["folder", "Group"] {
+        let node = &graph[nodes[*container]];
+        assert!(node.kind.is_container(), "{} is a container", container);
+        assert!(!node.properties.contains_key("shape"), "{} has no shape", container);
+    }
+
+    let exported = export::to_dot(&graph);
+    let reimported = import::import_dot(&exported).unwrap();
+    let nodes = by_label(&reimported);
+    assert_eq!(reimported[nodes["box"]].properties.get("shape").map(String::as_str), Some("box"));
+    assert!(!reimported[nodes["Group"]].properties.contains_key("shape"));
+    for line in exported.lines() {
+        assert!(line.matches("shape=").count() <= 1, "shape written twice in {}", line);
+    }
+}
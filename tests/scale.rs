@@ -0,0 +1,64 @@
+//! The model and geometry have to keep up with large graphs, since the canvas goes back to them whenever the graph changes.
+
+use std::time::{Duration, Instant};
+
+use petgraph::stable_graph::NodeIndex;
+
+use visual_programming_environment::geometry;
+use visual_programming_environment::model::{self, EdgeData, EdgeKind, GraphModel, NodeData, NodeKind};
+
+/// Far more than either takes, but far less than a pass over the whole graph per node would.
+const BUDGET: Duration = Duration::from_secs(2);
+
+/// 10 crates of 10 modules of 100 items each, every item calling the next one.
+fn large_graph() -> GraphModel {
+    let mut graph = GraphModel::default();
+    let mut items: Vec<NodeIndex> = Vec::new();
+    for krate in 0..10 {
+        let krate_index = graph.add_node(NodeData::new(format!("crate{}", krate), NodeKind::Crate));
+        for module in 0..10 {
+            let module_index = graph.add_node(NodeData::new(format!("module{}", module), NodeKind::Module));
+            model::set_parent(&mut graph, module_index, Some(krate_index));
+            for item in 0..100 {
+                let position = ((krate * 1000 + item) as f32 * 10.0, module as f32 * 100.0);
+                let index = graph.add_node(NodeData::new(format!("item{}", item), NodeKind::Struct).at(position.0, position.1));
+                model::set_parent(&mut graph, index, Some(module_index));
+                items.push(index);
+            }
+        }
+    }
+    for pair in items.windows(2) {
+        graph.add_edge(pair[0], pair[1], EdgeData::new(EdgeKind::Calls));
+    }
+    graph
+}
+
+#[test]
+fn extent_of_every_node() {
+    let graph = large_graph();
+    assert!(graph.node_count() > 10_000);
+
+    let start = Instant::now();
+    let placed = graph
+        .node_indices()
+        .filter(|index| geometry::extent(&graph, *index, 40.0).is_some())
+        .count();
+    let elapsed = start.elapsed();
+
+    assert_eq!(placed, graph.node_count());
+    assert!(elapsed < BUDGET, "extent of every node took {:?}", elapsed);
+}
+
+#[test]
+fn aggregated_edges_of_collapsed_modules() {
+    let graph = large_graph();
+
+    let start = Instant::now();
+    let aggregated = model::aggregated_edges_with(&graph, |container| graph[container].kind == NodeKind::Module, |_| true);
+    let elapsed = start.elapsed();
+
+    // Only the calls from the last item of one module to the first of the next leave a module.
+    assert_eq!(aggregated.len(), 99);
+    assert!(aggregated.iter().all(|edge| edge.count == 1));
+    assert!(elapsed < BUDGET, "aggregating the edges took {:?}", elapsed);
+}